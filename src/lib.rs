@@ -6,12 +6,70 @@ pub mod music {
     mod note;
     mod serializable;
     mod song_editor;
+    mod bus;
+    mod effect;
+    mod filter;
+    mod envelope;
+    mod fm;
+    mod instrument;
+    mod wavetable;
+    mod oscillator;
+    mod lfo;
+    mod pitch;
+    mod random;
+    mod drums;
+    mod step_pattern;
+    mod notation;
+    mod signature;
+    mod score;
+    mod abc;
+    mod musicxml;
+    mod lilypond;
+    mod xml;
+    mod zip;
+    mod transform;
+    mod selection;
+    mod clipboard;
+    mod pattern;
+    mod structure;
+    mod theory;
+    mod progression;
+    mod arpeggiator;
+    mod analysis;
+    mod part_writing;
 
     pub use song::Song as Song;
     pub use part::Part as Part;
-    pub use note::Note as Note;
+    pub use note::{BendPoint, Note};
     pub use serializable::Serializable as Serializable;
     pub use song_editor::SongEditor as SongEditor;
+    pub use bus::{Bus, BusSend};
+    pub use effect::Effect as Effect;
+    pub use envelope::Envelope as Envelope;
+    pub use fm::{FmSynth, Operator};
+    pub use instrument::{Instrument, Preset};
+    pub use wavetable::{Frame, WavetableSynth};
+    pub use oscillator::Waveform as Waveform;
+    pub use lfo::{Lfo, LfoShape, LfoTarget};
+    pub use filter::PartFilter as PartFilter;
+    pub use drums::DrumVoice as DrumVoice;
+    pub use pitch::{frequency_from_midi, frequency_from_name, midi_from_frequency, midi_from_name, name_from_frequency, name_from_midi};
+    pub use step_pattern::{Step, StepPattern};
+    pub use notation::{melody_to_text, parse_melody};
+    pub use signature::{Key, TimeSignature};
+    pub use abc::{song_from_abc, song_to_abc};
+    pub use musicxml::{song_from_musicxml, song_from_mxl, song_to_musicxml};
+    pub use lilypond::song_to_lilypond;
+    pub use transform::{interval_semitones, Transform};
+    pub use selection::Selection;
+    pub use clipboard::Clipboard;
+    pub use pattern::{Pattern, PatternInstance};
+    pub use structure::Marker;
+    pub use theory::{Chord, ChordQuality, Scale, CHORD_QUALITIES, NAMED_SCALES};
+    pub use progression::{parse_progression, voice_lead, Accompaniment, AccompanimentStyle};
+    pub use arpeggiator::{ArpeggioMode, Arpeggiator, MAX_ARPEGGIO_OCTAVES};
+    pub use analysis::Analysis;
+    pub use part_writing::{PartWritingIssue, PartWritingRule, Voice};
 }
 
 pub mod ui {
@@ -19,6 +77,11 @@ pub mod ui {
     mod part_cli;
     mod song_cli;
     mod pitch_ui;
+    mod bus_cli;
+    mod input_ui;
+    mod fm_cli;
+    mod wavetable_cli;
+    mod transform_ui;
     pub mod choice_ui;
     pub mod song_editor_cli;
 }
\ No newline at end of file
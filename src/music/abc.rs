@@ -0,0 +1,759 @@
+use std::collections::HashMap;
+
+use super::note::Note;
+use super::part::Part;
+use super::pitch::{frequency_from_midi, read_pitch_letter};
+use super::score;
+use super::signature::{signature_alter, Key, TimeSignature};
+use super::song::Song;
+
+/// Volume of imported notes until a dynamic marking says otherwise
+const DEFAULT_VOLUME: f32 = 0.5;
+/// Exported tunes count lengths in eighth notes
+const EXPORT_UNIT_BEATS: f32 = 0.5;
+const BARS_PER_LINE: usize = 4;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Bar {
+    Plain,
+    // `||` and `|]` end a repeat that has endings
+    Double,
+    RepeatStart,
+    RepeatEnd,
+    // `[1` and `[2` first and second time endings
+    Ending(u8),
+}
+
+#[derive(Clone)]
+enum Element {
+    Notes { pitches: Vec<u8>, beats: f32, tie: bool, volume: f32 },
+    Rest { beats: f32 },
+    Bar(Bar),
+}
+
+struct Voice {
+    id: String,
+    name: Option<String>,
+    elements: Vec<Element>,
+}
+
+/// State of the tune while its body is read
+struct AbcReader {
+    title: Option<String>,
+    time_signature: TimeSignature,
+    unit_beats: Option<f32>,
+    bpm: f32,
+    key: Key,
+    // sharps of the key signature, kept apart from `key` because keys with 6 or more flats or sharps are ambiguous there
+    fifths: i8,
+    voices: Vec<Voice>,
+    voice: usize,
+    // explicit accidentals that last until the end of the bar by letter and octave
+    accidentals: HashMap<(char, i32), i8>,
+    // length factor of the next notes of a tuplet and how many notes it lasts for
+    tuplet: Option<(f32, usize)>,
+    // length factor the second note of a broken rhythm like `a>b` gets
+    broken: f32,
+    volume: f32,
+}
+
+/// Reads the first tune of an ABC file into a song with one part per voice.
+/// Reads the title, meter, unit note length, tempo and key fields and notes, rests, chords, ties, tuplets,
+/// broken rhythms, dynamics, bar lines, repeats and first and second endings. Everything else is skipped
+pub fn song_from_abc(text: &str) -> Result<Song, &'static str> {
+    let mut reader = AbcReader {
+        title: None,
+        time_signature: TimeSignature::default(),
+        unit_beats: None,
+        bpm: 120.0,
+        key: Key::default(),
+        fifths: 0,
+        voices: Vec::new(),
+        voice: 0,
+        accidentals: HashMap::new(),
+        tuplet: None,
+        broken: 1.0,
+        volume: DEFAULT_VOLUME,
+    };
+    let mut in_header = true;
+    let mut found_tune = false;
+    for line in text.lines() {
+        // a blank line ends the tune once its music has started
+        if line.trim().is_empty() && reader.voices.iter().any(|voice| !voice.elements.is_empty()) {
+            break;
+        }
+        let line = line.split('%').next().unwrap_or("").trim_end();
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut chars = line.chars();
+        let is_field = matches!((chars.next(), chars.next()), (Some(letter), Some(':')) if letter.is_ascii_alphabetic());
+        if is_field {
+            let (field, value) = line.split_at(2);
+            let field = field.chars().next().unwrap_or(' ');
+            if field == 'X' {
+                if found_tune {
+                    break;
+                }
+                found_tune = true;
+                continue;
+            }
+            reader.read_field(field, value.trim())?;
+            if field == 'K' {
+                in_header = false;
+            }
+            continue;
+        }
+        if in_header {
+            continue;
+        }
+        reader.read_music(line)?;
+    }
+    if in_header {
+        return Err("The ABC tune has no K: field so it has no body");
+    }
+    reader.into_song()
+}
+
+impl AbcReader {
+    fn read_field(&mut self, field: char, value: &str) -> Result<(), &'static str> {
+        match field {
+            'T' if self.title.is_none() => self.title = Some(value.to_string()),
+            'M' => {
+                if let Some(time_signature) = parse_meter(value) {
+                    self.time_signature = time_signature;
+                }
+            }
+            'L' => self.unit_beats = Some(parse_fraction(value).ok_or("Invalid unit note length in ABC tune")? * 4.0),
+            'Q' => {
+                if let Some(bpm) = parse_tempo(value) {
+                    self.bpm = bpm;
+                }
+            }
+            'K' => {
+                let (key, fifths) = parse_key(value).ok_or("Invalid key in ABC tune")?;
+                self.key = key;
+                self.fifths = fifths;
+            }
+            'V' => {
+                let id = value.split_whitespace().next().unwrap_or("1").to_string();
+                let name = quoted_value(value, "name=").or_else(|| quoted_value(value, "nm="));
+                self.voice = match self.voices.iter().position(|voice| voice.id == id) {
+                    Some(index) => index,
+                    None => {
+                        self.voices.push(Voice { id, name: None, elements: Vec::new() });
+                        self.voices.len() - 1
+                    }
+                };
+                if name.is_some() {
+                    self.voices[self.voice].name = name;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Length of the unit note in beats, which defaults to an eighth note or a sixteenth note for short meters
+    fn unit_beats(&self) -> f32 {
+        self.unit_beats.unwrap_or(if (self.time_signature.beats as f32 / self.time_signature.beat_unit as f32) < 0.75 { 0.25 } else { 0.5 })
+    }
+
+    fn push(&mut self, element: Element) {
+        if self.voices.is_empty() {
+            self.voices.push(Voice { id: "1".to_string(), name: None, elements: Vec::new() });
+        }
+        self.voices[self.voice].elements.push(element);
+    }
+
+    /// Gets the length of the next note or rest from its length factor, applying tuplets and broken rhythms
+    fn note_beats(&mut self, factor: f32) -> f32 {
+        let mut beats = self.unit_beats() * factor * self.broken;
+        self.broken = 1.0;
+        if let Some((ratio, remaining)) = self.tuplet {
+            beats *= ratio;
+            self.tuplet = if remaining > 1 { Some((ratio, remaining - 1)) } else { None };
+        }
+        beats
+    }
+
+    fn read_music(&mut self, line: &str) -> Result<(), &'static str> {
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                '"' => i = skip_past(&chars, i + 1, '"'),
+                '!' | '+' => {
+                    let end = skip_past(&chars, i + 1, c);
+                    let decoration: String = chars[i + 1..end.saturating_sub(1).max(i + 1)].iter().collect();
+                    if let Some(volume) = score::volume_from_dynamic(&decoration) {
+                        self.volume = volume;
+                    }
+                    i = end;
+                }
+                '{' => i = skip_past(&chars, i + 1, '}'),
+                '(' if chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) => i = self.read_tuplet(&chars, i + 1),
+                '[' if chars.get(i + 2) == Some(&':') => {
+                    let end = skip_past(&chars, i + 1, ']');
+                    let field: String = chars[i + 1..end.saturating_sub(1).max(i + 1)].iter().collect();
+                    if let Some((name, value)) = field.split_once(':') {
+                        self.read_field(name.chars().next().unwrap_or(' '), value.trim())?;
+                    }
+                    i = end;
+                }
+                '[' if chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) => {
+                    let (number, end) = read_number(&chars, i + 1);
+                    self.push(Element::Bar(Bar::Ending(number.unwrap_or(1) as u8)));
+                    i = skip_ending_list(&chars, end);
+                }
+                '[' if chars.get(i + 1) == Some(&'|') => i = self.read_bar(&chars, i),
+                '[' => i = self.read_chord(&chars, i + 1)?,
+                '|' | ':' => i = self.read_bar(&chars, i),
+                '^' | '_' | '=' | 'A'..='G' | 'a'..='g' => {
+                    let (pitch, end) = self.read_pitch(&chars, i)?;
+                    let (factor, end) = read_length(&chars, end);
+                    let beats = self.note_beats(factor);
+                    let (tie, end) = read_tie(&chars, end);
+                    self.push(Element::Notes { pitches: vec![pitch], beats, tie, volume: self.volume });
+                    i = end;
+                }
+                'z' | 'x' => {
+                    let (factor, end) = read_length(&chars, i + 1);
+                    let beats = self.note_beats(factor);
+                    self.push(Element::Rest { beats });
+                    i = end;
+                }
+                'Z' | 'X' => {
+                    let (bars, end) = read_number(&chars, i + 1);
+                    self.push(Element::Rest { beats: bars.unwrap_or(1) as f32 * self.time_signature.bar_beats() });
+                    i = end;
+                }
+                '>' | '<' => {
+                    let mut count = 0;
+                    while chars.get(i) == Some(&c) {
+                        count += 1;
+                        i += 1;
+                    }
+                    let short = 0.5_f32.powi(count);
+                    let (first, second) = if c == '>' { (2.0 - short, short) } else { (short, 2.0 - short) };
+                    if let Some(Element::Notes { beats, .. } | Element::Rest { beats }) = self.voices.get_mut(self.voice).and_then(|voice| voice.elements.last_mut()) {
+                        *beats *= first;
+                    }
+                    self.broken = second;
+                }
+                '-' => {
+                    if let Some(Element::Notes { tie, .. }) = self.voices.get_mut(self.voice).and_then(|voice| voice.elements.last_mut()) {
+                        *tie = true;
+                    }
+                    i += 1;
+                }
+                // spaces, slurs, line continuations and single character decorations
+                _ => i += 1,
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads `(p:q:r`, meaning the next r notes take the time of q
+    fn read_tuplet(&mut self, chars: &[char], start: usize) -> usize {
+        let (p, mut end) = read_number(chars, start);
+        let p = p.unwrap_or(3).max(1);
+        let mut q = None;
+        let mut r = None;
+        if chars.get(end) == Some(&':') {
+            let (number, next) = read_number(chars, end + 1);
+            q = number;
+            end = next;
+            if chars.get(end) == Some(&':') {
+                let (number, next) = read_number(chars, end + 1);
+                r = number;
+                end = next;
+            }
+        }
+        let q = q.unwrap_or(match p {
+            2 | 4 | 8 => 3,
+            _ => 2,
+        });
+        self.tuplet = Some((q as f32 / p as f32, r.unwrap_or(p) as usize));
+        end
+    }
+
+    fn read_bar(&mut self, chars: &[char], start: usize) -> usize {
+        let mut end = start;
+        while end < chars.len() && matches!(chars[end], '|' | ':' | ']' | '[') {
+            // a `[` that starts an ending or a chord after the bar line isn't part of it
+            if chars[end] == '[' && end != start {
+                break;
+            }
+            end += 1;
+        }
+        let bar: String = chars[start..end].iter().collect();
+        let bars: Vec<Bar> = if bar.starts_with(':') && bar.ends_with(':') && bar.len() > 1 {
+            vec![Bar::RepeatEnd, Bar::RepeatStart]
+        } else if bar.starts_with(':') {
+            vec![Bar::RepeatEnd]
+        } else if bar.ends_with(':') {
+            vec![Bar::RepeatStart]
+        } else if bar == "||" || bar == "|]" || bar == "[|" {
+            vec![Bar::Double]
+        } else {
+            vec![Bar::Plain]
+        };
+        for bar in bars {
+            self.push(Element::Bar(bar));
+        }
+        self.accidentals.clear();
+        if chars.get(end).is_some_and(|c| c.is_ascii_digit()) {
+            let (number, next) = read_number(chars, end);
+            self.push(Element::Bar(Bar::Ending(number.unwrap_or(1) as u8)));
+            end = skip_ending_list(chars, next);
+        }
+        end
+    }
+
+    fn read_chord(&mut self, chars: &[char], start: usize) -> Result<usize, &'static str> {
+        let mut i = start;
+        let mut pitches = Vec::new();
+        let mut factor = None;
+        let mut tie = false;
+        while i < chars.len() && chars[i] != ']' {
+            match chars[i] {
+                '^' | '_' | '=' | 'A'..='G' | 'a'..='g' => {
+                    let (pitch, end) = self.read_pitch(chars, i)?;
+                    let (note_factor, end) = read_length(chars, end);
+                    // the chord is as long as its first note
+                    factor.get_or_insert(note_factor);
+                    pitches.push(pitch);
+                    i = end;
+                }
+                '-' => {
+                    tie = true;
+                    i += 1;
+                }
+                _ => i += 1,
+            }
+        }
+        let (chord_factor, end) = read_length(chars, i + 1);
+        let (chord_tie, end) = read_tie(chars, end);
+        if !pitches.is_empty() {
+            let beats = self.note_beats(factor.unwrap_or(1.0) * chord_factor);
+            self.push(Element::Notes { pitches, beats, tie: tie || chord_tie, volume: self.volume });
+        }
+        Ok(end)
+    }
+
+    /// Reads an accidental, a letter and octave marks into a MIDI note number
+    fn read_pitch(&mut self, chars: &[char], start: usize) -> Result<(u8, usize), &'static str> {
+        let mut i = start;
+        let mut accidental: Option<i8> = None;
+        while i < chars.len() && matches!(chars[i], '^' | '_' | '=') {
+            let step = match chars[i] {
+                '^' => 1,
+                '_' => -1,
+                _ => 0,
+            };
+            accidental = Some(accidental.unwrap_or(0) + step);
+            i += 1;
+        }
+        let letter = *chars.get(i).ok_or("Accidental without a note in ABC tune")?;
+        // the letter is read on its own as a `b` after it is the next note rather than a flat
+        let (semitone, _) = read_pitch_letter(letter.encode_utf8(&mut [0; 4])).ok_or("Accidental without a note in ABC tune")?;
+        i += 1;
+        let mut octave = if letter.is_ascii_lowercase() { 5 } else { 4 };
+        while let Some(mark) = chars.get(i) {
+            match mark {
+                '\'' => octave += 1,
+                ',' => octave -= 1,
+                _ => break,
+            }
+            i += 1;
+        }
+        let letter = letter.to_ascii_uppercase();
+        let alter = match accidental {
+            Some(alter) => {
+                self.accidentals.insert((letter, octave), alter);
+                alter
+            }
+            None => match self.accidentals.get(&(letter, octave)) {
+                Some(alter) => *alter,
+                None => signature_alter(self.fifths, letter),
+            },
+        };
+        let midi = (octave + 1) * 12 + semitone + alter as i32;
+        match u8::try_from(midi) {
+            Ok(midi) if midi <= 127 => Ok((midi, i)),
+            _ => Err("Pitch out of range in ABC tune"),
+        }
+    }
+
+    fn into_song(self) -> Result<Song, &'static str> {
+        let mut song = Song::new(self.title.clone().unwrap_or("ABC Tune".to_string()), self.bpm.round().clamp(1.0, u16::MAX as f32) as u16);
+        song.time_signature = self.time_signature;
+        song.key = self.key;
+        let single_voice = self.voices.len() == 1;
+        for voice in self.voices {
+            let name = match voice.name {
+                Some(name) => name,
+                None if single_voice => "Melody".to_string(),
+                None => format!("Voice {}", voice.id),
+            };
+            let mut part = Part::new(name);
+            let mut beat = 0.0;
+            // notes tied into the next notes as (pitch, index of the note in the part)
+            let mut tied: Vec<(u8, usize)> = Vec::new();
+            for element in expand_repeats(&voice.elements) {
+                match element {
+                    Element::Notes { mut pitches, beats, tie, volume } => {
+                        pitches.sort();
+                        pitches.dedup();
+                        let mut still_tied = Vec::new();
+                        for pitch in pitches {
+                            let index = match tied.iter().find(|(tied_pitch, _)| *tied_pitch == pitch) {
+                                Some((_, index)) => {
+                                    part.notes[*index].duration += beats;
+                                    *index
+                                }
+                                None => part.merge_chord_note(Note::new(beat, beats, frequency_from_midi(pitch), volume)?),
+                            };
+                            if tie {
+                                still_tied.push((pitch, index));
+                            }
+                        }
+                        tied = still_tied;
+                        beat += beats;
+                    }
+                    Element::Rest { beats } => {
+                        tied.clear();
+                        beat += beats;
+                    }
+                    Element::Bar(_) => {}
+                }
+            }
+            song.parts.push(part);
+        }
+        Ok(song)
+    }
+}
+
+/// Plays repeated sections twice, taking the first ending the first time and the second ending the second time
+fn expand_repeats(elements: &[Element]) -> Vec<Element> {
+    let mut expanded = Vec::new();
+    let mut repeat_start = 0;
+    let mut second_pass = false;
+    let mut skipping = false;
+    let mut i = 0;
+    while i < elements.len() {
+        match &elements[i] {
+            Element::Bar(Bar::RepeatStart) => {
+                repeat_start = i + 1;
+                second_pass = false;
+                skipping = false;
+            }
+            Element::Bar(Bar::RepeatEnd) if !skipping => {
+                if !second_pass {
+                    second_pass = true;
+                    i = repeat_start;
+                    continue;
+                }
+                second_pass = false;
+                repeat_start = i + 1;
+            }
+            Element::Bar(Bar::Ending(number)) => skipping = (*number == 1) == second_pass,
+            Element::Bar(Bar::Double) => {
+                skipping = false;
+                second_pass = false;
+                repeat_start = i + 1;
+            }
+            element if !skipping => expanded.push(element.clone()),
+            _ => {}
+        }
+        i += 1;
+    }
+    expanded
+}
+
+/// Writes a song as an ABC tune with one voice per part
+pub fn song_to_abc(song: &Song) -> Result<String, &'static str> {
+    let bar_beats = song.time_signature.bar_beats();
+    let num_bars = score::num_bars(song.duration(), bar_beats);
+    let mut abc = String::new();
+    abc.push_str("X:1\n");
+    abc.push_str(&format!("T:{}\n", song.name));
+    abc.push_str(&format!("M:{}\n", song.time_signature));
+    abc.push_str("L:1/8\n");
+    abc.push_str(&format!("Q:1/4={}\n", song.bpm));
+    // patterns are written out as notes wherever they are placed
+    let parts = song.arranged_parts();
+    for (index, part) in parts.iter().enumerate() {
+        abc.push_str(&format!("V:{} name=\"{}\"\n", index + 1, part.name.replace('"', "'")));
+    }
+    abc.push_str(&format!("K:{}{}\n", song.key.tonic_name(), if song.key.minor { "m" } else { "" }));
+    for (index, part) in parts.iter().enumerate() {
+        abc.push_str(&format!("V:{}\n", index + 1));
+        if part.is_percussion() {
+            abc.push_str("%%MIDI channel 10\n");
+        }
+        let bars: Vec<String> = score::bars(&part.notes, bar_beats, num_bars).iter().map(|bar| write_bar(bar, &song.key)).collect();
+        for (line, bars) in bars.chunks(BARS_PER_LINE).enumerate() {
+            let last = (line + 1) * BARS_PER_LINE >= num_bars;
+            abc.push_str(&bars.join(" | "));
+            abc.push_str(if last { " |]\n" } else { " |\n" });
+        }
+    }
+    Ok(abc)
+}
+
+fn write_bar(bar: &[score::Segment], key: &Key) -> String {
+    let mut accidentals = score::Accidentals::default();
+    let tokens: Vec<String> = bar
+        .iter()
+        .map(|segment| {
+            let length = write_length(segment.duration / EXPORT_UNIT_BEATS);
+            let mut pitches: Vec<String> = segment
+                .pitches
+                .iter()
+                .map(|pitch| {
+                    let (letter, alter, octave) = key.spell(pitch.midi);
+                    let accidental = match accidentals.write(key, letter, alter, octave) {
+                        None => "",
+                        Some(1) => "^",
+                        Some(-1) => "_",
+                        Some(_) => "=",
+                    };
+                    let tie = if pitch.tied { "-" } else { "" };
+                    format!("{accidental}{}{tie}", write_letter(letter, octave))
+                })
+                .collect();
+            match pitches.len() {
+                0 => format!("z{length}"),
+                1 => {
+                    // a tie goes after the length of a lone note
+                    let pitch = pitches.remove(0);
+                    match pitch.strip_suffix('-') {
+                        Some(pitch) => format!("{pitch}{length}-"),
+                        None => format!("{pitch}{length}"),
+                    }
+                }
+                _ => format!("[{}]{length}", pitches.join("")),
+            }
+        })
+        .collect();
+    tokens.join(" ")
+}
+
+/// Writes a letter in the octave ABC puts it in, where `C` is middle C and `c` the octave above
+fn write_letter(letter: char, octave: i32) -> String {
+    if octave >= 5 {
+        format!("{}{}", letter.to_ascii_lowercase(), "'".repeat((octave - 5) as usize))
+    } else {
+        format!("{letter}{}", ",".repeat((4 - octave).max(0) as usize))
+    }
+}
+
+/// Writes a length in unit notes as an ABC length like `2`, `/2` or `3/2`
+fn write_length(units: f32) -> String {
+    let denominator = (1..=48).find(|denominator| {
+        let scaled = units * *denominator as f32;
+        (scaled - scaled.round()).abs() < 0.01
+    });
+    let denominator = denominator.unwrap_or(48);
+    let numerator = (units * denominator as f32).round().max(1.0) as u32;
+    let divisor = gcd(numerator, denominator);
+    let (numerator, denominator) = (numerator / divisor, denominator / divisor);
+    match (numerator, denominator) {
+        (1, 1) => String::new(),
+        (numerator, 1) => numerator.to_string(),
+        (1, denominator) => format!("/{denominator}"),
+        (numerator, denominator) => format!("{numerator}/{denominator}"),
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a.max(1) } else { gcd(b, a % b) }
+}
+
+/// Reads a meter like `3/4`, `2+3/8`, `C` or `C|`
+fn parse_meter(value: &str) -> Option<TimeSignature> {
+    match value.trim() {
+        "C" => return Some(TimeSignature::default()),
+        "C|" => return TimeSignature::new(2, 2).ok(),
+        _ => {}
+    }
+    let (beats, unit) = value.trim().split_once('/')?;
+    let beats: u32 = beats.split('+').map(|beats| beats.trim().parse::<u32>().ok()).sum::<Option<u32>>()?;
+    TimeSignature::new(u8::try_from(beats).ok()?, unit.trim().parse().ok()?).ok()
+}
+
+fn parse_fraction(value: &str) -> Option<f32> {
+    let (numerator, denominator) = value.trim().split_once('/')?;
+    let denominator = denominator.trim().parse::<f32>().ok()?;
+    if denominator <= 0.0 {
+        return None;
+    }
+    Some(numerator.trim().parse::<f32>().ok()? / denominator)
+}
+
+/// Reads a tempo like `1/4=120`, `3/8=60` or `"Allegro" 1/4=120` into quarter note beats per minute.
+/// A lone number is taken as quarter note beats per minute
+fn parse_tempo(value: &str) -> Option<f32> {
+    let mut text = String::new();
+    let mut quoted = false;
+    for c in value.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if !quoted {
+            text.push(c);
+        }
+    }
+    match text.split_once('=') {
+        Some((lengths, bpm)) => {
+            let length: f32 = lengths.split_whitespace().map(parse_fraction).sum::<Option<f32>>()?;
+            Some(bpm.trim().parse::<f32>().ok()? * length * 4.0)
+        }
+        None => text.trim().parse::<f32>().ok(),
+    }
+    .filter(|bpm| *bpm > 0.0)
+}
+
+/// Reads a key like `G`, `Em`, `Bb minor` or `D dorian` into a key and its sharps, or flats when negative.
+/// Modes other than major and minor are kept as the major key with the same signature
+fn parse_key(value: &str) -> Option<(Key, i8)> {
+    let value = value.trim();
+    let mut chars = value.chars();
+    let letter = match chars.next() {
+        Some(letter) if matches!(letter.to_ascii_uppercase(), 'A'..='G') => letter.to_ascii_uppercase(),
+        // `K:none`, `K:` or a clef only
+        _ => return Some((Key::default(), 0)),
+    };
+    let mut fifths: i32 = match letter {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => -1,
+        'G' => 1,
+        'A' => 3,
+        _ => 5,
+    };
+    let mut rest = chars.as_str();
+    match rest.chars().next() {
+        Some('#') => {
+            fifths += 7;
+            rest = &rest[1..];
+        }
+        Some('b') => {
+            fifths -= 7;
+            rest = &rest[1..];
+        }
+        _ => {}
+    }
+    let mode: String = rest.trim_start().chars().take_while(|c| c.is_ascii_alphabetic()).take(3).collect::<String>().to_lowercase();
+    let minor = matches!(mode.as_str(), "m" | "min" | "aeo");
+    fifths += match mode.as_str() {
+        "" | "maj" | "ion" => 0,
+        "m" | "min" | "aeo" => -3,
+        "mix" => -1,
+        "dor" => -2,
+        "phr" => -4,
+        "lyd" => 1,
+        "loc" => -5,
+        _ => return None,
+    };
+    let fifths = fifths.clamp(-7, 7) as i8;
+    Some((Key::from_fifths(fifths, minor), fifths))
+}
+
+/// Gets the value of a field option like `name="Alto"`
+fn quoted_value(value: &str, option: &str) -> Option<String> {
+    let start = value.find(option)? + option.len();
+    let rest = &value[start..];
+    match rest.strip_prefix('"') {
+        Some(rest) => Some(rest.split('"').next().unwrap_or("").to_string()),
+        None => rest.split_whitespace().next().map(str::to_string),
+    }
+}
+
+/// Reads a length factor like `2`, `/2`, `3/2` or `//`
+fn read_length(chars: &[char], start: usize) -> (f32, usize) {
+    let (numerator, mut end) = read_number(chars, start);
+    let mut factor = numerator.unwrap_or(1) as f32;
+    while chars.get(end) == Some(&'/') {
+        let (denominator, next) = read_number(chars, end + 1);
+        factor /= denominator.unwrap_or(2).max(1) as f32;
+        end = next;
+    }
+    (factor, end)
+}
+
+fn read_number(chars: &[char], start: usize) -> (Option<u32>, usize) {
+    let mut end = start;
+    while chars.get(end).is_some_and(|c| c.is_ascii_digit()) {
+        end += 1;
+    }
+    let number: String = chars[start..end].iter().collect();
+    (number.parse().ok(), end)
+}
+
+fn read_tie(chars: &[char], start: usize) -> (bool, usize) {
+    if chars.get(start) == Some(&'-') { (true, start + 1) } else { (false, start) }
+}
+
+/// Skips the rest of an ending list like `1,3` or `1-2`, only the first ending is used
+fn skip_ending_list(chars: &[char], start: usize) -> usize {
+    let mut end = start;
+    while chars.get(end).is_some_and(|c| c.is_ascii_digit() || *c == ',' || *c == '-') {
+        end += 1;
+    }
+    end
+}
+
+/// Gets the index after the next `close` character from `start`
+fn skip_past(chars: &[char], start: usize, close: char) -> usize {
+    match chars[start.min(chars.len())..].iter().position(|c| *c == close) {
+        Some(offset) => start + offset + 1,
+        None => chars.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::{name_from_frequency, parse_melody};
+
+    #[test]
+    fn inline_fields_with_multibyte_names_dont_panic() {
+        let song = song_from_abc("X:1\nK:C\nCD[€:x]EF|\n").unwrap();
+        assert_eq!(song.parts[0].notes.len(), 4);
+    }
+
+    fn pitches_and_beats(part: &Part) -> Vec<(String, f32, f32)> {
+        let mut notes: Vec<(String, f32, f32)> = part.notes.iter().map(|note| (name_from_frequency(note.frequency), note.beat, note.duration)).collect();
+        notes.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        notes
+    }
+
+    #[test]
+    fn reads_header_key_accidentals_ties_chords_and_repeats() {
+        let song = song_from_abc("X:1\nT:Test\nM:3/4\nL:1/8\nQ:1/4=90\nK:G\nG2 ^c2 c2 | F2- F2 [CE]2 :|\n").unwrap();
+        assert_eq!(song.name, "Test");
+        assert_eq!((song.bpm, song.time_signature.beats, song.time_signature.beat_unit), (90, 3, 4));
+        assert!(song.key == Key::new(7, false));
+        let once = [("G4", 0.0, 1.0), ("C#5", 1.0, 1.0), ("C#5", 2.0, 1.0), ("F#4", 3.0, 2.0), ("C4", 5.0, 1.0), ("E4", 5.0, 1.0)];
+        let expected: Vec<(String, f32, f32)> = [0.0, 6.0].iter().flat_map(|pass| once.iter().map(move |(name, beat, duration)| (name.to_string(), beat + pass, *duration))).collect();
+        assert_eq!(pitches_and_beats(&song.parts[0]), expected);
+    }
+
+    #[test]
+    fn exported_tunes_read_back_the_same() {
+        let mut song = Song::new("Round Trip".to_string(), 100);
+        song.key = Key::new(2, true);
+        let mut part = Part::new("Lead".to_string());
+        part.notes = parse_melody("D4/4 F4/8 G#4/8 A4/2 Bb4/4. r/8 C#5/8 D5/2", 0.0, 0.5).unwrap();
+        song.parts.push(part);
+        let read = song_from_abc(&song_to_abc(&song).unwrap()).unwrap();
+        assert_eq!(read.name, "Round Trip");
+        assert!(read.key == song.key);
+        assert_eq!(read.parts[0].name, "Lead");
+        assert_eq!(pitches_and_beats(&read.parts[0]), pitches_and_beats(&song.parts[0]));
+    }
+}
@@ -0,0 +1,263 @@
+use std::fmt::Display;
+
+use super::note::Note;
+use super::pitch::midi_from_frequency;
+use super::signature::Key;
+use super::song::Song;
+use super::theory::{Chord, ChordQuality, CHORD_QUALITIES};
+
+/// How strongly each pitch class above the tonic is heard in major keys, from Krumhansl and Kessler's listening tests
+const MAJOR_PROFILE: [f32; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+/// How strongly each pitch class above the tonic is heard in minor keys
+const MINOR_PROFILE: [f32; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+/// Chords with more notes than this aren't guessed as the extra notes are usually passing notes
+const MAX_GUESSED_CHORD_NOTES: usize = 4;
+/// Score lost for every note of a chord that isn't heard in the window
+const MISSING_NOTE_COST: f32 = 0.25;
+/// Score lost for every chord note past a triad, so a triad with a passing note isn't read as a seventh
+const EXTRA_NOTE_COST: f32 = 0.25;
+/// Score gained by a chord whose root is the lowest note heard, to choose between chords sharing their notes like C6 and Am7
+const BASS_ROOT_BONUS: f32 = 0.1;
+
+/// The key and chords [Song::analyze] hears in a song
+pub struct Analysis {
+    // every major and minor key with how well the pitches of the song fit it from -1 to 1, best first
+    pub keys: Vec<(Key, f32)>,
+    pub window_beats: f32,
+    // the chord heard in every window from beat 0, `None` where fewer than two pitches are heard
+    pub chords: Vec<Option<Chord>>,
+}
+
+impl Analysis {
+    /// The key that fits the song best
+    pub fn key(&self) -> Key {
+        self.keys[0].0
+    }
+}
+
+impl Song {
+    /// Estimates the key of the song by comparing how long every pitch class is heard against the profile of every
+    /// major and minor key, then guesses the chord heard in every `window_beats` beats. Drum parts are left out
+    pub fn analyze(&self, window_beats: f32) -> Result<Analysis, &'static str> {
+        if window_beats <= 0.0 {
+            return Err("Analysis windows must last longer than 0 beats!");
+        }
+        let notes: Vec<Note> = self.arranged_parts().into_iter().filter(|part| !part.is_percussion()).flat_map(|part| part.notes).collect();
+        let end = notes.iter().map(|note| note.end_beat()).fold(0.0, f32::max);
+        let weights = pitch_class_weights(&notes, 0.0, end);
+        if weights.iter().all(|weight| *weight == 0.0) {
+            return Err("The song has no pitched notes to analyze!");
+        }
+        let mut keys: Vec<(Key, f32)> = (0..24)
+            .map(|index| {
+                let key = Key::new(index % 12, index >= 12);
+                let profile = if key.minor { MINOR_PROFILE } else { MAJOR_PROFILE };
+                // the profile is turned so its tonic lines up with the key's
+                let profile: Vec<f32> = (0..12).map(|pitch_class| profile[(pitch_class + 12 - key.tonic as usize) % 12]).collect();
+                (key, correlation(&weights, &profile))
+            })
+            .collect();
+        keys.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let num_windows = (end / window_beats).ceil() as usize;
+        let chords = (0..num_windows)
+            .map(|window| {
+                let start = window as f32 * window_beats;
+                guess_chord(&notes, start, start + window_beats)
+            })
+            .collect();
+        Ok(Analysis { keys, window_beats, chords })
+    }
+}
+
+/// How long every pitch class is heard between two beats, louder notes counting for more
+fn pitch_class_weights(notes: &[Note], start: f32, end: f32) -> [f32; 12] {
+    let mut weights = [0.0; 12];
+    for note in notes {
+        let heard = note.end_beat().min(end) - note.beat.max(start);
+        if heard > 0.0 {
+            weights[midi_from_frequency(note.frequency) as usize % 12] += heard * note.volume.abs();
+        }
+    }
+    weights
+}
+
+/// Pearson correlation of two lists of the same length, 0 when either doesn't change
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let covariance: f32 = a.iter().zip(b).map(|(a, b)| (a - mean_a) * (b - mean_b)).sum();
+    let spread = |values: &[f32], mean: f32| values.iter().map(|value| (value - mean).powi(2)).sum::<f32>().sqrt();
+    let spreads = spread(a, mean_a) * spread(b, mean_b);
+    if spreads == 0.0 { 0.0 } else { covariance / spreads }
+}
+
+/// Guesses the chord heard between two beats by scoring every chord of up to four notes on every root by how much of
+/// what is heard it covers. The lowest note heard picks the inversion
+fn guess_chord(notes: &[Note], start: f32, end: f32) -> Option<Chord> {
+    let weights = pitch_class_weights(notes, start, end);
+    if weights.iter().filter(|weight| **weight > 0.0).count() < 2 {
+        return None;
+    }
+    let total: f32 = weights.iter().sum();
+    let bass = notes.iter().filter(|note| note.beat < end && note.end_beat() > start).map(|note| midi_from_frequency(note.frequency)).min()? % 12;
+    let score = |root: u8, quality: ChordQuality| {
+        let pitch_classes: Vec<usize> = quality.intervals().iter().map(|interval| (root + interval) as usize % 12).collect();
+        let covered: f32 = pitch_classes.iter().map(|pitch_class| weights[*pitch_class]).sum();
+        let missing = pitch_classes.iter().filter(|pitch_class| weights[**pitch_class] == 0.0).count();
+        let mut score = (2.0 * covered - total) / total - MISSING_NOTE_COST * missing as f32 - EXTRA_NOTE_COST * pitch_classes.len().saturating_sub(3) as f32;
+        if root == bass {
+            score += BASS_ROOT_BONUS;
+        }
+        score
+    };
+    let (root, quality) = (0..12)
+        .flat_map(|root| CHORD_QUALITIES.iter().filter(|quality| quality.intervals().len() <= MAX_GUESSED_CHORD_NOTES).map(move |quality| (root, *quality)))
+        .fold(None, |best: Option<(u8, ChordQuality, f32)>, (root, quality)| {
+            let score = score(root, quality);
+            match best {
+                // ties go to the chord listed first so simpler chords win
+                Some(best) if best.2 >= score => Some(best),
+                _ => Some((root, quality, score)),
+            }
+        })
+        .map(|(root, quality, _)| (root, quality))?;
+    let inversion = quality.intervals().iter().position(|interval| (root + interval) % 12 == bass).unwrap_or(0) as u8;
+    Some(Chord { root, quality, inversion })
+}
+
+impl Chord {
+    /// Writes the chord as a Roman numeral in a key like `V7`, `ii` or `bVII`, the way [parse_progression] reads them.
+    /// Roots outside the key are written as the note of the key above them flattened, or in minor keys as the note
+    /// below them sharpened, like the raised sixth and seventh
+    ///
+    /// [parse_progression]: super::progression::parse_progression
+    pub fn numeral(&self, key: Key) -> String {
+        const NUMERALS: [&str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
+        let scale = key.scale();
+        let degree_of = |semitones: i32| scale.iter().position(|step| *step as i32 == semitones.rem_euclid(12));
+        let semitones = self.root as i32 - key.tonic as i32;
+        let (accidental, degree) = match degree_of(semitones) {
+            Some(degree) => ("", degree),
+            None => match (degree_of(semitones - 1), degree_of(semitones + 1)) {
+                (Some(below), _) if key.minor && below != 0 => ("#", below),
+                (_, Some(above)) if above != 0 => ("b", above),
+                (Some(below), _) => ("#", below),
+                // every note outside a major or minor scale is a semitone from a note in it
+                _ => ("", 0),
+            },
+        };
+        let (upper_case, suffix) = match self.quality {
+            ChordQuality::Major => (true, ""),
+            ChordQuality::Minor => (false, ""),
+            ChordQuality::Diminished => (false, "°"),
+            ChordQuality::Augmented => (true, "+"),
+            ChordQuality::Sus2 => (true, "sus2"),
+            ChordQuality::Sus4 => (true, "sus4"),
+            ChordQuality::Major6 => (true, "6"),
+            ChordQuality::Minor6 => (false, "6"),
+            ChordQuality::Dominant7 => (true, "7"),
+            ChordQuality::Major7 => (true, "maj7"),
+            ChordQuality::Minor7 => (false, "7"),
+            ChordQuality::MinorMajor7 => (false, "maj7"),
+            ChordQuality::HalfDiminished7 => (false, "ø7"),
+            ChordQuality::Diminished7 => (false, "°7"),
+            ChordQuality::Augmented7 => (true, "+7"),
+            ChordQuality::Add9 => (true, "add9"),
+            ChordQuality::Dominant9 => (true, "9"),
+            ChordQuality::Major9 => (true, "maj9"),
+            ChordQuality::Minor9 => (false, "9"),
+            ChordQuality::Dominant11 => (true, "11"),
+            ChordQuality::Minor11 => (false, "11"),
+            ChordQuality::Dominant13 => (true, "13"),
+            // read back as a major ninth as Roman numerals have no thirteenth of a major seventh
+            ChordQuality::Major13 => (true, "maj9"),
+        };
+        let numeral = if upper_case { NUMERALS[degree].to_string() } else { NUMERALS[degree].to_lowercase() };
+        format!("{accidental}{numeral}{suffix}")
+    }
+}
+
+impl Display for Analysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = self.key();
+        writeln!(f, "Key: {} (fit {:.2})", key, self.keys[0].1)?;
+        let others: Vec<String> = self.keys[1..4].iter().map(|(key, fit)| format!("{key} ({fit:.2})")).collect();
+        writeln!(f, "Other keys that fit: {}", others.join(", "))?;
+        write!(f, "Chords every {} beats:", self.window_beats)?;
+        for (window, chord) in self.chords.iter().enumerate() {
+            let beat = window as f32 * self.window_beats;
+            match chord {
+                Some(chord) => write!(f, "\n\tbeat {beat}: {chord} ({})", chord.numeral(key))?,
+                None => write!(f, "\n\tbeat {beat}: no chord")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::{frequency_from_name, parse_melody, parse_progression, Accompaniment, AccompanimentStyle, Instrument, Part};
+
+    fn song_of(progression: &str, key: Key) -> Song {
+        let mut song = Song::new("Analysis".to_string(), 120);
+        let accompaniment = Accompaniment { style: AccompanimentStyle::Block, rhythm: 1.0, octave: 4, volume: 0.5 };
+        let progression = parse_progression(progression, key, 4.0).unwrap();
+        song.parts.push(accompaniment.part("Chords".to_string(), &progression, 0.0).unwrap());
+        song
+    }
+
+    #[test]
+    fn hears_the_key_and_chords_of_a_progression() {
+        let analysis = song_of("I IV V7 I", Key::parse("G").unwrap()).analyze(4.0).unwrap();
+        assert!(analysis.key() == Key::parse("G").unwrap());
+        let chords: Vec<String> = analysis.chords.iter().map(|chord| chord.unwrap().to_string()).collect();
+        // voice leading plays the middle chords in inversions
+        assert_eq!(chords, ["G", "C/G", "D7/F#", "G"]);
+    }
+
+    #[test]
+    fn hears_minor_keys() {
+        let mut song = Song::new("Minor".to_string(), 120);
+        let mut melody = Part::new("Melody".to_string());
+        melody.notes = parse_melody("A4/4 C5/4 E5/4 A4/4 B4/8 C5/8 D5/4 E5/4 G#4/4 A4/2 E4/2 A4/1", 0.0, 0.5).unwrap();
+        song.parts.push(melody);
+        assert!(song.analyze(4.0).unwrap().key() == Key::parse("Am").unwrap());
+    }
+
+    #[test]
+    fn the_lowest_note_picks_the_inversion_and_lone_notes_make_no_chord() {
+        let mut song = Song::new("Inversion".to_string(), 120);
+        let mut part = Part::new("Piano".to_string());
+        part.notes = parse_melody("E3/1 C4/1", 0.0, 0.5).unwrap();
+        part.add_note(Note::new(0.0, 4.0, frequency_from_name("G4").unwrap(), 0.5).unwrap()).unwrap();
+        part.add_note(Note::new(0.0, 4.0, frequency_from_name("C5").unwrap(), 0.5).unwrap()).unwrap();
+        song.parts.push(part);
+        let analysis = song.analyze(4.0).unwrap();
+        assert_eq!(analysis.chords[0].unwrap().to_string(), "C/E");
+        assert!(analysis.chords[1].is_none());
+    }
+
+    #[test]
+    fn numerals_read_back_as_the_same_chord() {
+        for key in [Key::parse("C").unwrap(), Key::parse("Am").unwrap(), Key::parse("Eb").unwrap(), Key::parse("F#m").unwrap()] {
+            for root in 0..12 {
+                for quality in CHORD_QUALITIES.iter().filter(|quality| **quality != ChordQuality::Major13) {
+                    let chord = Chord { root, quality: *quality, inversion: 0 };
+                    let numeral = chord.numeral(key);
+                    let read = parse_progression(&numeral, key, 1.0).unwrap()[0].0;
+                    assert!(read == chord, "{numeral} in {key} read back as {read} instead of {chord}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_songs_without_pitched_notes() {
+        let mut song = song_of("I", Key::default());
+        assert!(song.analyze(0.0).is_err());
+        song.parts[0].instrument = Instrument::Drums;
+        assert!(song.analyze(4.0).is_err());
+    }
+}
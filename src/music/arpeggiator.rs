@@ -0,0 +1,231 @@
+use std::fmt::Display;
+
+use super::note::Note;
+use super::part::Part;
+use super::random::Random;
+use super::selection::Selection;
+
+/// Leeway when checking whether a note is held on a step, so notes starting right on a step are caught
+const HOLD_TOLERANCE: f32 = 0.0001;
+/// Most octaves an arpeggio can climb
+pub const MAX_ARPEGGIO_OCTAVES: u8 = 4;
+
+/// The order an [Arpeggiator] plays the held notes in
+#[derive(Clone, Copy, PartialEq)]
+pub enum ArpeggioMode {
+    Up,
+    Down,
+    // up and back down without playing the highest and lowest notes twice
+    UpDown,
+    // a held note picked at random on every step, the same way every time for the same seed
+    Random,
+    // in the order the notes started
+    AsPlayed,
+}
+
+/// Plays the notes held at the same time one after another, stepping every `rate` beats from when the first of them
+/// starts until they are all released
+#[derive(Clone, Copy)]
+pub struct Arpeggiator {
+    pub mode: ArpeggioMode,
+    // beats between steps
+    pub rate: f32,
+    // how much of each step the notes sound, 1 plays them until the next step
+    pub gate: f32,
+    // octaves the held notes are played in, going up from their own
+    pub octaves: u8,
+    pub seed: u64,
+}
+
+impl ArpeggioMode {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ArpeggioMode::Up),
+            1 => Some(ArpeggioMode::Down),
+            2 => Some(ArpeggioMode::UpDown),
+            3 => Some(ArpeggioMode::Random),
+            4 => Some(ArpeggioMode::AsPlayed),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArpeggioMode::Up => "up",
+            ArpeggioMode::Down => "down",
+            ArpeggioMode::UpDown => "up-down",
+            ArpeggioMode::Random => "random",
+            ArpeggioMode::AsPlayed => "as played",
+        }
+    }
+}
+
+impl Arpeggiator {
+    /// Checks the settings can be played
+    pub fn check(&self) -> Result<(), &'static str> {
+        if self.rate <= 0.0 {
+            return Err("Arpeggiator rate must be above 0 beats!");
+        }
+        if self.gate <= 0.0 || self.gate > 1.0 {
+            return Err("Arpeggiator gate must be above 0 and at most 1!");
+        }
+        if self.octaves == 0 || self.octaves > MAX_ARPEGGIO_OCTAVES {
+            return Err("Arpeggiator octave range must be between 1 and 4!");
+        }
+        Ok(())
+    }
+
+    /// Gets the notes the arpeggiator plays for held `notes`. Each step plays one held note at its volume and never
+    /// past its end. Once every note has been released the pattern starts over on the next note pressed, even between steps
+    pub fn arpeggiate(&self, notes: &[Note]) -> Result<Vec<Note>, &'static str> {
+        self.check()?;
+        let mut random = Random::new(self.seed);
+        let mut sorted: Vec<&Note> = notes.iter().collect();
+        sorted.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        let mut arpeggiated = Vec::new();
+        let mut next = 0;
+        while next < sorted.len() {
+            // a phrase lasts while any note is held, notes pressed before it ends join it
+            let phrase_start = sorted[next].beat;
+            let mut phrase_end = sorted[next].end_beat();
+            next += 1;
+            while next < sorted.len() && sorted[next].beat < phrase_end - HOLD_TOLERANCE {
+                phrase_end = phrase_end.max(sorted[next].end_beat());
+                next += 1;
+            }
+            // steps are counted from the start of the phrase so they don't drift
+            let mut step = 0;
+            let mut beat = phrase_start;
+            while beat < phrase_end - HOLD_TOLERANCE {
+                let held: Vec<&Note> = notes.iter().filter(|note| note.beat <= beat + HOLD_TOLERANCE && beat < note.end_beat() - HOLD_TOLERANCE).collect();
+                if !held.is_empty() {
+                    let sequence = self.sequence(&held);
+                    let index = match self.mode {
+                        ArpeggioMode::Random => random.next_u32() as usize,
+                        _ => step,
+                    };
+                    let (frequency, note) = sequence[index % sequence.len()];
+                    let duration = (self.rate * self.gate).min(note.end_beat() - beat);
+                    arpeggiated.push(Note::new(beat, duration, frequency, note.volume)?);
+                }
+                step += 1;
+                beat = phrase_start + step as f32 * self.rate;
+            }
+        }
+        Ok(arpeggiated)
+    }
+
+    /// Orders the held notes for one pass of the arpeggio across every octave, with the frequency each is played at
+    fn sequence<'a>(&self, held: &[&'a Note]) -> Vec<(f32, &'a Note)> {
+        let mut held = held.to_vec();
+        match self.mode {
+            ArpeggioMode::AsPlayed => held.sort_by(|a, b| a.beat.total_cmp(&b.beat)),
+            _ => held.sort_by(|a, b| a.frequency.total_cmp(&b.frequency)),
+        }
+        let mut sequence: Vec<(f32, &Note)> = (0..self.octaves)
+            .flat_map(|octave| held.iter().map(move |note| (note.frequency * 2_f32.powi(octave as i32), *note)))
+            .collect();
+        match self.mode {
+            ArpeggioMode::Down => sequence.reverse(),
+            ArpeggioMode::UpDown if sequence.len() > 2 => {
+                let down: Vec<(f32, &Note)> = sequence[1..sequence.len() - 1].iter().rev().copied().collect();
+                sequence.extend(down);
+            }
+            _ => {}
+        }
+        sequence
+    }
+}
+
+impl Display for Arpeggiator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "arpeggiator: {} every {} beats, gate {}%, {} octaves", self.mode.name(), self.rate, self.gate * 100.0, self.octaves)?;
+        if self.mode == ArpeggioMode::Random {
+            write!(f, ", seed {}", self.seed)?;
+        }
+        Ok(())
+    }
+}
+
+impl Part {
+    /// Replaces the selected notes with the notes an arpeggiator plays for them, returning how many notes it played.
+    /// Nothing changes if an arpeggiated note would overlap a note of the same pitch or leave the MIDI pitch range
+    pub fn arpeggiate(&mut self, arpeggiator: &Arpeggiator, selection: &Selection) -> Result<usize, &'static str> {
+        if self.is_percussion() {
+            return Err("Drum parts pick drums by pitch so they can't be arpeggiated!");
+        }
+        let selected = selection.indices_in(&self.notes);
+        if selected.is_empty() {
+            return Err("No notes are selected!");
+        }
+        let held: Vec<Note> = selected.iter().map(|index| self.notes[*index].clone()).collect();
+        let arpeggiated = arpeggiator.arpeggiate(&held)?;
+        let old_notes = self.notes.clone();
+        self.delete_notes(selection);
+        let first_new = self.notes.len();
+        self.notes.extend(arpeggiated);
+        let new_notes: Vec<usize> = (first_new..self.notes.len()).collect();
+        if let Err(err) = self.check_notes(&new_notes) {
+            self.notes = old_notes;
+            return Err(err);
+        }
+        Ok(new_notes.len())
+    }
+
+    /// Sets the arpeggiator the part's notes are played through when it is rendered, keeping the notes as they are.
+    /// `None` plays the notes as written
+    pub fn set_arpeggiator(&mut self, arpeggiator: Option<Arpeggiator>) -> Result<(), &'static str> {
+        if let Some(arpeggiator) = arpeggiator {
+            if self.is_percussion() {
+                return Err("Drum parts pick drums by pitch so they can't be arpeggiated!");
+            }
+            arpeggiator.check()?;
+        }
+        self.arpeggiator = arpeggiator;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arpeggiator(mode: ArpeggioMode, rate: f32) -> Arpeggiator {
+        Arpeggiator { mode, rate, gate: 1.0, octaves: 1, seed: 0 }
+    }
+
+    #[test]
+    fn plays_held_notes_in_order() {
+        let notes = vec![Note::new(0.0, 2.0, 440.0, 0.5).unwrap(), Note::new(0.0, 2.0, 220.0, 0.5).unwrap()];
+        let played = arpeggiator(ArpeggioMode::Up, 0.5).arpeggiate(&notes).unwrap();
+        let frequencies: Vec<f32> = played.iter().map(|note| note.frequency).collect();
+        assert_eq!(frequencies, vec![220.0, 440.0, 220.0, 440.0]);
+    }
+
+    #[test]
+    fn restarts_on_notes_pressed_between_steps() {
+        let notes = vec![Note::new(0.0, 1.2, 440.0, 0.5).unwrap(), Note::new(1.5, 0.4, 220.0, 0.5).unwrap()];
+        let played = arpeggiator(ArpeggioMode::Up, 1.0).arpeggiate(&notes).unwrap();
+        let steps: Vec<(f32, f32)> = played.iter().map(|note| (note.beat, note.frequency)).collect();
+        assert_eq!(steps, vec![(0.0, 440.0), (1.0, 440.0), (1.5, 220.0)]);
+        assert!((played[1].duration - 0.2).abs() < 0.0001);
+    }
+
+    #[test]
+    fn random_mode_repeats_for_the_same_seed() {
+        let notes: Vec<Note> = [220.0, 330.0, 440.0].iter().map(|frequency| Note::new(0.0, 4.0, *frequency, 0.5).unwrap()).collect();
+        let arpeggiator = arpeggiator(ArpeggioMode::Random, 0.25);
+        let frequencies = |played: Vec<Note>| played.iter().map(|note| note.frequency).collect::<Vec<f32>>();
+        assert_eq!(frequencies(arpeggiator.arpeggiate(&notes).unwrap()), frequencies(arpeggiator.arpeggiate(&notes).unwrap()));
+    }
+
+    #[test]
+    fn parts_with_unplayable_arpeggiators_fail_to_load_and_render() {
+        use crate::music::Serializable;
+        let mut part = Part::new("test".to_string());
+        part.add_note(Note::new(0.0, 1.0, 440.0, 0.5).unwrap()).unwrap();
+        part.arpeggiator = Some(arpeggiator(ArpeggioMode::Up, 0.0));
+        assert!(part.render(&mut [0.0; 64], 120.0, 44100).is_err());
+        assert!(Part::deserialize(&part.serialize().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt::Display;
+
+use super::effect::Effect;
+use super::serializable::{write_sized, write_string, ByteReader, Serializable};
+
+/// A mixing bus that runs everything routed into it through a chain of [Effect]s. A [Song](super::Song) has one master bus and any number of aux buses
+#[derive(Clone)]
+pub struct Bus {
+    pub name: String,
+    pub effects: Vec<Effect>
+}
+
+impl Bus {
+    pub fn new(name: String) -> Self {
+        Bus { name, effects: Vec::new() }
+    }
+
+    /// Runs the samples through every effect in order
+    pub fn process(&self, samples: &mut [f32], sample_rate: u32) {
+        for effect in &self.effects {
+            effect.process(samples, sample_rate);
+        }
+    }
+
+    pub fn tail_seconds(&self) -> f32 {
+        self.effects.iter().map(|effect| effect.tail_seconds()).sum()
+    }
+}
+
+/// Routes a copy of a [Part](super::Part)'s output to the aux bus at index `bus` of the song, scaled by `level`
+#[derive(Clone, Copy)]
+pub struct BusSend {
+    pub bus: usize,
+    pub level: f32
+}
+
+impl Display for Bus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "name: {}", self.name)?;
+        writeln!(f, "effects:")?;
+        for (index, effect) in self.effects.iter().enumerate() {
+            writeln!(f, "\t{}. {effect}", index + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serializable for Bus {
+    /// Serializes a `Bus` struct into a byte representation
+    /// u16: name_len
+    /// name_len: name
+    /// u16: num_effects
+    /// (effects) u16: size_of_effect
+    /// (effects) size_of_effect: effect
+    fn serialize(&self) -> Result<Vec<u8>, &'static str> {
+        let mut serialized_data = Vec::new();
+        write_string(&mut serialized_data, &self.name, "Could not serialize bus. Name too long!")?;
+        serialized_data.extend((self.effects.len() as u16).to_le_bytes());
+        for effect in &self.effects {
+            write_sized(&mut serialized_data, effect, "Could not serialize bus. Effect too large!")?;
+        }
+        Ok(serialized_data)
+    }
+
+    fn deserialize(serialized_data: &[u8]) -> Result<Self, &'static str> {
+        let mut reader = ByteReader::new(serialized_data);
+        let name = reader.read_string("Invalid serialized data! Insufficient length for bus name")?;
+        let num_effects = reader.read_u16("Invalid serialized data! Insufficient length for number of effects!")?;
+        let mut effects = Vec::new();
+        for _ in 0..num_effects {
+            effects.push(reader.read_sized("Invalid serialized data! Missing effect data!")?);
+        }
+        Ok(Bus { name, effects })
+    }
+}
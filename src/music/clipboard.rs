@@ -0,0 +1,128 @@
+use super::note::Note;
+use super::part::Part;
+use super::selection::Selection;
+use super::song::Song;
+
+/// Notes or whole parts copied in a [SongEditor](super::SongEditor), to be pasted into any of its songs
+#[derive(Clone, Default)]
+pub enum Clipboard {
+    #[default]
+    Empty,
+    // kept with the first note on beat 0 so they can be pasted at any beat
+    Notes(Vec<Note>),
+    Parts(Vec<Part>),
+}
+
+impl Clipboard {
+    /// Copies the selected notes of a part
+    pub fn copy_notes(part: &Part, selection: &Selection) -> Result<Self, &'static str> {
+        let mut notes: Vec<Note> = selection.indices_in(&part.notes).into_iter().map(|index| part.notes[index].clone()).collect();
+        let first_beat = notes.iter().map(|note| note.beat).min_by(|a, b| a.total_cmp(b)).ok_or("No notes are selected!")?;
+        for note in &mut notes {
+            note.beat -= first_beat;
+        }
+        Ok(Clipboard::Notes(notes))
+    }
+
+    /// Copies whole parts with their instrument, effects and notes. Pattern instances only make sense in the song
+    /// the parts come from, so parts playing patterns are copied as [Part::with_patterns] gives them
+    pub fn copy_parts(parts: &[Part]) -> Self {
+        Clipboard::Parts(parts.to_vec())
+    }
+
+    /// Adds the copied notes to a part with the first one on `beat`. Notes are added like [Part::add_note] adds them,
+    /// so they can make chords with the notes already in the part but notes overlapping one of the same pitch are left out.
+    /// Returns how many notes were pasted and how many were left out
+    pub fn paste_notes(&self, part: &mut Part, beat: f32) -> Result<(usize, usize), &'static str> {
+        let notes = match self {
+            Clipboard::Notes(notes) => notes,
+            Clipboard::Parts(_) => return Err("The clipboard holds parts, paste them into a song!"),
+            Clipboard::Empty => return Err("The clipboard is empty!"),
+        };
+        if beat < 0.0 {
+            return Err("Notes can't be pasted before beat 0!");
+        }
+        let mut pasted = 0;
+        for note in notes {
+            let mut note = note.clone();
+            note.beat += beat;
+            if part.add_note(note).is_ok() {
+                pasted += 1;
+            }
+        }
+        Ok((pasted, notes.len() - pasted))
+    }
+
+    /// Adds the copied parts to a song, returning how many were pasted. Sends are left out
+    /// because the aux buses they went to belong to the song the parts were copied from
+    pub fn paste_parts(&self, song: &mut Song) -> Result<usize, &'static str> {
+        let parts = match self {
+            Clipboard::Parts(parts) => parts,
+            Clipboard::Notes(_) => return Err("The clipboard holds notes, paste them into a part!"),
+            Clipboard::Empty => return Err("The clipboard is empty!"),
+        };
+        for part in parts {
+            let mut part = part.clone();
+            part.sends.clear();
+            song.parts.push(part);
+        }
+        Ok(parts.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::{parse_melody, BusSend};
+
+    fn part(melody: &str) -> Part {
+        let mut part = Part::new("Lead".to_string());
+        part.notes = parse_melody(melody, 0.0, 0.5).unwrap();
+        part
+    }
+
+    #[test]
+    fn copied_notes_start_on_the_beat_they_are_pasted_at() {
+        let from = part("C4/4 D4 E4 F4");
+        let clipboard = Clipboard::copy_notes(&from, &Selection::beats(1.0, 3.0).unwrap()).unwrap();
+        let mut into = Part::new("Copy".to_string());
+        assert_eq!(clipboard.paste_notes(&mut into, 8.0).unwrap(), (2, 0));
+        assert_eq!((into.notes[0].beat, into.notes[1].beat), (8.0, 9.0));
+        assert_eq!((into.notes[0].frequency, into.notes[1].frequency), (from.notes[1].frequency, from.notes[2].frequency));
+        assert!(clipboard.paste_notes(&mut into, -1.0).is_err());
+    }
+
+    #[test]
+    fn pasted_notes_make_chords_but_skip_notes_of_the_same_pitch() {
+        let clipboard = Clipboard::copy_notes(&part("C4/4 E4"), &Selection::all()).unwrap();
+        let mut into = part("C4/2");
+        // the C4 would overlap the one in the part, the E4 sounds with it
+        assert_eq!(clipboard.paste_notes(&mut into, 0.5).unwrap(), (1, 1));
+        assert_eq!(into.notes.len(), 2);
+    }
+
+    #[test]
+    fn parts_are_pasted_without_their_sends() {
+        let mut copied = part("C4/4");
+        copied.sends.push(BusSend { bus: 0, level: 0.5 });
+        let clipboard = Clipboard::copy_parts(&[copied]);
+        let mut song = Song::default();
+        let parts_before = song.parts.len();
+        assert_eq!(clipboard.paste_parts(&mut song).unwrap(), 1);
+        assert_eq!(song.parts.len(), parts_before + 1);
+        assert!(song.parts[parts_before].sends.is_empty());
+        assert!(clipboard.paste_notes(&mut Part::new("x".to_string()), 0.0).is_err());
+        assert!(Clipboard::default().paste_parts(&mut song).is_err());
+    }
+
+    #[test]
+    fn duplicated_parts_keep_their_notes() {
+        let mut song = Song::default();
+        song.parts.push(part("C4/4 D4"));
+        let index = song.parts.len() - 1;
+        song.duplicate_part(index).unwrap();
+        assert_eq!(song.parts[index + 1].name, "Lead copy");
+        assert_eq!(song.parts[index + 1].notes.len(), 2);
+        assert!(song.duplicate_part(song.parts.len()).is_err());
+    }
+}
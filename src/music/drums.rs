@@ -0,0 +1,233 @@
+use std::f32::consts::PI;
+use std::fmt::Display;
+
+use super::pitch::{frequency_from_midi, midi_from_frequency};
+use super::random::hash_to_signed_unit;
+
+/// A synthesized drum sound. Drum parts key each voice by its General MIDI drum number,
+/// stored as the frequency of that MIDI note so drum notes can be exported like any other note
+#[derive(Clone, Copy, PartialEq)]
+pub enum DrumVoice {
+    Kick,
+    SideStick,
+    Snare,
+    Clap,
+    ClosedHiHat,
+    PedalHiHat,
+    OpenHiHat,
+    LowTom,
+    MidTom,
+    HighTom,
+    Crash,
+    Ride,
+}
+
+impl DrumVoice {
+    pub const ALL: [DrumVoice; 12] = [
+        DrumVoice::Kick,
+        DrumVoice::SideStick,
+        DrumVoice::Snare,
+        DrumVoice::Clap,
+        DrumVoice::ClosedHiHat,
+        DrumVoice::PedalHiHat,
+        DrumVoice::OpenHiHat,
+        DrumVoice::LowTom,
+        DrumVoice::MidTom,
+        DrumVoice::HighTom,
+        DrumVoice::Crash,
+        DrumVoice::Ride,
+    ];
+
+    /// Gets the voice for a General MIDI drum number, using the closest voice for numbers that share a sound
+    pub fn from_gm(number: u8) -> Option<Self> {
+        match number {
+            35 | 36 => Some(DrumVoice::Kick),
+            37 => Some(DrumVoice::SideStick),
+            38 | 40 => Some(DrumVoice::Snare),
+            39 => Some(DrumVoice::Clap),
+            42 => Some(DrumVoice::ClosedHiHat),
+            44 => Some(DrumVoice::PedalHiHat),
+            46 => Some(DrumVoice::OpenHiHat),
+            41 | 43 => Some(DrumVoice::LowTom),
+            45 | 47 => Some(DrumVoice::MidTom),
+            48 | 50 => Some(DrumVoice::HighTom),
+            49 | 52 | 55 | 57 => Some(DrumVoice::Crash),
+            51 | 53 | 59 => Some(DrumVoice::Ride),
+            _ => None,
+        }
+    }
+
+    /// The General MIDI drum number of the voice
+    pub fn gm_number(&self) -> u8 {
+        match self {
+            DrumVoice::Kick => 36,
+            DrumVoice::SideStick => 37,
+            DrumVoice::Snare => 38,
+            DrumVoice::Clap => 39,
+            DrumVoice::ClosedHiHat => 42,
+            DrumVoice::PedalHiHat => 44,
+            DrumVoice::OpenHiHat => 46,
+            DrumVoice::LowTom => 41,
+            DrumVoice::MidTom => 45,
+            DrumVoice::HighTom => 48,
+            DrumVoice::Crash => 49,
+            DrumVoice::Ride => 51,
+        }
+    }
+
+    /// The note frequency a drum part stores for this voice
+    pub fn frequency(&self) -> f32 {
+        frequency_from_midi(self.gm_number())
+    }
+
+    pub fn from_frequency(frequency: f32) -> Option<Self> {
+        DrumVoice::from_gm(midi_from_frequency(frequency))
+    }
+
+    /// Finds a voice by its name, ignoring case and spaces
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name: String = name.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_lowercase();
+        DrumVoice::ALL
+            .into_iter()
+            .find(|voice| voice.to_string().replace(' ', "").to_lowercase() == name)
+    }
+
+    /// How many seconds the drum rings for
+    pub fn length(&self) -> f32 {
+        match self {
+            DrumVoice::Kick => 0.6,
+            DrumVoice::SideStick => 0.1,
+            DrumVoice::Snare => 0.4,
+            DrumVoice::Clap => 0.4,
+            DrumVoice::ClosedHiHat => 0.12,
+            DrumVoice::PedalHiHat => 0.2,
+            DrumVoice::OpenHiHat => 1.0,
+            DrumVoice::LowTom | DrumVoice::MidTom | DrumVoice::HighTom => 0.7,
+            DrumVoice::Crash | DrumVoice::Ride => 2.5,
+        }
+    }
+
+    /// Gets the value of the drum `time` seconds after it was hit in range [-1, 1]
+    pub fn sample(&self, time: f32, sample_rate: u32) -> f32 {
+        if time < 0.0 || time >= self.length() {
+            return 0.0;
+        }
+        let index = (time * sample_rate as f32) as u32;
+        // each voice reads its own stretch of noise
+        let seed = (self.gm_number() as u32) << 24;
+        let noise = hash_to_signed_unit(seed.wrapping_add(index));
+        // differences of white noise tilt it towards the highs like a high pass filter
+        let previous_noise = hash_to_signed_unit(seed.wrapping_add(index.wrapping_sub(1)));
+        let bright_noise = (noise - previous_noise) / 2.0;
+        match self {
+            DrumVoice::Kick => {
+                // pitch sweeps from 150 Hz down to 50 Hz, integrated to get the phase
+                let sweep = 0.03;
+                let phase = 50.0 * time + 100.0 * sweep * (1.0 - (-time / sweep).exp());
+                (2.0 * PI * phase).sin() * decay(time, 0.35) * 0.85 + noise * decay(time, 0.003) * 0.15
+            }
+            DrumVoice::SideStick => {
+                (2.0 * PI * 400.0 * time).sin() * decay(time, 0.02) * 0.6 + bright_noise * decay(time, 0.01) * 0.6
+            }
+            DrumVoice::Snare => {
+                let tone = (2.0 * PI * 180.0 * time).sin() * decay(time, 0.08) * 0.35 + (2.0 * PI * 330.0 * time).sin() * decay(time, 0.05) * 0.15;
+                tone + bright_noise * decay(time, 0.15) * 0.5
+            }
+            DrumVoice::Clap => {
+                // three quick bursts followed by a longer tail
+                let burst_time = if time < 0.03 { time % 0.01 } else { time - 0.03 };
+                let level = if time < 0.03 { decay(burst_time, 0.004) } else { decay(burst_time, 0.12) };
+                (bright_noise * 0.7 + noise * 0.3) * level
+            }
+            DrumVoice::ClosedHiHat => bright_noise * decay(time, 0.03),
+            DrumVoice::PedalHiHat => bright_noise * decay(time, 0.05) * 0.7,
+            DrumVoice::OpenHiHat => bright_noise * decay(time, 0.35),
+            DrumVoice::LowTom => tom(time, 90.0),
+            DrumVoice::MidTom => tom(time, 130.0),
+            DrumVoice::HighTom => tom(time, 180.0),
+            DrumVoice::Crash => (bright_noise * 0.8 + noise * 0.2) * decay(time, 0.8),
+            DrumVoice::Ride => {
+                let bell: f32 = [1.0, 1.47, 2.09, 2.56].iter().map(|ratio| (2.0 * PI * 3000.0 * ratio * time).sin()).sum::<f32>() / 4.0;
+                bright_noise * decay(time, 1.0) * 0.4 + bell * decay(time, 1.2) * 0.2
+            }
+        }
+    }
+
+    /// The longest any voice rings for
+    pub fn max_length() -> f32 {
+        DrumVoice::ALL.iter().map(|voice| voice.length()).fold(0.0, f32::max)
+    }
+}
+
+fn decay(time: f32, time_constant: f32) -> f32 {
+    (-time / time_constant).exp()
+}
+
+fn tom(time: f32, frequency: f32) -> f32 {
+    // starts a little sharp and settles onto its pitch
+    let sweep = 0.05;
+    let phase = frequency * time + frequency * 0.5 * sweep * (1.0 - (-time / sweep).exp());
+    (2.0 * PI * phase).sin() * decay(time, 0.3)
+}
+
+impl Display for DrumVoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DrumVoice::Kick => "Kick",
+            DrumVoice::SideStick => "Side Stick",
+            DrumVoice::Snare => "Snare",
+            DrumVoice::Clap => "Clap",
+            DrumVoice::ClosedHiHat => "Closed Hi Hat",
+            DrumVoice::PedalHiHat => "Pedal Hi Hat",
+            DrumVoice::OpenHiHat => "Open Hi Hat",
+            DrumVoice::LowTom => "Low Tom",
+            DrumVoice::MidTom => "Mid Tom",
+            DrumVoice::HighTom => "High Tom",
+            DrumVoice::Crash => "Crash",
+            DrumVoice::Ride => "Ride",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn voices_keep_their_general_midi_number() {
+        for voice in DrumVoice::ALL {
+            assert!(DrumVoice::from_gm(voice.gm_number()) == Some(voice), "{voice}");
+            assert!(DrumVoice::from_frequency(voice.frequency()) == Some(voice), "{voice}");
+        }
+        assert!(DrumVoice::from_gm(35) == Some(DrumVoice::Kick));
+        assert!(DrumVoice::from_gm(60).is_none());
+    }
+
+    #[test]
+    fn voices_are_found_by_name() {
+        assert!(DrumVoice::from_name("closed hihat") == Some(DrumVoice::ClosedHiHat));
+        assert!(DrumVoice::from_name("SNARE") == Some(DrumVoice::Snare));
+        assert!(DrumVoice::from_name("cowbell").is_none());
+    }
+
+    #[test]
+    fn every_voice_sounds_and_then_stops() {
+        let sample_rate = 8000;
+        for voice in DrumVoice::ALL {
+            let samples: Vec<f32> = (0..(voice.length() * sample_rate as f32) as u32)
+                .map(|i| voice.sample(i as f32 / sample_rate as f32, sample_rate))
+                .collect();
+            assert!(samples.iter().any(|sample| sample.abs() > 0.1), "{voice} is silent");
+            assert!(samples.iter().all(|sample| sample.abs() <= 1.0), "{voice} clips");
+            assert_eq!(voice.sample(voice.length(), sample_rate), 0.0);
+        }
+    }
+
+    #[test]
+    fn hits_sound_the_same_every_time() {
+        for voice in DrumVoice::ALL {
+            assert_eq!(voice.sample(0.01, 44100), voice.sample(0.01, 44100));
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt::Display;
+
+use super::filter::{Biquad, BiquadKind};
+use super::serializable::{ByteReader, Serializable};
+
+const COMB_TUNINGS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_TUNINGS: [usize; 4] = [556, 441, 341, 225];
+
+/// An audio effect that processes a buffer of samples in the range [-1, 1]. Effects are chained on a [Bus](super::Bus)
+#[derive(Clone)]
+pub enum Effect {
+    /// Changes the level by `gain` decibels
+    Gain { gain: f32 },
+    /// Freeverb style reverb. All parameters are in range [0, 1]
+    Reverb { room_size: f32, damping: f32, mix: f32 },
+    /// Reduces the level of anything over `threshold` decibels by `ratio`. Attack and release are in milliseconds
+    Compressor { threshold: f32, ratio: f32, attack: f32, release: f32, makeup_gain: f32 },
+    /// Three band equalizer with the gain of each band in decibels
+    Equalizer { low_gain: f32, mid_gain: f32, high_gain: f32 },
+}
+
+impl Effect {
+    pub fn process(&self, samples: &mut [f32], sample_rate: u32) {
+        match *self {
+            Effect::Gain { gain } => {
+                let level = db_to_amplitude(gain);
+                for sample in samples.iter_mut() {
+                    *sample *= level;
+                }
+            }
+            Effect::Reverb { room_size, damping, mix } => reverb(samples, sample_rate, room_size, damping, mix),
+            Effect::Compressor { threshold, ratio, attack, release, makeup_gain } => {
+                compress(samples, sample_rate, threshold, ratio, attack, release, makeup_gain)
+            }
+            Effect::Equalizer { low_gain, mid_gain, high_gain } => {
+                let mut bands = [
+                    Biquad::new(BiquadKind::LowShelf, 200.0, 0.707, low_gain, sample_rate),
+                    Biquad::new(BiquadKind::Peaking, 1000.0, 0.707, mid_gain, sample_rate),
+                    Biquad::new(BiquadKind::HighShelf, 5000.0, 0.707, high_gain, sample_rate),
+                ];
+                for sample in samples.iter_mut() {
+                    for band in bands.iter_mut() {
+                        *sample = band.process(*sample);
+                    }
+                }
+            }
+        }
+    }
+
+    /// How many seconds the effect keeps ringing after its input goes silent
+    pub fn tail_seconds(&self) -> f32 {
+        match *self {
+            Effect::Reverb { room_size, .. } => 1.0 + room_size * 3.0,
+            _ => 0.0,
+        }
+    }
+}
+
+pub fn db_to_amplitude(db: f32) -> f32 {
+    10_f32.powf(db / 20.0)
+}
+
+fn reverb(samples: &mut [f32], sample_rate: u32, room_size: f32, damping: f32, mix: f32) {
+    let scale = sample_rate as f32 / 44100.0;
+    let feedback = room_size.clamp(0.0, 1.0) * 0.28 + 0.7;
+    let damp = damping.clamp(0.0, 1.0) * 0.4;
+    let mix = mix.clamp(0.0, 1.0);
+    let mut combs: Vec<(Vec<f32>, usize, f32)> = COMB_TUNINGS
+        .iter()
+        .map(|tuning| (vec![0.0; ((*tuning as f32 * scale) as usize).max(1)], 0, 0.0))
+        .collect();
+    let mut allpasses: Vec<(Vec<f32>, usize)> = ALLPASS_TUNINGS
+        .iter()
+        .map(|tuning| (vec![0.0; ((*tuning as f32 * scale) as usize).max(1)], 0))
+        .collect();
+    for sample in samples.iter_mut() {
+        let input = *sample * 0.015;
+        let mut wet = 0.0;
+        for (buffer, index, filter_store) in combs.iter_mut() {
+            let output = buffer[*index];
+            *filter_store = output * (1.0 - damp) + *filter_store * damp;
+            buffer[*index] = input + *filter_store * feedback;
+            *index = (*index + 1) % buffer.len();
+            wet += output;
+        }
+        for (buffer, index) in allpasses.iter_mut() {
+            let buffered = buffer[*index];
+            buffer[*index] = wet + buffered * 0.5;
+            wet = buffered - wet;
+            *index = (*index + 1) % buffer.len();
+        }
+        *sample = *sample * (1.0 - mix) + wet * 3.0 * mix;
+    }
+}
+
+fn compress(samples: &mut [f32], sample_rate: u32, threshold: f32, ratio: f32, attack: f32, release: f32, makeup_gain: f32) {
+    let attack_coef = (-1.0 / (attack.max(0.01) / 1000.0 * sample_rate as f32)).exp();
+    let release_coef = (-1.0 / (release.max(0.01) / 1000.0 * sample_rate as f32)).exp();
+    let slope = 1.0 - 1.0 / ratio.max(1.0);
+    let makeup = db_to_amplitude(makeup_gain);
+    let mut envelope = 0.0_f32;
+    for sample in samples.iter_mut() {
+        let level = sample.abs();
+        let coef = if level > envelope { attack_coef } else { release_coef };
+        envelope = coef * envelope + (1.0 - coef) * level;
+        let envelope_db = 20.0 * envelope.max(1e-9).log10();
+        let reduction = if envelope_db > threshold { (envelope_db - threshold) * slope } else { 0.0 };
+        *sample *= db_to_amplitude(-reduction) * makeup;
+    }
+}
+
+impl Serializable for Effect {
+    /// Serializes an `Effect` into a byte representation
+    /// u8: kind
+    /// (params) f32: parameter in the order they are declared
+    fn serialize(&self) -> Result<Vec<u8>, &'static str> {
+        let (kind, params): (u8, Vec<f32>) = match *self {
+            Effect::Gain { gain } => (0, vec![gain]),
+            Effect::Reverb { room_size, damping, mix } => (1, vec![room_size, damping, mix]),
+            Effect::Compressor { threshold, ratio, attack, release, makeup_gain } => {
+                (2, vec![threshold, ratio, attack, release, makeup_gain])
+            }
+            Effect::Equalizer { low_gain, mid_gain, high_gain } => (3, vec![low_gain, mid_gain, high_gain]),
+        };
+        let mut serialized_data = vec![kind];
+        for param in params {
+            serialized_data.extend(param.to_le_bytes());
+        }
+        Ok(serialized_data)
+    }
+
+    fn deserialize(serialized_data: &[u8]) -> Result<Self, &'static str> {
+        let mut reader = ByteReader::new(serialized_data);
+        let err = "Invalid serialized data! Insufficient data for effect";
+        match reader.read_u8(err)? {
+            0 => Ok(Effect::Gain { gain: reader.read_f32(err)? }),
+            1 => Ok(Effect::Reverb {
+                room_size: reader.read_f32(err)?,
+                damping: reader.read_f32(err)?,
+                mix: reader.read_f32(err)?,
+            }),
+            2 => Ok(Effect::Compressor {
+                threshold: reader.read_f32(err)?,
+                ratio: reader.read_f32(err)?,
+                attack: reader.read_f32(err)?,
+                release: reader.read_f32(err)?,
+                makeup_gain: reader.read_f32(err)?,
+            }),
+            3 => Ok(Effect::Equalizer {
+                low_gain: reader.read_f32(err)?,
+                mid_gain: reader.read_f32(err)?,
+                high_gain: reader.read_f32(err)?,
+            }),
+            _ => Err("Invalid serialized data! Unknown effect kind"),
+        }
+    }
+}
+
+impl Display for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Effect::Gain { gain } => write!(f, "Gain({gain} dB)"),
+            Effect::Reverb { room_size, damping, mix } => {
+                write!(f, "Reverb(room size: {room_size}, damping: {damping}, mix: {mix})")
+            }
+            Effect::Compressor { threshold, ratio, attack, release, makeup_gain } => write!(
+                f,
+                "Compressor(threshold: {threshold} dB, ratio: {ratio}:1, attack: {attack} ms, release: {release} ms, makeup: {makeup_gain} dB)"
+            ),
+            Effect::Equalizer { low_gain, mid_gain, high_gain } => {
+                write!(f, "Equalizer(low: {low_gain} dB, mid: {mid_gain} dB, high: {high_gain} dB)")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f32, level: f32, sample_rate: u32, num_samples: usize) -> Vec<f32> {
+        (0..num_samples).map(|i| (i as f32 / sample_rate as f32 * 2.0 * std::f32::consts::PI * frequency).sin() * level).collect()
+    }
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0, |peak, sample| peak.max(sample.abs()))
+    }
+
+    #[test]
+    fn compressor_turns_down_only_what_is_over_the_threshold() {
+        let compressor = Effect::Compressor { threshold: -12.0, ratio: 4.0, attack: 1.0, release: 50.0, makeup_gain: 0.0 };
+        let mut loud = sine(440.0, 1.0, 8000, 8000);
+        let mut quiet = sine(440.0, 0.05, 8000, 8000);
+        compressor.process(&mut loud, 8000);
+        compressor.process(&mut quiet, 8000);
+        assert!(peak(&loud[4000..]) < 0.6);
+        assert!((peak(&quiet[4000..]) - 0.05).abs() < 0.001);
+    }
+
+    #[test]
+    fn reverb_rings_on_after_the_input_stops() {
+        let reverb = Effect::Reverb { room_size: 0.8, damping: 0.2, mix: 0.5 };
+        let mut samples = sine(440.0, 0.5, 8000, 800);
+        samples.resize(8000, 0.0);
+        reverb.process(&mut samples, 8000);
+        assert!(peak(&samples[2000..4000]) > 0.001);
+    }
+
+    #[test]
+    fn equalizer_boosts_the_band_it_is_set_for() {
+        let equalizer = Effect::Equalizer { low_gain: 12.0, mid_gain: 0.0, high_gain: 0.0 };
+        let mut low = sine(60.0, 0.1, 8000, 8000);
+        let mut mid = sine(1000.0, 0.1, 8000, 8000);
+        equalizer.process(&mut low, 8000);
+        equalizer.process(&mut mid, 8000);
+        assert!(peak(&low[4000..]) > 0.3);
+        assert!(peak(&mid[4000..]) < 0.13);
+    }
+
+    #[test]
+    fn effects_are_saved_with_their_settings() {
+        let effects = [
+            Effect::Gain { gain: -3.0 },
+            Effect::Reverb { room_size: 0.5, damping: 0.3, mix: 0.25 },
+            Effect::Compressor { threshold: -18.0, ratio: 3.0, attack: 10.0, release: 100.0, makeup_gain: 2.0 },
+            Effect::Equalizer { low_gain: 1.0, mid_gain: -1.0, high_gain: 2.5 },
+        ];
+        for effect in effects {
+            let loaded = Effect::deserialize(&effect.serialize().unwrap()).unwrap();
+            assert_eq!(loaded.to_string(), effect.to_string());
+        }
+        assert!(Effect::deserialize(&[9]).is_err());
+    }
+}
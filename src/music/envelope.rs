@@ -0,0 +1,111 @@
+use std::fmt::Display;
+
+use super::serializable::{ByteReader, Serializable};
+
+/// An attack, decay, sustain, release envelope. Times are in seconds and sustain is a level in range [0, 1]
+#[derive(Clone, Copy)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32
+}
+
+impl Envelope {
+    pub fn new(attack: f32, decay: f32, sustain: f32, release: f32) -> Self {
+        Envelope { attack, decay, sustain, release }
+    }
+
+    /// Gets the level of the envelope `time` seconds after a note started when the note is held for `held` seconds
+    pub fn level(&self, time: f32, held: f32) -> f32 {
+        if time < 0.0 {
+            return 0.0;
+        }
+        if time < held {
+            return self.held_level(time);
+        }
+        if self.release <= 0.0 {
+            return 0.0;
+        }
+        let released_for = time - held;
+        if released_for >= self.release {
+            return 0.0;
+        }
+        self.held_level(held) * (1.0 - released_for / self.release)
+    }
+
+    fn held_level(&self, time: f32) -> f32 {
+        if time < self.attack {
+            return time / self.attack;
+        }
+        let decay_time = time - self.attack;
+        if decay_time < self.decay {
+            return 1.0 - (1.0 - self.sustain) * decay_time / self.decay;
+        }
+        self.sustain
+    }
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope { attack: 0.0, decay: 0.0, sustain: 1.0, release: 0.0 }
+    }
+}
+
+impl Display for Envelope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Envelope(attack: {}s, decay: {}s, sustain: {}, release: {}s)", self.attack, self.decay, self.sustain, self.release)
+    }
+}
+
+impl Serializable for Envelope {
+    /// Serializes an `Envelope` struct into a byte representation
+    /// f32: attack
+    /// f32: decay
+    /// f32: sustain
+    /// f32: release
+    fn serialize(&self) -> Result<Vec<u8>, &'static str> {
+        let mut serialized_data = Vec::new();
+        for value in [self.attack, self.decay, self.sustain, self.release] {
+            serialized_data.extend(value.to_le_bytes());
+        }
+        Ok(serialized_data)
+    }
+
+    fn deserialize(serialized_data: &[u8]) -> Result<Self, &'static str> {
+        if serialized_data.len() != 16 {
+            return Err("Invalid serialized data! Insuffient data for envelope");
+        }
+        let mut reader = ByteReader::new(serialized_data);
+        let err = "Invalid serialized data! Insuffient data for envelope";
+        Ok(Envelope {
+            attack: reader.read_f32(err)?,
+            decay: reader.read_f32(err)?,
+            sustain: reader.read_f32(err)?,
+            release: reader.read_f32(err)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn goes_through_attack_decay_sustain_and_release() {
+        let envelope = Envelope::new(0.1, 0.2, 0.5, 0.4);
+        assert_eq!(envelope.level(-0.1, 1.0), 0.0);
+        assert!((envelope.level(0.05, 1.0) - 0.5).abs() < 0.0001);
+        assert!((envelope.level(0.2, 1.0) - 0.75).abs() < 0.0001);
+        assert_eq!(envelope.level(0.8, 1.0), 0.5);
+        // released at 1 second, halfway through the release
+        assert!((envelope.level(1.2, 1.0) - 0.25).abs() < 0.0001);
+        assert_eq!(envelope.level(1.5, 1.0), 0.0);
+    }
+
+    #[test]
+    fn notes_released_during_the_attack_fade_from_where_they_got_to() {
+        let envelope = Envelope::new(1.0, 0.0, 1.0, 1.0);
+        assert!((envelope.level(0.75, 0.5) - 0.375).abs() < 0.0001);
+    }
+}
@@ -0,0 +1,143 @@
+use std::f32::consts::PI;
+
+/// The shape of a [Biquad] filter
+#[derive(Clone, Copy)]
+pub enum BiquadKind {
+    LowPass,
+    LowShelf,
+    HighShelf,
+    Peaking,
+}
+
+/// A resonant low pass filter on the output of a [Part](super::Part)
+#[derive(Clone, Copy)]
+pub struct PartFilter {
+    // hz
+    pub cutoff: f32,
+    // the q of the filter. 0.707 has no resonant peak
+    pub resonance: f32
+}
+
+/// A second order IIR filter using the coefficients from the RBJ audio eq cookbook
+#[derive(Clone, Copy)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    /// Creates a filter. `gain` is in decibels and is only used by the shelf and peaking kinds
+    pub fn new(kind: BiquadKind, frequency: f32, q: f32, gain: f32, sample_rate: u32) -> Self {
+        let mut filter = Biquad { b0: 1.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0, x1: 0.0, x2: 0.0, y1: 0.0, y2: 0.0 };
+        filter.set(kind, frequency, q, gain, sample_rate);
+        filter
+    }
+
+    /// Recalculates the coefficients while keeping the filter history so it can be swept without clicks
+    pub fn set(&mut self, kind: BiquadKind, frequency: f32, q: f32, gain: f32, sample_rate: u32) {
+        let nyquist = sample_rate as f32 / 2.0;
+        let frequency = frequency.clamp(10.0, nyquist * 0.99);
+        let q = q.max(0.01);
+        let a = 10_f32.powf(gain / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate as f32;
+        let (sin_w0, cos_w0) = w0.sin_cos();
+        let alpha = sin_w0 / (2.0 * q);
+        let (b0, b1, b2, a0, a1, a2) = match kind {
+            BiquadKind::LowPass => (
+                (1.0 - cos_w0) / 2.0,
+                1.0 - cos_w0,
+                (1.0 - cos_w0) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos_w0,
+                1.0 - alpha,
+            ),
+            BiquadKind::Peaking => (
+                1.0 + alpha * a,
+                -2.0 * cos_w0,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos_w0,
+                1.0 - alpha / a,
+            ),
+            BiquadKind::LowShelf => {
+                let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos_w0),
+                    a * ((a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha),
+                    (a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos_w0),
+                    (a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha,
+                )
+            }
+            BiquadKind::HighShelf => {
+                let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_w0),
+                    a * ((a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha),
+                    (a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos_w0),
+                    (a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha,
+                )
+            }
+        };
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.b1 * self.x1 + self.b2 * self.x2 - self.a1 * self.y1 - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = input;
+        self.y2 = self.y1;
+        self.y1 = output;
+        output
+    }
+}
+
+/// Low pass filters samples that were rendered at `factor` times the target sample rate and keeps every `factor`th one.
+/// Uses a Blackman windowed sinc with its cutoff just under the nyquist frequency of the target rate
+pub fn decimate(samples: &[f32], factor: usize) -> Vec<f32> {
+    if factor <= 1 {
+        return samples.to_vec();
+    }
+    let half_taps = 16 * factor;
+    let num_taps = 2 * half_taps + 1;
+    let cutoff = 0.45 / factor as f32;
+    let mut taps: Vec<f32> = (0..num_taps)
+        .map(|i| {
+            let n = i as f32 - half_taps as f32;
+            let sinc = if n == 0.0 { 2.0 * cutoff } else { (2.0 * PI * cutoff * n).sin() / (PI * n) };
+            let window_phase = 2.0 * PI * i as f32 / (num_taps - 1) as f32;
+            let window = 0.42 - 0.5 * window_phase.cos() + 0.08 * (2.0 * window_phase).cos();
+            sinc * window
+        })
+        .collect();
+    let sum: f32 = taps.iter().sum();
+    for tap in taps.iter_mut() {
+        *tap /= sum;
+    }
+    (0..samples.len() / factor)
+        .map(|output| {
+            let center = output * factor;
+            taps.iter()
+                .enumerate()
+                .filter_map(|(i, tap)| {
+                    let index = (center + i).checked_sub(half_taps)?;
+                    samples.get(index).map(|sample| sample * tap)
+                })
+                .sum()
+        })
+        .collect()
+}
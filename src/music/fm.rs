@@ -0,0 +1,252 @@
+use std::f32::consts::PI;
+use std::fmt::Display;
+
+use super::envelope::Envelope;
+use super::serializable::{ByteReader, Serializable};
+
+pub const NUM_OPERATORS: usize = 4;
+
+/// Which operators modulate each operator and which operators are heard, for each of the 4 operator algorithms.
+/// Operators only ever modulate operators after them so they can be computed in order
+const ALGORITHMS: [([&[usize]; NUM_OPERATORS], &[usize]); 8] = [
+    // 1 -> 2 -> 3 -> 4
+    ([&[], &[0], &[1], &[2]], &[3]),
+    // (1 + 2) -> 3 -> 4
+    ([&[], &[], &[0, 1], &[2]], &[3]),
+    // (1 + (2 -> 3)) -> 4
+    ([&[], &[], &[1], &[0, 2]], &[3]),
+    // ((1 -> 2) + 3) -> 4
+    ([&[], &[0], &[], &[1, 2]], &[3]),
+    // (1 -> 2) + (3 -> 4)
+    ([&[], &[0], &[], &[2]], &[1, 3]),
+    // 1 -> (2 + 3 + 4)
+    ([&[], &[0], &[0], &[0]], &[1, 2, 3]),
+    // (1 -> 2) + 3 + 4
+    ([&[], &[0], &[], &[]], &[1, 2, 3]),
+    // 1 + 2 + 3 + 4
+    ([&[], &[], &[], &[]], &[0, 1, 2, 3]),
+];
+
+pub const NUM_ALGORITHMS: usize = ALGORITHMS.len();
+
+/// One sine oscillator of an [FmSynth]
+#[derive(Clone, Copy)]
+pub struct Operator {
+    // frequency of the operator relative to the note frequency
+    pub ratio: f32,
+    // modulation index in radians when modulating, output level when heard
+    pub index: f32,
+    pub envelope: Envelope
+}
+
+impl Operator {
+    pub fn new(ratio: f32, index: f32, envelope: Envelope) -> Self {
+        Operator { ratio, index, envelope }
+    }
+}
+
+/// A 4 operator frequency modulation synthesizer
+#[derive(Clone)]
+pub struct FmSynth {
+    // index into the list of algorithms, see [FmSynth::describe_algorithm]
+    pub algorithm: usize,
+    pub operators: [Operator; NUM_OPERATORS]
+}
+
+impl FmSynth {
+    pub fn new(algorithm: usize, operators: [Operator; NUM_OPERATORS]) -> Result<Self, &'static str> {
+        if algorithm >= NUM_ALGORITHMS {
+            return Err("FM algorithm must be in range [0, 7]");
+        }
+        Ok(FmSynth { algorithm, operators })
+    }
+
+    /// Gets the value of a note `time` seconds after it started when it is held for `held` seconds.
+    /// `phase` is how many cycles of the note frequency have played
+    pub fn sample(&self, phase: f64, time: f32, held: f32) -> f32 {
+        let (modulators, carriers) = ALGORITHMS[self.algorithm];
+        let mut outputs = [0.0_f32; NUM_OPERATORS];
+        for (index, operator) in self.operators.iter().enumerate() {
+            let modulation: f32 = modulators[index].iter().map(|modulator| outputs[*modulator]).sum();
+            let operator_phase = 2.0 * PI * (phase * operator.ratio as f64).fract() as f32 + modulation;
+            outputs[index] = operator_phase.sin() * operator.index * operator.envelope.level(time, held);
+        }
+        carriers.iter().map(|carrier| outputs[*carrier]).sum::<f32>() / carriers.len() as f32
+    }
+
+    /// How long the synth keeps sounding after a note is released
+    pub fn release_seconds(&self) -> f32 {
+        let (_, carriers) = ALGORITHMS[self.algorithm];
+        carriers.iter().map(|carrier| self.operators[*carrier].envelope.release).fold(0.0, f32::max)
+    }
+
+    pub fn describe_algorithm(algorithm: usize) -> &'static str {
+        match algorithm {
+            0 => "1 -> 2 -> 3 -> 4",
+            1 => "(1 + 2) -> 3 -> 4",
+            2 => "(1 + (2 -> 3)) -> 4",
+            3 => "((1 -> 2) + 3) -> 4",
+            4 => "(1 -> 2) + (3 -> 4)",
+            5 => "1 -> (2 + 3 + 4)",
+            6 => "(1 -> 2) + 3 + 4",
+            _ => "1 + 2 + 3 + 4",
+        }
+    }
+
+    /// The FM patches that ship with the editor as (name, synth) pairs
+    pub fn presets() -> Vec<(String, FmSynth)> {
+        vec![
+            ("Electric Piano".to_string(), FmSynth {
+                algorithm: 4,
+                operators: [
+                    Operator::new(14.0, 0.6, Envelope::new(0.0, 0.3, 0.0, 0.1)),
+                    Operator::new(1.0, 1.0, Envelope::new(0.002, 1.5, 0.4, 0.3)),
+                    Operator::new(1.0, 1.8, Envelope::new(0.0, 0.8, 0.2, 0.3)),
+                    Operator::new(1.0, 1.0, Envelope::new(0.002, 2.0, 0.3, 0.3)),
+                ]
+            }),
+            ("Bell".to_string(), FmSynth {
+                algorithm: 4,
+                operators: [
+                    Operator::new(3.5, 3.0, Envelope::new(0.0, 3.0, 0.0, 1.5)),
+                    Operator::new(1.0, 1.0, Envelope::new(0.0, 4.0, 0.0, 1.5)),
+                    Operator::new(1.41, 2.0, Envelope::new(0.0, 2.0, 0.0, 1.0)),
+                    Operator::new(2.0, 0.6, Envelope::new(0.0, 2.5, 0.0, 1.0)),
+                ]
+            }),
+            ("Bass".to_string(), FmSynth {
+                algorithm: 0,
+                operators: [
+                    Operator::new(1.0, 0.5, Envelope::new(0.0, 0.2, 0.0, 0.05)),
+                    Operator::new(0.5, 1.5, Envelope::new(0.0, 0.4, 0.3, 0.05)),
+                    Operator::new(1.0, 2.0, Envelope::new(0.0, 0.3, 0.2, 0.05)),
+                    Operator::new(0.5, 1.0, Envelope::new(0.005, 0.5, 0.8, 0.08)),
+                ]
+            }),
+            ("Brass".to_string(), FmSynth {
+                algorithm: 0,
+                operators: [
+                    Operator::new(1.0, 0.5, Envelope::new(0.05, 0.2, 0.6, 0.1)),
+                    Operator::new(1.0, 1.0, Envelope::new(0.05, 0.2, 0.7, 0.1)),
+                    Operator::new(1.0, 2.5, Envelope::new(0.08, 0.3, 0.6, 0.1)),
+                    Operator::new(1.0, 1.0, Envelope::new(0.06, 0.2, 0.9, 0.15)),
+                ]
+            }),
+        ]
+    }
+}
+
+impl Default for FmSynth {
+    /// A starting point for new patches: operator 3 modulates the carrier at the note's frequency and the other
+    /// operators are silent
+    fn default() -> Self {
+        let mut operators = [Operator::new(1.0, 0.0, Envelope::default()); NUM_OPERATORS];
+        operators[2].index = 1.0;
+        operators[3].index = 1.0;
+        FmSynth { algorithm: 0, operators }
+    }
+}
+
+impl Display for FmSynth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "FM algorithm {}: {}", self.algorithm + 1, FmSynth::describe_algorithm(self.algorithm))?;
+        for (index, operator) in self.operators.iter().enumerate() {
+            writeln!(f, "\t{}. ratio: {}, index: {}, {}", index + 1, operator.ratio, operator.index, operator.envelope)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serializable for FmSynth {
+    /// Serializes an `FmSynth` struct into a byte representation
+    /// u8: algorithm
+    /// (operators) f32: ratio
+    /// (operators) f32: index
+    /// (operators) 16: envelope
+    fn serialize(&self) -> Result<Vec<u8>, &'static str> {
+        let mut serialized_data = vec![self.algorithm as u8];
+        for operator in &self.operators {
+            serialized_data.extend(operator.ratio.to_le_bytes());
+            serialized_data.extend(operator.index.to_le_bytes());
+            serialized_data.extend(operator.envelope.serialize()?);
+        }
+        Ok(serialized_data)
+    }
+
+    fn deserialize(serialized_data: &[u8]) -> Result<Self, &'static str> {
+        let err = "Invalid serialized data! Insufficient data for FM synth";
+        let mut reader = ByteReader::new(serialized_data);
+        let algorithm = reader.read_u8(err)? as usize;
+        let mut operators = [Operator::new(1.0, 0.0, Envelope::default()); NUM_OPERATORS];
+        for operator in operators.iter_mut() {
+            operator.ratio = reader.read_f32(err)?;
+            operator.index = reader.read_f32(err)?;
+            operator.envelope = Envelope::deserialize(reader.read_bytes(16, err)?)?;
+        }
+        FmSynth::new(algorithm, operators)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn silent_operators() -> [Operator; NUM_OPERATORS] {
+        [Operator::new(1.0, 0.0, Envelope::default()); NUM_OPERATORS]
+    }
+
+    #[test]
+    fn an_unmodulated_carrier_is_a_sine() {
+        let mut operators = silent_operators();
+        operators[3].index = 1.0;
+        let synth = FmSynth::new(0, operators).unwrap();
+        for i in 0..100 {
+            let time = i as f32 / 8000.0;
+            assert!((synth.sample(220.0 * time as f64, time, 1.0) - (2.0 * PI * 220.0 * time).sin()).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn the_default_patch_is_modulated() {
+        let synth = FmSynth::default();
+        let largest_difference = (0..400)
+            .map(|i| i as f32 / 8000.0)
+            .map(|time| (synth.sample(220.0 * time as f64, time, 1.0) - (2.0 * PI * 220.0 * time).sin()).abs())
+            .fold(0.0, f32::max);
+        assert!(largest_difference > 0.1);
+    }
+
+    #[test]
+    fn algorithms_mix_their_carriers() {
+        // every operator is heard in the last algorithm
+        let mut operators = silent_operators();
+        for (index, operator) in operators.iter_mut().enumerate() {
+            operator.ratio = index as f32 + 1.0;
+            operator.index = 1.0;
+        }
+        let synth = FmSynth::new(7, operators).unwrap();
+        let time = 0.0013;
+        let expected = (1..=4).map(|ratio| (2.0 * PI * 100.0 * ratio as f32 * time).sin()).sum::<f32>() / 4.0;
+        assert!((synth.sample(100.0 * time as f64, time, 1.0) - expected).abs() < 0.0001);
+        assert!(FmSynth::new(NUM_ALGORITHMS, operators).is_err());
+    }
+
+    #[test]
+    fn notes_ring_on_for_the_release_of_the_carriers() {
+        let mut operators = silent_operators();
+        operators[3] = Operator::new(1.0, 1.0, Envelope::new(0.0, 0.0, 1.0, 0.5));
+        // the modulator's release doesn't keep the note sounding
+        operators[2] = Operator::new(1.0, 1.0, Envelope::new(0.0, 0.0, 1.0, 2.0));
+        let synth = FmSynth::new(0, operators).unwrap();
+        assert_eq!(synth.release_seconds(), 0.5);
+        assert_eq!(synth.sample(100.0 * 1.6, 1.6, 1.0), 0.0);
+    }
+
+    #[test]
+    fn patches_are_saved_with_every_operator() {
+        for (name, synth) in FmSynth::presets() {
+            let loaded = FmSynth::deserialize(&synth.serialize().unwrap()).unwrap();
+            assert_eq!(loaded.to_string(), synth.to_string(), "{name} changed when saved");
+        }
+    }
+}
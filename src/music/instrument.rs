@@ -0,0 +1,159 @@
+use std::fmt::Display;
+
+use super::drums::DrumVoice;
+use super::fm::FmSynth;
+use super::oscillator::Waveform;
+use super::serializable::{write_string, ByteReader, Serializable};
+use super::wavetable::WavetableSynth;
+
+/// The voice a [Part](super::Part) plays its notes with
+#[derive(Clone)]
+pub enum Instrument {
+    Oscillator(Waveform),
+    Fm(FmSynth),
+    Wavetable(WavetableSynth),
+    // synthesized drum kit that picks the drum from the General MIDI number of the note
+    Drums
+}
+
+impl Instrument {
+    /// Gets the value of a note currently playing at `frequency` in range [-1, 1].
+    /// `phase` is how many cycles the voice has played, `note_time` the seconds since the note started and `held` how many seconds the note is held for
+    pub fn sample(&self, phase: f64, frequency: f32, note_time: f32, held: f32, sample_rate: u32) -> f32 {
+        match self {
+            Instrument::Oscillator(waveform) => waveform.sample(phase.fract() as f32, frequency / sample_rate as f32),
+            Instrument::Fm(synth) => synth.sample(phase, note_time, held),
+            Instrument::Wavetable(synth) => synth.sample(phase, frequency, note_time, held, sample_rate),
+            Instrument::Drums => match DrumVoice::from_frequency(frequency) {
+                Some(voice) => voice.sample(note_time, sample_rate),
+                None => 0.0
+            }
+        }
+    }
+
+    /// How long a note keeps sounding after it is released
+    pub fn release_seconds(&self) -> f32 {
+        match self {
+            Instrument::Oscillator(_) => 0.0,
+            Instrument::Fm(synth) => synth.release_seconds(),
+            Instrument::Wavetable(synth) => synth.envelope.release,
+            // drums ring out for their full length however short the note is
+            Instrument::Drums => DrumVoice::max_length()
+        }
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Instrument::Oscillator(_) => "Oscillator",
+            Instrument::Fm(_) => "FM",
+            Instrument::Wavetable(_) => "Wavetable",
+            Instrument::Drums => "Drums"
+        }
+    }
+}
+
+impl Default for Instrument {
+    fn default() -> Self {
+        Instrument::Oscillator(Waveform::Sine)
+    }
+}
+
+impl Display for Instrument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instrument::Oscillator(waveform) => writeln!(f, "{waveform} oscillator"),
+            Instrument::Fm(synth) => write!(f, "{synth}"),
+            Instrument::Wavetable(synth) => write!(f, "{synth}"),
+            Instrument::Drums => writeln!(f, "Drum kit")
+        }
+    }
+}
+
+impl Serializable for Instrument {
+    /// Serializes an `Instrument` into a byte representation
+    /// u8: kind
+    /// (rest): data of the kind
+    fn serialize(&self) -> Result<Vec<u8>, &'static str> {
+        let mut serialized_data = Vec::new();
+        match self {
+            Instrument::Oscillator(waveform) => {
+                serialized_data.push(0);
+                serialized_data.push(waveform.to_u8());
+            }
+            Instrument::Fm(synth) => {
+                serialized_data.push(1);
+                serialized_data.extend(synth.serialize()?);
+            }
+            Instrument::Wavetable(synth) => {
+                serialized_data.push(2);
+                serialized_data.extend(synth.serialize()?);
+            }
+            Instrument::Drums => serialized_data.push(3)
+        }
+        Ok(serialized_data)
+    }
+
+    fn deserialize(serialized_data: &[u8]) -> Result<Self, &'static str> {
+        if serialized_data.is_empty() {
+            return Err("Invalid serialized data! Insufficient data for instrument");
+        }
+        match serialized_data[0] {
+            // Instruments saved before waveforms existed are a lone sine
+            0 => match serialized_data.get(1) {
+                Some(waveform) => match Waveform::from_u8(*waveform) {
+                    Some(waveform) => Ok(Instrument::Oscillator(waveform)),
+                    None => Err("Invalid serialized data! Unknown waveform")
+                },
+                None => Ok(Instrument::Oscillator(Waveform::Sine))
+            },
+            1 => Ok(Instrument::Fm(FmSynth::deserialize(&serialized_data[1..])?)),
+            2 => Ok(Instrument::Wavetable(WavetableSynth::deserialize(&serialized_data[1..])?)),
+            3 => Ok(Instrument::Drums),
+            _ => Err("Invalid serialized data! Unknown instrument kind")
+        }
+    }
+}
+
+/// A named [Instrument] that can be applied to parts. Songs store their own presets next to the ones that ship with the editor
+#[derive(Clone)]
+pub struct Preset {
+    pub name: String,
+    pub instrument: Instrument
+}
+
+impl Preset {
+    pub fn new(name: String, instrument: Instrument) -> Self {
+        Preset { name, instrument }
+    }
+
+    /// The presets that ship with the editor
+    pub fn built_in() -> Vec<Preset> {
+        let fm = FmSynth::presets()
+            .into_iter()
+            .map(|(name, synth)| Preset::new(name, Instrument::Fm(synth)));
+        let wavetable = WavetableSynth::presets()
+            .into_iter()
+            .map(|(name, synth)| Preset::new(name, Instrument::Wavetable(synth)));
+        fm.chain(wavetable).collect()
+    }
+}
+
+impl Serializable for Preset {
+    /// Serializes a `Preset` struct into a byte representation
+    /// u16: name_len
+    /// name_len: name
+    /// (rest): instrument
+    fn serialize(&self) -> Result<Vec<u8>, &'static str> {
+        let mut serialized_data = Vec::new();
+        write_string(&mut serialized_data, &self.name, "Could not serialize preset. Name too long!")?;
+        serialized_data.extend(self.instrument.serialize()?);
+        Ok(serialized_data)
+    }
+
+    fn deserialize(serialized_data: &[u8]) -> Result<Self, &'static str> {
+        let mut reader = ByteReader::new(serialized_data);
+        let name = reader.read_string("Invalid serialized data! Insufficient length for preset name")?;
+        let instrument = Instrument::deserialize(reader.read_rest())?;
+        Ok(Preset { name, instrument })
+    }
+}
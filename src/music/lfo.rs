@@ -0,0 +1,220 @@
+use std::f32::consts::PI;
+use std::fmt::Display;
+
+use super::random::hash_to_signed_unit;
+use super::serializable::{ByteReader, Serializable};
+
+/// The shape of an [Lfo]
+#[derive(Clone, Copy, PartialEq)]
+pub enum LfoShape {
+    Sine,
+    Triangle,
+    Square,
+    /// Jumps to a new random value every cycle. The values are the same every render
+    Random,
+}
+
+/// What an [Lfo] modulates
+#[derive(Clone, Copy, PartialEq)]
+pub enum LfoTarget {
+    /// Vibrato. Depth is in semitones
+    Pitch,
+    /// Tremolo. Depth is how much of the volume is taken away at the bottom of the cycle, in range [0, 1]
+    Amplitude,
+    /// Moves the cutoff of the part filter. Depth is in octaves
+    FilterCutoff,
+}
+
+/// A low frequency oscillator of a [Part](super::Part). It waits `delay` seconds after a note starts
+/// and then fades in to its full depth over `fade_in` seconds
+#[derive(Clone, Copy)]
+pub struct Lfo {
+    pub shape: LfoShape,
+    pub target: LfoTarget,
+    // cycles per second
+    pub rate: f32,
+    pub depth: f32,
+    pub delay: f32,
+    pub fade_in: f32
+}
+
+impl Lfo {
+    pub fn new(shape: LfoShape, target: LfoTarget, rate: f32, depth: f32, delay: f32, fade_in: f32) -> Self {
+        Lfo { shape, target, rate, depth, delay, fade_in }
+    }
+
+    /// Gets the value of the LFO scaled by its depth `time` seconds after a note started
+    pub fn value(&self, time: f32) -> f32 {
+        let (value, fade) = self.shape_value(time);
+        value * self.depth * fade
+    }
+
+    /// Gets how much to scale the volume by `time` seconds after a note started, dipping by at most the depth
+    pub fn gain(&self, time: f32) -> f32 {
+        let (value, fade) = self.shape_value(time);
+        1.0 - (1.0 - value) / 2.0 * self.depth * fade
+    }
+
+    /// Gets the value of the shape in range [-1, 1] and how far the LFO has faded in
+    fn shape_value(&self, time: f32) -> (f32, f32) {
+        let time = time - self.delay;
+        if time < 0.0 {
+            return (0.0, 0.0);
+        }
+        let fade = if self.fade_in > 0.0 { (time / self.fade_in).min(1.0) } else { 1.0 };
+        let cycles = time * self.rate;
+        let phase = cycles.fract();
+        let value = match self.shape {
+            LfoShape::Sine => (2.0 * PI * phase).sin(),
+            LfoShape::Triangle => {
+                if phase < 0.25 {
+                    4.0 * phase
+                } else if phase < 0.75 {
+                    2.0 - 4.0 * phase
+                } else {
+                    4.0 * phase - 4.0
+                }
+            }
+            LfoShape::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            LfoShape::Random => hash_to_signed_unit(cycles.floor() as u32),
+        };
+        (value, fade)
+    }
+
+    fn shape_to_u8(&self) -> u8 {
+        match self.shape {
+            LfoShape::Sine => 0,
+            LfoShape::Triangle => 1,
+            LfoShape::Square => 2,
+            LfoShape::Random => 3,
+        }
+    }
+
+    fn target_to_u8(&self) -> u8 {
+        match self.target {
+            LfoTarget::Pitch => 0,
+            LfoTarget::Amplitude => 1,
+            LfoTarget::FilterCutoff => 2,
+        }
+    }
+}
+
+impl Display for LfoShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LfoShape::Sine => write!(f, "sine"),
+            LfoShape::Triangle => write!(f, "triangle"),
+            LfoShape::Square => write!(f, "square"),
+            LfoShape::Random => write!(f, "random"),
+        }
+    }
+}
+
+impl Display for LfoTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LfoTarget::Pitch => write!(f, "pitch"),
+            LfoTarget::Amplitude => write!(f, "amplitude"),
+            LfoTarget::FilterCutoff => write!(f, "filter cutoff"),
+        }
+    }
+}
+
+impl Display for Lfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LFO({} -> {}, rate: {}Hz, depth: {}, delay: {}s, fade in: {}s)", self.shape, self.target, self.rate, self.depth, self.delay, self.fade_in)
+    }
+}
+
+impl Serializable for Lfo {
+    /// Serializes an `Lfo` struct into a byte representation
+    /// u8: shape
+    /// u8: target
+    /// f32: rate
+    /// f32: depth
+    /// f32: delay
+    /// f32: fade_in
+    fn serialize(&self) -> Result<Vec<u8>, &'static str> {
+        let mut serialized_data = vec![self.shape_to_u8(), self.target_to_u8()];
+        for value in [self.rate, self.depth, self.delay, self.fade_in] {
+            serialized_data.extend(value.to_le_bytes());
+        }
+        Ok(serialized_data)
+    }
+
+    fn deserialize(serialized_data: &[u8]) -> Result<Self, &'static str> {
+        let err = "Invalid serialized data! Insufficient data for LFO";
+        let mut reader = ByteReader::new(serialized_data);
+        let shape = match reader.read_u8(err)? {
+            0 => LfoShape::Sine,
+            1 => LfoShape::Triangle,
+            2 => LfoShape::Square,
+            3 => LfoShape::Random,
+            _ => return Err("Invalid serialized data! Unknown LFO shape")
+        };
+        let target = match reader.read_u8(err)? {
+            0 => LfoTarget::Pitch,
+            1 => LfoTarget::Amplitude,
+            2 => LfoTarget::FilterCutoff,
+            _ => return Err("Invalid serialized data! Unknown LFO target")
+        };
+        Ok(Lfo {
+            shape,
+            target,
+            rate: reader.read_f32(err)?,
+            depth: reader.read_f32(err)?,
+            delay: reader.read_f32(err)?,
+            fade_in: reader.read_f32(err)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_peak_a_quarter_of_the_way_through_a_cycle() {
+        for shape in [LfoShape::Sine, LfoShape::Triangle, LfoShape::Square] {
+            let lfo = Lfo::new(shape, LfoTarget::Pitch, 2.0, 0.5, 0.0, 0.0);
+            assert!((lfo.value(0.125) - 0.5).abs() < 1e-4, "{shape}");
+            assert!((lfo.value(0.375) + 0.5).abs() < 1e-4, "{shape}");
+        }
+    }
+
+    #[test]
+    fn random_values_hold_for_a_cycle_and_repeat_every_render() {
+        let lfo = Lfo::new(LfoShape::Random, LfoTarget::Pitch, 1.0, 1.0, 0.0, 0.0);
+        assert_eq!(lfo.value(2.1), lfo.value(2.9));
+        assert_ne!(lfo.value(2.5), lfo.value(3.5));
+        let again = Lfo::new(LfoShape::Random, LfoTarget::Pitch, 1.0, 1.0, 0.0, 0.0);
+        assert_eq!(lfo.value(3.5), again.value(3.5));
+        for cycle in 0..100 {
+            assert!(lfo.value(cycle as f32).abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn waits_for_the_delay_and_then_fades_in() {
+        let lfo = Lfo::new(LfoShape::Square, LfoTarget::Pitch, 1.0, 1.0, 0.5, 1.0);
+        assert_eq!(lfo.value(0.4), 0.0);
+        assert!((lfo.value(0.75) - 0.25).abs() < 1e-4);
+        assert!((lfo.value(2.75) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn gain_dips_by_at_most_the_depth() {
+        let lfo = Lfo::new(LfoShape::Sine, LfoTarget::Amplitude, 1.0, 0.5, 0.0, 0.0);
+        assert!((lfo.gain(0.25) - 1.0).abs() < 1e-4);
+        assert!((lfo.gain(0.75) - 0.5).abs() < 1e-4);
+        assert_eq!(lfo.gain(0.0), 0.75);
+    }
+
+    #[test]
+    fn lfos_are_saved_with_their_settings() {
+        let lfo = Lfo::new(LfoShape::Triangle, LfoTarget::FilterCutoff, 5.5, 2.0, 0.25, 0.75);
+        let loaded = Lfo::deserialize(&lfo.serialize().unwrap()).unwrap();
+        assert_eq!(loaded.to_string(), lfo.to_string());
+        assert!(Lfo::deserialize(&lfo.serialize().unwrap()[..10]).is_err());
+    }
+}
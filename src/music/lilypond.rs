@@ -0,0 +1,189 @@
+use std::fmt::Write;
+
+use super::drums::DrumVoice;
+use super::part::Part;
+use super::pitch::frequency_from_midi;
+use super::score::{self, NoteValue, Segment};
+use super::signature::Key;
+use super::song::Song;
+
+const LILYPOND_VERSION: &str = "2.24.0";
+
+/// Writes a song as LilyPond source with one staff per [Part], or a drum staff for drum parts. Bars follow the
+/// song's time signature, pitches are spelled from the key, note lengths are split into written note values tied
+/// together and tied across bar lines, and gaps become rests. The same song always gives the same text
+pub fn song_to_lilypond(song: &Song) -> Result<String, &'static str> {
+    let err = "Could not write LilyPond source!";
+    let mut ly = String::new();
+    writeln!(ly, "\\version \"{LILYPOND_VERSION}\"").map_err(|_| err)?;
+    writeln!(ly).map_err(|_| err)?;
+    writeln!(ly, "\\header {{").map_err(|_| err)?;
+    writeln!(ly, "  title = \"{}\"", escape(&song.name)).map_err(|_| err)?;
+    writeln!(ly, "}}").map_err(|_| err)?;
+    writeln!(ly).map_err(|_| err)?;
+    writeln!(ly, "\\score {{").map_err(|_| err)?;
+    writeln!(ly, "  <<").map_err(|_| err)?;
+    let num_bars = score::num_bars(song.duration(), song.time_signature.bar_beats());
+    // patterns are written out as notes wherever they are placed
+    let parts = song.arranged_parts();
+    for (index, part) in parts.iter().enumerate() {
+        write_staff(&mut ly, song, part, index == 0, num_bars).map_err(|_| err)?;
+    }
+    writeln!(ly, "  >>").map_err(|_| err)?;
+    writeln!(ly, "  \\layout {{ }}").map_err(|_| err)?;
+    writeln!(ly, "}}").map_err(|_| err)?;
+    Ok(ly)
+}
+
+fn write_staff(ly: &mut String, song: &Song, part: &Part, first: bool, num_bars: usize) -> std::fmt::Result {
+    let time = song.time_signature;
+    if part.is_percussion() {
+        writeln!(ly, "    \\new DrumStaff \\with {{ instrumentName = \"{}\" }} \\drummode {{", escape(&part.name))?;
+    } else {
+        writeln!(ly, "    \\new Staff \\with {{ instrumentName = \"{}\" }} {{", escape(&part.name))?;
+        writeln!(ly, "      \\clef {}", if score::uses_bass_clef(&part.notes) { "bass" } else { "treble" })?;
+        let (letter, alter, _) = song.key.spell(song.key.tonic);
+        writeln!(ly, "      \\key {} \\{}", note_name(letter, alter), if song.key.minor { "minor" } else { "major" })?;
+    }
+    writeln!(ly, "      \\time {time}")?;
+    if first {
+        writeln!(ly, "      \\tempo 4 = {}", song.bpm)?;
+    }
+    for bar in score::bars(&part.notes, time.bar_beats(), num_bars) {
+        if bar.iter().all(|segment| segment.pitches.is_empty()) {
+            writeln!(ly, "      R1*{time} |")?;
+            continue;
+        }
+        let mut tokens: Vec<String> = Vec::new();
+        let mut in_tuplet = false;
+        for segment in &bar {
+            let values = score::note_values(segment.duration);
+            for (value_index, value) in values.iter().enumerate() {
+                if value.triplet != in_tuplet {
+                    tokens.push(if value.triplet { "\\tuplet 3/2 {".to_string() } else { "}".to_string() });
+                    in_tuplet = value.triplet;
+                }
+                let last_value = value_index + 1 == values.len();
+                tokens.push(write_event(song, part, segment, value, last_value));
+            }
+        }
+        if in_tuplet {
+            tokens.push("}".to_string());
+        }
+        writeln!(ly, "      {} |", tokens.join(" "))?;
+    }
+    writeln!(ly, "      \\bar \"|.\"")?;
+    writeln!(ly, "    }}")
+}
+
+/// Writes one note, chord or rest lasting a single note value. Every pitch is tied into the next value of
+/// the same segment, and pitches that keep sounding past the segment are tied into the next one
+fn write_event(song: &Song, part: &Part, segment: &Segment, value: &NoteValue, last_value: bool) -> String {
+    let duration = duration_name(value);
+    if segment.pitches.is_empty() {
+        return format!("r{duration}");
+    }
+    let name = |midi: u8| if part.is_percussion() { drum_name(midi) } else { pitch_name(&song.key, midi) };
+    if !last_value {
+        return match segment.pitches.as_slice() {
+            [pitch] => format!("{}{duration}~", name(pitch.midi)),
+            pitches => format!("<{}>{duration}~", pitches.iter().map(|pitch| name(pitch.midi)).collect::<Vec<_>>().join(" ")),
+        };
+    }
+    let tie = |tied: bool| if tied { "~" } else { "" };
+    match segment.pitches.as_slice() {
+        [pitch] => format!("{}{duration}{}", name(pitch.midi), tie(pitch.tied)),
+        // only some notes of a chord may go on sounding, so chords are tied note by note
+        pitches => format!("<{}>{duration}", pitches.iter().map(|pitch| format!("{}{}", name(pitch.midi), tie(pitch.tied))).collect::<Vec<_>>().join(" ")),
+    }
+}
+
+/// Spells a MIDI note number as a LilyPond pitch in absolute octaves, like `fis'` for the F sharp above middle C
+fn pitch_name(key: &Key, midi: u8) -> String {
+    let (letter, alter, octave) = key.spell(midi);
+    let mut name = note_name(letter, alter);
+    // the octave below middle C has no marks
+    let marks = octave - 3;
+    let mark = if marks > 0 { '\'' } else { ',' };
+    name.extend(std::iter::repeat_n(mark, marks.unsigned_abs() as usize));
+    name
+}
+
+/// Gets the LilyPond name of a letter and alteration without an octave, like `bes` for B flat
+fn note_name(letter: char, alter: i8) -> String {
+    let accidental = match alter {
+        1 => "is",
+        -1 => "es",
+        _ => "",
+    };
+    format!("{}{accidental}", letter.to_ascii_lowercase())
+}
+
+/// Gets the LilyPond drum name of a drum part's note
+fn drum_name(midi: u8) -> String {
+    let name = match DrumVoice::from_frequency(frequency_from_midi(midi)) {
+        Some(DrumVoice::Kick) => "bd",
+        Some(DrumVoice::SideStick) => "ss",
+        Some(DrumVoice::Snare) => "sn",
+        Some(DrumVoice::Clap) => "hc",
+        Some(DrumVoice::ClosedHiHat) => "hhc",
+        Some(DrumVoice::PedalHiHat) => "hhp",
+        Some(DrumVoice::OpenHiHat) => "hho",
+        Some(DrumVoice::LowTom) => "tomfl",
+        Some(DrumVoice::MidTom) => "toml",
+        Some(DrumVoice::HighTom) => "tommh",
+        Some(DrumVoice::Crash) => "cymc",
+        Some(DrumVoice::Ride) => "cymr",
+        None => "sn",
+    };
+    name.to_string()
+}
+
+/// Length of a note value like `4.` for a dotted quarter note. Triplet values are written inside a `\tuplet`
+fn duration_name(value: &NoteValue) -> String {
+    format!("{}{}", value.denominator, ".".repeat(value.dots as usize))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::{frequency_from_midi, frequency_from_name, parse_melody, Instrument, Key, Note, Part, TimeSignature};
+
+    fn golden_fields() -> Song {
+        let mut song = Song::new("Golden Fields".to_string(), 96);
+        song.time_signature = TimeSignature::new(3, 4).unwrap();
+        song.key = Key::parse("F").unwrap();
+        let mut melody = Part::new("Melody".to_string());
+        melody.notes = parse_melody("F4/4 A4/8 Bb4/8 C5/4 D5/2 r/4 Bb4/4. A4/8 G4/4 F4/2.", 0.0, 0.5).unwrap();
+        song.parts.push(melody);
+        let mut bass = Part::new("Bass".to_string());
+        for (beat, chord) in [(0.0, ["F3", "A3", "C4"]), (3.0, ["D3", "F3", "Bb3"])] {
+            for name in chord {
+                bass.add_note(Note::new(beat, 3.0, frequency_from_name(name).unwrap(), 0.5).unwrap()).unwrap();
+            }
+        }
+        // tied over the bar line, then a rest before a note across the beat
+        bass.add_note(Note::new(6.0, 4.0, frequency_from_name("C3").unwrap(), 0.5).unwrap()).unwrap();
+        bass.add_note(Note::new(10.5, 1.5, frequency_from_name("F2").unwrap(), 0.5).unwrap()).unwrap();
+        song.parts.push(bass);
+        let mut drums = Part::new("Drums".to_string());
+        drums.instrument = Instrument::Drums;
+        for beat in [0.0, 3.0, 6.0, 9.0] {
+            drums.add_note(Note::new(beat, 1.0, frequency_from_midi(36), 0.5).unwrap()).unwrap();
+        }
+        for beat in [1.0, 2.0, 4.0, 5.0] {
+            drums.add_note(Note::new(beat, 0.5, frequency_from_midi(38), 0.5).unwrap()).unwrap();
+        }
+        song.parts.push(drums);
+        song
+    }
+
+    #[test]
+    fn matches_the_golden_file() {
+        assert_eq!(song_to_lilypond(&golden_fields()).unwrap(), include_str!("../../tests/fixtures/golden_fields.ly"));
+    }
+}
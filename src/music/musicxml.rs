@@ -0,0 +1,504 @@
+use std::fmt::Write;
+
+use super::instrument::Instrument;
+use super::note::Note;
+use super::part::Part;
+use super::pitch::frequency_from_midi;
+use super::score::{self, Accidentals, NoteValue, GRID};
+use super::signature::{Key, TimeSignature};
+use super::song::Song;
+use super::xml::{parse_xml, XmlElement};
+use super::zip::zip_entries;
+
+/// Volume of imported notes until a dynamic marking says otherwise
+const DEFAULT_VOLUME: f32 = 0.5;
+
+/// Writes a song as a MusicXML score with one part per [Part]. Bars follow the song's time signature,
+/// note lengths are split into written note values tied together and tied across bar lines, and gaps become rests
+pub fn song_to_musicxml(song: &Song) -> Result<String, &'static str> {
+    let err = "Could not write MusicXML!";
+    let bar_beats = song.time_signature.bar_beats();
+    let num_bars = score::num_bars(song.duration(), bar_beats);
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>"#).map_err(|_| err)?;
+    writeln!(xml, r#"<!DOCTYPE score-partwise PUBLIC "-//Recordare//DTD MusicXML 4.0 Partwise//EN" "http://www.musicxml.org/dtds/partwise.dtd">"#).map_err(|_| err)?;
+    writeln!(xml, r#"<score-partwise version="4.0">"#).map_err(|_| err)?;
+    writeln!(xml, "  <work><work-title>{}</work-title></work>", escape(&song.name)).map_err(|_| err)?;
+    writeln!(xml, "  <part-list>").map_err(|_| err)?;
+    // patterns are written out as notes wherever they are placed
+    let parts = song.arranged_parts();
+    for (index, part) in parts.iter().enumerate() {
+        let id = index + 1;
+        writeln!(xml, r#"    <score-part id="P{id}">"#).map_err(|_| err)?;
+        writeln!(xml, "      <part-name>{}</part-name>", escape(&part.name)).map_err(|_| err)?;
+        if part.is_percussion() {
+            writeln!(xml, r#"      <score-instrument id="P{id}-I1"><instrument-name>Drums</instrument-name></score-instrument>"#).map_err(|_| err)?;
+            writeln!(xml, r#"      <midi-instrument id="P{id}-I1"><midi-channel>10</midi-channel></midi-instrument>"#).map_err(|_| err)?;
+        }
+        writeln!(xml, "    </score-part>").map_err(|_| err)?;
+    }
+    writeln!(xml, "  </part-list>").map_err(|_| err)?;
+    for (index, part) in parts.iter().enumerate() {
+        write_part(&mut xml, song, part, index, num_bars).map_err(|_| err)?;
+    }
+    writeln!(xml, "</score-partwise>").map_err(|_| err)?;
+    Ok(xml)
+}
+
+fn write_part(xml: &mut String, song: &Song, part: &Part, index: usize, num_bars: usize) -> std::fmt::Result {
+    let bar_beats = song.time_signature.bar_beats();
+    let divisions = GRID as u32;
+    let mut accidentals = Accidentals::default();
+    // pitches tied over from the previous segment
+    let mut tied_in: Vec<u8> = Vec::new();
+    writeln!(xml, r#"  <part id="P{}">"#, index + 1)?;
+    for (number, bar) in score::bars(&part.notes, bar_beats, num_bars).iter().enumerate() {
+        writeln!(xml, r#"    <measure number="{}">"#, number + 1)?;
+        if number == 0 {
+            writeln!(xml, "      <attributes>")?;
+            writeln!(xml, "        <divisions>{divisions}</divisions>")?;
+            writeln!(xml, "        <key><fifths>{}</fifths><mode>{}</mode></key>", song.key.fifths(), if song.key.minor { "minor" } else { "major" })?;
+            writeln!(xml, "        <time><beats>{}</beats><beat-type>{}</beat-type></time>", song.time_signature.beats, song.time_signature.beat_unit)?;
+            if part.is_percussion() {
+                writeln!(xml, "        <clef><sign>percussion</sign></clef>")?;
+            } else if score::uses_bass_clef(&part.notes) {
+                writeln!(xml, "        <clef><sign>F</sign><line>4</line></clef>")?;
+            } else {
+                writeln!(xml, "        <clef><sign>G</sign><line>2</line></clef>")?;
+            }
+            writeln!(xml, "      </attributes>")?;
+            if index == 0 {
+                writeln!(xml, r#"      <direction placement="above"><direction-type><metronome><beat-unit>quarter</beat-unit><per-minute>{}</per-minute></metronome></direction-type><sound tempo="{}"/></direction>"#, song.bpm, song.bpm)?;
+            }
+        }
+        accidentals.clear();
+        if bar.iter().all(|segment| segment.pitches.is_empty()) {
+            let duration = (bar_beats * GRID).round() as u32;
+            writeln!(xml, r#"      <note><rest measure="yes"/><duration>{duration}</duration><voice>1</voice></note>"#)?;
+            writeln!(xml, "    </measure>")?;
+            tied_in.clear();
+            continue;
+        }
+        for segment in bar {
+            let values = score::note_values(segment.duration);
+            for (value_index, value) in values.iter().enumerate() {
+                let last_value = value_index + 1 == values.len();
+                if segment.pitches.is_empty() {
+                    write_note(xml, None, value, false, false)?;
+                    continue;
+                }
+                for (pitch_index, pitch) in segment.pitches.iter().enumerate() {
+                    let stops_tie = value_index > 0 || tied_in.contains(&pitch.midi);
+                    let starts_tie = !last_value || pitch.tied;
+                    let (letter, alter, octave) = song.key.spell(pitch.midi);
+                    let written = WrittenPitch { chord: pitch_index > 0, letter, alter, octave, accidental: accidentals.write(&song.key, letter, alter, octave) };
+                    write_note(xml, Some(&written), value, stops_tie, starts_tie)?;
+                }
+            }
+            tied_in = segment.pitches.iter().filter(|pitch| pitch.tied).map(|pitch| pitch.midi).collect();
+        }
+        writeln!(xml, "    </measure>")?;
+    }
+    writeln!(xml, "  </part>")
+}
+
+struct WrittenPitch {
+    // sounds together with the note before it
+    chord: bool,
+    letter: char,
+    alter: i8,
+    octave: i32,
+    accidental: Option<i8>,
+}
+
+/// Writes one note, or a rest without a pitch
+fn write_note(xml: &mut String, pitch: Option<&WrittenPitch>, value: &NoteValue, stops_tie: bool, starts_tie: bool) -> std::fmt::Result {
+    writeln!(xml, "      <note>")?;
+    match pitch {
+        Some(pitch) => {
+            if pitch.chord {
+                writeln!(xml, "        <chord/>")?;
+            }
+            let alter = if pitch.alter != 0 { format!("<alter>{}</alter>", pitch.alter) } else { String::new() };
+            writeln!(xml, "        <pitch><step>{}</step>{alter}<octave>{}</octave></pitch>", pitch.letter, pitch.octave)?;
+        }
+        None => writeln!(xml, "        <rest/>")?,
+    }
+    writeln!(xml, "        <duration>{}</duration>", (value.beats() * GRID).round() as u32)?;
+    if stops_tie {
+        writeln!(xml, r#"        <tie type="stop"/>"#)?;
+    }
+    if starts_tie {
+        writeln!(xml, r#"        <tie type="start"/>"#)?;
+    }
+    writeln!(xml, "        <voice>1</voice>")?;
+    writeln!(xml, "        <type>{}</type>", type_name(value.denominator))?;
+    for _ in 0..value.dots {
+        writeln!(xml, "        <dot/>")?;
+    }
+    if let Some(accidental) = pitch.and_then(|pitch| pitch.accidental) {
+        let name = match accidental {
+            1 => "sharp",
+            -1 => "flat",
+            _ => "natural",
+        };
+        writeln!(xml, "        <accidental>{name}</accidental>")?;
+    }
+    if value.triplet {
+        writeln!(xml, "        <time-modification><actual-notes>3</actual-notes><normal-notes>2</normal-notes></time-modification>")?;
+    }
+    if stops_tie || starts_tie {
+        let stop = if stops_tie { r#"<tied type="stop"/>"# } else { "" };
+        let start = if starts_tie { r#"<tied type="start"/>"# } else { "" };
+        writeln!(xml, "        <notations>{stop}{start}</notations>")?;
+    }
+    writeln!(xml, "      </note>")
+}
+
+fn type_name(denominator: u32) -> &'static str {
+    match denominator {
+        1 => "whole",
+        2 => "half",
+        4 => "quarter",
+        8 => "eighth",
+        16 => "16th",
+        32 => "32nd",
+        _ => "64th",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+/// Song wide settings taken from the first part that gives them
+#[derive(Default)]
+struct ScoreSettings {
+    bpm: Option<f32>,
+    time_signature: Option<TimeSignature>,
+    key: Option<Key>,
+}
+
+/// Reads a compressed MusicXML file, a zip archive holding the score and a container file that says where it is
+pub fn song_from_mxl(data: &[u8]) -> Result<Song, &'static str> {
+    let entries = zip_entries(data)?;
+    let container = match entries.iter().find(|entry| entry.name == "META-INF/container.xml") {
+        Some(entry) => Some(parse_xml(&String::from_utf8_lossy(&entry.read()?))?),
+        None => None,
+    };
+    let root_path = container
+        .as_ref()
+        .and_then(|container| container.child("rootfiles"))
+        .and_then(|rootfiles| rootfiles.child("rootfile"))
+        .and_then(|rootfile| rootfile.attribute("full-path"));
+    let score = match root_path {
+        Some(path) => entries.iter().find(|entry| entry.name == path),
+        // archives without a container hold the score as their only xml file outside of META-INF
+        None => entries.iter().find(|entry| !entry.name.starts_with("META-INF/") && (entry.name.ends_with(".xml") || entry.name.ends_with(".musicxml"))),
+    };
+    let score = score.ok_or("Compressed MusicXML file holds no score!")?;
+    song_from_musicxml(&String::from_utf8_lossy(&score.read()?))
+}
+
+/// Reads a partwise MusicXML score into a song with one part per score part. Durations are counted in the divisions
+/// of a quarter note given by each part, chords, ties, backups and forwards are followed, dynamics set the volume of
+/// the notes after them, and the first tempo, time signature and key become the song's. Repeats are played once
+/// and grace notes are skipped
+pub fn song_from_musicxml(text: &str) -> Result<Song, &'static str> {
+    let root = parse_xml(text.trim_start_matches('\u{feff}'))?;
+    match root.name.as_str() {
+        "score-partwise" => {}
+        "score-timewise" => return Err("Only partwise MusicXML scores can be imported!"),
+        _ => return Err("Not a MusicXML score!"),
+    }
+    let title = root
+        .child("work")
+        .and_then(|work| work.child_text("work-title"))
+        .or(root.child_text("movement-title"))
+        .filter(|title| !title.is_empty())
+        .unwrap_or("MusicXML Score");
+    let mut song = Song::new(title.to_string(), 120);
+    let mut settings = ScoreSettings::default();
+    let part_list = root.child("part-list");
+    for (index, element) in root.children_named("part").enumerate() {
+        let id = element.attribute("id").unwrap_or_default();
+        let score_part = part_list.and_then(|list| list.children_named("score-part").find(|score_part| score_part.attribute("id") == Some(id)));
+        let name = match score_part.and_then(|score_part| score_part.child_text("part-name")).filter(|name| !name.is_empty()) {
+            Some(name) => name.to_string(),
+            None => format!("Part {}", index + 1),
+        };
+        song.parts.push(read_part(element, score_part, name, &mut settings)?);
+    }
+    if song.parts.is_empty() {
+        return Err("MusicXML score has no parts!");
+    }
+    if let Some(bpm) = settings.bpm {
+        song.bpm = bpm.round().clamp(1.0, u16::MAX as f32) as u16;
+    }
+    song.time_signature = settings.time_signature.unwrap_or_default();
+    song.key = settings.key.unwrap_or_default();
+    Ok(song)
+}
+
+fn read_part(element: &XmlElement, score_part: Option<&XmlElement>, name: String, settings: &mut ScoreSettings) -> Result<Part, &'static str> {
+    let mut part = Part::new(name);
+    // unpitched notes name the instrument they play, which gives the General MIDI drum
+    let drum_keys: Vec<(&str, u8)> = score_part
+        .iter()
+        .flat_map(|score_part| score_part.children_named("midi-instrument"))
+        .filter_map(|instrument| Some((instrument.attribute("id")?, instrument.child_text("midi-unpitched")?.parse::<u8>().ok()?.checked_sub(1)?)))
+        .collect();
+    let measures: Vec<&XmlElement> = element.children_named("measure").collect();
+    let on_drum_channel = score_part
+        .iter()
+        .flat_map(|score_part| score_part.children_named("midi-instrument"))
+        .any(|instrument| instrument.child_text("midi-channel") == Some("10"));
+    let has_unpitched_notes = measures.iter().any(|measure| measure.children_named("note").any(|note| note.child("unpitched").is_some()));
+    if on_drum_channel || has_unpitched_notes {
+        part.instrument = Instrument::Drums;
+    }
+    let mut divisions = 1.0;
+    let mut beat: f32 = 0.0;
+    let mut chord_beat = 0.0;
+    let mut volume = DEFAULT_VOLUME;
+    // notes tied into a later note as (pitch, index of the note in the part)
+    let mut tied: Vec<(u8, usize)> = Vec::new();
+    for measure in measures {
+        // voices move back to the start of the bar, the next bar starts after the longest one
+        let mut measure_end = beat;
+        for child in &measure.children {
+            // taken before every element as rests and skipped notes leave the loop early
+            measure_end = measure_end.max(beat);
+            match child.name.as_str() {
+                "attributes" => {
+                    if let Some(value) = child.child_text("divisions").and_then(|value| value.parse::<f32>().ok()).filter(|value| *value > 0.0) {
+                        divisions = value;
+                    }
+                    read_attributes(child, settings);
+                }
+                "direction" => {
+                    for direction_type in child.children_named("direction-type") {
+                        let dynamic = direction_type.child("dynamics").into_iter().flat_map(|dynamics| &dynamics.children).find_map(|dynamic| score::volume_from_dynamic(&dynamic.name));
+                        if let Some(dynamic) = dynamic {
+                            volume = dynamic;
+                        }
+                        if settings.bpm.is_none() {
+                            settings.bpm = direction_type.child("metronome").and_then(metronome_bpm);
+                        }
+                    }
+                    if let Some(sound) = child.child("sound") {
+                        read_sound(sound, settings);
+                    }
+                }
+                "sound" => read_sound(child, settings),
+                "backup" => beat -= read_duration(child, divisions),
+                "forward" => beat += read_duration(child, divisions),
+                "note" => {
+                    if child.child("grace").is_some() {
+                        continue;
+                    }
+                    let duration = read_duration(child, divisions);
+                    let start = if child.child("chord").is_some() { chord_beat } else { beat };
+                    if child.child("chord").is_none() {
+                        chord_beat = beat;
+                        beat += duration;
+                    }
+                    if child.child("rest").is_some() || child.child("cue").is_some() || duration <= 0.0 {
+                        continue;
+                    }
+                    let Some(pitch) = read_pitch(child, &drum_keys) else { continue };
+                    let ties: Vec<&str> = child.children_named("tie").filter_map(|tie| tie.attribute("type")).collect();
+                    let tied_from = tied.iter().position(|(tied_pitch, _)| *tied_pitch == pitch).filter(|_| ties.contains(&"stop"));
+                    let index = match tied_from {
+                        Some(position) => {
+                            let (_, index) = tied.remove(position);
+                            part.notes[index].duration += duration;
+                            index
+                        }
+                        None => part.merge_chord_note(Note::new(start, duration, frequency_from_midi(pitch), volume)?),
+                    };
+                    if ties.contains(&"start") {
+                        tied.push((pitch, index));
+                    }
+                }
+                _ => {}
+            }
+        }
+        beat = measure_end.max(beat);
+    }
+    Ok(part)
+}
+
+fn read_duration(element: &XmlElement, divisions: f32) -> f32 {
+    element.child_text("duration").and_then(|duration| duration.parse::<f32>().ok()).unwrap_or(0.0) / divisions
+}
+
+/// Takes the time signature and key from the first attributes that give them
+fn read_attributes(attributes: &XmlElement, settings: &mut ScoreSettings) {
+    if settings.time_signature.is_none() {
+        settings.time_signature = attributes.child("time").and_then(|time| {
+            TimeSignature::new(time.child_text("beats")?.parse().ok()?, time.child_text("beat-type")?.parse().ok()?).ok()
+        });
+    }
+    if settings.key.is_none() {
+        settings.key = attributes.child("key").and_then(|key| {
+            let fifths = key.child_text("fifths")?.parse().ok()?;
+            Some(Key::from_fifths(fifths, key.child_text("mode") == Some("minor")))
+        });
+    }
+}
+
+fn read_sound(sound: &XmlElement, settings: &mut ScoreSettings) {
+    if settings.bpm.is_none() {
+        settings.bpm = sound.attribute("tempo").and_then(|tempo| tempo.parse().ok()).filter(|tempo: &f32| *tempo > 0.0);
+    }
+}
+
+/// Gets the tempo in quarter notes per minute of a metronome marking like a dotted quarter at 80
+fn metronome_bpm(metronome: &XmlElement) -> Option<f32> {
+    let per_minute: f32 = metronome.child_text("per-minute")?.parse().ok()?;
+    let unit_beats = match metronome.child_text("beat-unit")? {
+        "whole" => 4.0,
+        "half" => 2.0,
+        "quarter" => 1.0,
+        "eighth" => 0.5,
+        "16th" => 0.25,
+        _ => return None,
+    };
+    let dotted = if metronome.child("beat-unit-dot").is_some() { 1.5 } else { 1.0 };
+    Some(per_minute * unit_beats * dotted)
+}
+
+/// Gets the MIDI note number of a pitched note, or of the drum an unpitched note plays
+fn read_pitch(note: &XmlElement, drum_keys: &[(&str, u8)]) -> Option<u8> {
+    if let Some(pitch) = note.child("pitch") {
+        let alter = pitch.child_text("alter").and_then(|alter| alter.parse::<f32>().ok()).unwrap_or(0.0).round() as i32;
+        return midi_from_step(pitch.child_text("step")?, alter, pitch.child_text("octave")?.parse().ok()?);
+    }
+    let unpitched = note.child("unpitched")?;
+    let instrument = note.child("instrument").and_then(|instrument| instrument.attribute("id"));
+    match drum_keys.iter().find(|(id, _)| Some(*id) == instrument) {
+        Some((_, key)) => Some(*key),
+        // without a drum the note is placed by where it is written on the staff
+        None => midi_from_step(unpitched.child_text("display-step")?, 0, unpitched.child_text("display-octave")?.parse().ok()?),
+    }
+}
+
+fn midi_from_step(step: &str, alter: i32, octave: i32) -> Option<u8> {
+    let semitone = match step {
+        "C" => 0,
+        "D" => 2,
+        "E" => 4,
+        "F" => 5,
+        "G" => 7,
+        "A" => 9,
+        "B" => 11,
+        _ => return None,
+    };
+    u8::try_from((octave + 1) * 12 + semitone + alter).ok().filter(|midi| *midi < 128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::{frequency_from_name, name_from_frequency, parse_melody};
+
+    fn notes_of(part: &Part) -> Vec<(f32, f32, String)> {
+        let mut notes: Vec<(f32, f32, String)> = part.notes.iter().map(|note| (note.beat, note.duration, name_from_frequency(note.frequency))).collect();
+        notes.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.2.cmp(&b.2)));
+        notes
+    }
+
+    fn score(measure: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<score-partwise version="4.0">
+  <part-list><score-part id="P1"><part-name>Piano</part-name></score-part></part-list>
+  <part id="P1"><measure number="1">{measure}</measure></part>
+</score-partwise>"#
+        )
+    }
+
+    fn note(step: char, duration: u32, voice: u32) -> String {
+        format!("<note><pitch><step>{step}</step><octave>4</octave></pitch><duration>{duration}</duration><voice>{voice}</voice></note>")
+    }
+
+    #[test]
+    fn voices_sharing_a_pitch_are_merged() {
+        let measure = format!(
+            "<attributes><divisions>1</divisions></attributes>{}<backup><duration>2</duration></backup>{}{}",
+            note('C', 2, 1),
+            note('E', 1, 2),
+            note('C', 2, 2)
+        );
+        let song = song_from_musicxml(&score(&measure)).unwrap();
+        assert_eq!(notes_of(&song.parts[0]), vec![(0.0, 3.0, "C4".to_string()), (0.0, 1.0, "E4".to_string())]);
+    }
+
+    #[test]
+    fn exported_scores_read_back_the_same() {
+        let mut song = Song::new("Round & Back".to_string(), 90);
+        song.time_signature = TimeSignature::new(6, 8).unwrap();
+        song.key = Key::parse("Dm").unwrap();
+        let mut melody = Part::new("Melody".to_string());
+        melody.notes = parse_melody("D5/8 E5/8 F5/8 A5/4. C#5/4 D5/8 r/4.", 0.0, 0.5).unwrap();
+        song.parts.push(melody);
+        let mut bass = Part::new("Bass".to_string());
+        // tied over the bar line, then a chord
+        bass.add_note(Note::new(0.0, 4.5, frequency_from_name("D3").unwrap(), 0.5).unwrap()).unwrap();
+        bass.add_note(Note::new(4.5, 1.5, frequency_from_name("A2").unwrap(), 0.5).unwrap()).unwrap();
+        bass.add_note(Note::new(4.5, 1.5, frequency_from_name("E3").unwrap(), 0.5).unwrap()).unwrap();
+        song.parts.push(bass);
+        let mut drums = Part::new("Drums".to_string());
+        drums.instrument = Instrument::Drums;
+        for beat in [0.0, 1.5, 3.0, 4.5] {
+            drums.add_note(Note::new(beat, 0.5, frequency_from_midi(36), 0.5).unwrap()).unwrap();
+        }
+        song.parts.push(drums);
+
+        let read = song_from_musicxml(&song_to_musicxml(&song).unwrap()).unwrap();
+        assert_eq!(read.name, "Round & Back");
+        assert_eq!(read.bpm, 90);
+        assert!(read.time_signature == song.time_signature && read.key == song.key);
+        assert_eq!(read.parts.len(), 3);
+        for (read, part) in read.parts.iter().zip(&song.parts) {
+            assert_eq!(read.name, part.name);
+            assert_eq!(read.is_percussion(), part.is_percussion());
+            assert_eq!(notes_of(read), notes_of(part));
+        }
+    }
+
+    #[test]
+    fn reads_compressed_scores() {
+        let song = song_from_mxl(include_bytes!("../../tests/fixtures/packed.mxl")).unwrap();
+        assert_eq!(song.name, "Packed");
+        assert!(song.key == Key::parse("F").unwrap());
+        assert!(song.time_signature == TimeSignature::new(2, 4).unwrap());
+        assert_eq!(song.parts[0].name, "Flute");
+        let expected = [(0.0, 1.0, "F4"), (1.0, 0.5, "A#4"), (1.5, 0.5, "A4")].map(|(beat, duration, name)| (beat, duration, name.to_string()));
+        assert_eq!(notes_of(&song.parts[0]), expected);
+    }
+
+    #[test]
+    fn archives_without_a_score_are_refused() {
+        assert!(song_from_mxl(b"not a zip file").is_err());
+        assert!(song_from_musicxml(&score("").replace("score-partwise", "score-timewise")).is_err());
+    }
+
+    #[test]
+    fn leading_tones_of_minor_keys_are_written_as_sharpened_sevenths() {
+        let mut song = Song::new("Leading Tone".to_string(), 120);
+        song.key = Key::parse("Dm").unwrap();
+        let mut part = Part::new("Lead".to_string());
+        part.notes = parse_melody("D4/4 C#4/4 Bb4/4 D4/4", 0.0, 0.5).unwrap();
+        song.parts.push(part);
+        let xml = song_to_musicxml(&song).unwrap();
+        assert!(xml.contains("<key><fifths>-1</fifths><mode>minor</mode></key>"));
+        assert!(xml.contains("<pitch><step>C</step><alter>1</alter><octave>4</octave></pitch>"));
+        assert!(xml.contains("<accidental>sharp</accidental>"));
+        // Bb is in the key signature so it gets no accidental
+        assert!(xml.contains("<pitch><step>B</step><alter>-1</alter><octave>4</octave></pitch>"));
+        assert!(!xml.contains("<accidental>flat</accidental>"));
+        assert!(!xml.contains("<step>D</step><alter>-1</alter>"));
+    }
+}
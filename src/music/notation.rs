@@ -0,0 +1,192 @@
+use super::note::Note;
+use super::pitch::{frequency_from_midi, midi_from_frequency, midi_from_name, name_from_midi};
+use super::score::note_values;
+
+/// How close two beat lengths have to be to count as the same
+const EPSILON: f32 = 0.001;
+
+/// Reads a line of melody text into notes starting at `start_beat`. Tokens are separated by spaces:
+/// - `C4/4` a pitch and a note value, where `/4` is a quarter note of 1 beat, `/8` an eighth note and so on
+/// - `r/4` a rest
+/// - `C#/8` a pitch without an octave uses the octave of the previous pitch, which starts at 4
+/// - `>` and `<` shift that octave up and down
+/// - `/4.` and `/4..` are dotted, `/8t` is one note of a triplet
+/// - a value can be left out to reuse the previous one, which starts as a quarter note
+/// - `C4/4~ C4/8` ties a note into the next one of the same pitch
+/// - `|` bar lines are ignored
+pub fn parse_melody(text: &str, start_beat: f32, volume: f32) -> Result<Vec<Note>, &'static str> {
+    let mut notes: Vec<Note> = Vec::new();
+    let mut beat = start_beat;
+    let mut octave = 4;
+    let mut beats = 1.0;
+    let mut tied = false;
+    for token in text.split_whitespace() {
+        match token {
+            "|" => continue,
+            ">" => {
+                octave += 1;
+                continue;
+            }
+            "<" => {
+                octave -= 1;
+                continue;
+            }
+            _ => {}
+        }
+        let (token, ties_next) = match token.strip_suffix('~') {
+            Some(token) => (token, true),
+            None => (token, false),
+        };
+        let (pitch, value) = match token.split_once('/') {
+            Some((pitch, value)) => (pitch, Some(value)),
+            None => (token, None),
+        };
+        if let Some(value) = value {
+            beats = parse_value(value)?;
+        }
+        if pitch == "r" || pitch == "R" {
+            if tied {
+                return Err("A tie must be followed by a note of the same pitch");
+            }
+            beat += beats;
+            continue;
+        }
+        let name = if pitch.ends_with(|c: char| c.is_ascii_digit()) {
+            octave = pitch.trim_start_matches(|c: char| !c.is_ascii_digit()).parse().map_err(|_| "Invalid octave in melody")?;
+            pitch.to_string()
+        } else {
+            format!("{pitch}{octave}")
+        };
+        let frequency = match midi_from_name(&name) {
+            Some(number) => frequency_from_midi(number),
+            None => return Err("Unknown pitch in melody! Use note names like C4, F#, Bb3 or r for a rest"),
+        };
+        match notes.last_mut() {
+            Some(previous) if tied => {
+                if midi_from_frequency(previous.frequency) != midi_from_frequency(frequency) {
+                    return Err("A tie must be followed by a note of the same pitch");
+                }
+                previous.duration += beats;
+            }
+            _ => notes.push(Note::new(beat, beats, frequency, volume)?),
+        }
+        beat += beats;
+        tied = ties_next;
+    }
+    if tied {
+        return Err("The last note of the melody is tied to nothing");
+    }
+    Ok(notes)
+}
+
+/// Reads a note value like `4`, `8.` or `8t` into beats
+fn parse_value(value: &str) -> Result<f32, &'static str> {
+    let err = "Invalid note value in melody! Use values like /4, /8., /16t";
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let denominator = digits.parse::<u32>().map_err(|_| err)?;
+    if denominator == 0 || !denominator.is_power_of_two() {
+        return Err(err);
+    }
+    let mut beats = 4.0 / denominator as f32;
+    match &value[digits.len()..] {
+        "" => {}
+        "." => beats *= 1.5,
+        ".." => beats *= 1.75,
+        "t" => beats *= 2.0 / 3.0,
+        _ => return Err(err),
+    }
+    Ok(beats)
+}
+
+/// Writes notes back as melody text starting at `start_beat`, filling gaps with rests and splitting lengths
+/// that have no single note value into tied notes. Notes must not overlap
+pub fn melody_to_text(notes: &[Note], start_beat: f32) -> Result<String, &'static str> {
+    let mut notes: Vec<&Note> = notes.iter().filter(|note| note.end_beat() > start_beat + EPSILON).collect();
+    notes.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+    let mut tokens = Vec::new();
+    let mut beat = start_beat;
+    for note in notes {
+        if note.beat < beat - EPSILON {
+            return Err("Overlapping notes can't be written as a single melody");
+        }
+        if note.beat > beat + EPSILON {
+            tokens.extend(split_into_values(note.beat - beat).iter().map(|value| format!("r/{value}")));
+        }
+        let name = name_from_midi(midi_from_frequency(note.frequency));
+        let values = split_into_values(note.duration);
+        let last = values.len().saturating_sub(1);
+        for (index, value) in values.iter().enumerate() {
+            let tie = if index < last { "~" } else { "" };
+            tokens.push(format!("{name}/{value}{tie}"));
+        }
+        beat = note.end_beat();
+    }
+    Ok(tokens.join(" "))
+}
+
+/// Splits a length in beats into note values written like `4.` or `8t`
+fn split_into_values(beats: f32) -> Vec<String> {
+    note_values(beats)
+        .iter()
+        .map(|value| {
+            let suffix = if value.triplet { "t".to_string() } else { ".".repeat(value.dots as usize) };
+            format!("{}{suffix}", value.denominator)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beats_and_names(notes: &[Note]) -> Vec<(f32, f32, String)> {
+        notes.iter().map(|note| (note.beat, note.duration, name_from_midi(midi_from_frequency(note.frequency)))).collect()
+    }
+
+    #[test]
+    fn reads_pitches_values_and_rests() {
+        let notes = parse_melody("C4/4 D/8 r/8 | > E/4. < F#/8t Bb3", 2.0, 0.5).unwrap();
+        let expected = [
+            (2.0, 1.0, "C4"),
+            (3.0, 0.5, "D4"),
+            (4.0, 1.5, "E5"),
+            (5.5, 1.0 / 3.0, "F#4"),
+            (5.5 + 1.0 / 3.0, 1.0 / 3.0, "A#3"),
+        ];
+        for (note, (beat, duration, name)) in beats_and_names(&notes).iter().zip(expected) {
+            assert!((note.0 - beat).abs() < EPSILON && (note.1 - duration).abs() < EPSILON, "{note:?}");
+            assert_eq!(note.2, name);
+        }
+        assert_eq!(notes.len(), expected.len());
+        assert!(notes.iter().all(|note| note.volume == 0.5));
+    }
+
+    #[test]
+    fn ties_join_notes_of_the_same_pitch() {
+        let notes = parse_melody("G4/2~ G/8 A/8", 0.0, 1.0).unwrap();
+        assert_eq!(beats_and_names(&notes), vec![(0.0, 2.5, "G4".to_string()), (2.5, 0.5, "A4".to_string())]);
+        assert!(parse_melody("G4/2~ A/8", 0.0, 1.0).is_err());
+        assert!(parse_melody("G4/2~", 0.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn bad_tokens_are_refused() {
+        assert!(parse_melody("H4/4", 0.0, 1.0).is_err());
+        assert!(parse_melody("C4/3", 0.0, 1.0).is_err());
+        assert!(parse_melody("C4/4x", 0.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn written_melodies_read_back_the_same() {
+        let text = "r/2 C4/4 D#4/8. E4/16 r/4 G5/1~ G5/8 A3/8t";
+        let notes = parse_melody(text, 1.0, 1.0).unwrap();
+        assert_eq!(melody_to_text(&notes, 1.0).unwrap(), text);
+        assert_eq!(beats_and_names(&parse_melody(&melody_to_text(&notes, 0.0).unwrap(), 0.0, 1.0).unwrap()), beats_and_names(&notes));
+    }
+
+    #[test]
+    fn overlapping_notes_are_not_a_melody() {
+        let notes = vec![Note::new(0.0, 2.0, 440.0, 1.0).unwrap(), Note::new(1.0, 1.0, 220.0, 1.0).unwrap()];
+        assert!(melody_to_text(&notes, 0.0).is_err());
+    }
+}
@@ -2,14 +2,26 @@ use std::fmt::Display;
 
 use super::serializable::Serializable;
 
+/// A point on the pitch bend curve of a [Note]
+#[derive(Clone, Copy)]
+pub struct BendPoint {
+    // beats since the note started
+    pub offset: f32,
+    // how far the pitch is bent
+    pub semitones: f32
+}
+
 /// Represents a certain pitch at a certain time at a certain volume. Is part of a [Part]
+#[derive(Clone)]
 pub struct Note {
     // what beat it plays on
     pub beat: f32,  
     // how long it plays in beats
     pub duration: f32, 
     pub frequency: f32,
-    pub volume: f32
+    pub volume: f32,
+    // pitch bend curve sorted by offset. The pitch moves in a straight line between points and holds before the first and after the last
+    pub bend: Vec<BendPoint>
 }
 
 impl Note {
@@ -17,7 +29,32 @@ impl Note {
         if volume > 1.0 {
             return Err("Note must have volume in range [0, 1]");
         }
-        Ok(Note {beat, duration, frequency, volume})
+        Ok(Note {beat, duration, frequency, volume, bend: Vec::new()})
+    }
+
+    /// Gets how many semitones the note is bent `offset` beats after it started
+    pub fn bend_at(&self, offset: f32) -> f32 {
+        let first = match self.bend.first() {
+            Some(first) => first,
+            None => return 0.0
+        };
+        if offset <= first.offset {
+            return first.semitones;
+        }
+        for points in self.bend.windows(2) {
+            let (from, to) = (points[0], points[1]);
+            if offset < to.offset {
+                let progress = (offset - from.offset) / (to.offset - from.offset);
+                return from.semitones + (to.semitones - from.semitones) * progress;
+            }
+        }
+        self.bend[self.bend.len() - 1].semitones
+    }
+
+    /// Replaces the bend curve, sorting the points by offset
+    pub fn set_bend(&mut self, mut bend: Vec<BendPoint>) {
+        bend.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+        self.bend = bend;
     }
 
     pub fn end_beat(&self) -> f32 {
@@ -30,10 +67,6 @@ impl Note {
         }
         false
     }
-
-    pub fn get_sample_amplitude(&self, time: f32) -> i16 {
-        ((time * 2.0 * std::f32::consts::PI * self.frequency).sin() * self.volume * crate::wav::MAX_AMPLITUDE as f32) as i16
-    }
 }
 
 impl Serializable for Note {
@@ -42,6 +75,7 @@ impl Serializable for Note {
     /// f32: duration
     /// f32: frequency
     /// f32: volume
+    /// The bend curve is saved by the [Part] so every note keeps the same size
     fn serialize(&self) -> Result<Vec<u8>, &'static str> {
         let mut serialized_data = Vec::new();
         // Serialize the time
@@ -75,12 +109,17 @@ impl Serializable for Note {
         // Deserialize the volume
         let vol_bytes = &serialized_data[12..16];
         let volume = f32::from_le_bytes(vol_bytes.try_into().unwrap());
-        Ok(Self { beat, duration, frequency, volume })
+        Ok(Self { beat, duration, frequency, volume, bend: Vec::new() })
     }
 }
 
 impl Display for Note {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Note(beat: {}, duration: {}, frequency: {}, volume: {})", self.beat, self.duration, self.frequency, self.volume)
+        write!(f, "Note(beat: {}, duration: {}, frequency: {}, volume: {}", self.beat, self.duration, self.frequency, self.volume)?;
+        if !self.bend.is_empty() {
+            let points: Vec<String> = self.bend.iter().map(|point| format!("{}:{}", point.offset, point.semitones)).collect();
+            write!(f, ", bend: {}", points.join(" "))?;
+        }
+        write!(f, ")")
     }
 }
\ No newline at end of file
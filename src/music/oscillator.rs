@@ -0,0 +1,148 @@
+use std::f32::consts::PI;
+use std::fmt::Display;
+
+/// The shape of a basic oscillator. Everything but the sine is band limited with PolyBLEP/PolyBLAMP corrections
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Square,
+    Saw,
+    Triangle,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 4] = [Waveform::Sine, Waveform::Square, Waveform::Saw, Waveform::Triangle];
+
+    /// Gets the value of the waveform at `phase` in cycles [0, 1) in the range [-1, 1].
+    /// `phase_increment` is how many cycles pass per sample and decides how much smoothing the discontinuities get
+    pub fn sample(&self, phase: f32, phase_increment: f32) -> f32 {
+        let phase = phase.rem_euclid(1.0);
+        let dt = phase_increment.abs().min(0.5);
+        match self {
+            Waveform::Sine => (2.0 * PI * phase).sin(),
+            Waveform::Saw => 2.0 * phase - 1.0 - poly_blep(phase, dt),
+            Waveform::Square => {
+                let naive = if phase < 0.5 { 1.0 } else { -1.0 };
+                naive + poly_blep(phase, dt) - poly_blep((phase + 0.5) % 1.0, dt)
+            }
+            Waveform::Triangle => {
+                let naive = if phase < 0.5 { 4.0 * phase - 1.0 } else { 3.0 - 4.0 * phase };
+                // the slope changes by 8 per cycle at each corner and the two sided blamp covers half of that on each side
+                naive + 4.0 * dt * (poly_blamp(phase, dt) - poly_blamp((phase + 0.5) % 1.0, dt))
+            }
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Waveform::Sine => 0,
+            Waveform::Square => 1,
+            Waveform::Saw => 2,
+            Waveform::Triangle => 3,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Waveform::Sine),
+            1 => Some(Waveform::Square),
+            2 => Some(Waveform::Saw),
+            3 => Some(Waveform::Triangle),
+            _ => None,
+        }
+    }
+}
+
+/// Two sample polynomial approximation of a band limited step, subtracted around a jump of -2
+fn poly_blep(phase: f32, dt: f32) -> f32 {
+    if dt <= 0.0 {
+        return 0.0;
+    }
+    if phase < dt {
+        let t = phase / dt;
+        2.0 * t - t * t - 1.0
+    } else if phase > 1.0 - dt {
+        let t = (phase - 1.0) / dt;
+        t * t + 2.0 * t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Integral of [poly_blep], used to smooth a sudden change of slope
+fn poly_blamp(phase: f32, dt: f32) -> f32 {
+    if dt <= 0.0 {
+        return 0.0;
+    }
+    if phase < dt {
+        let t = phase / dt - 1.0;
+        -t * t * t / 3.0
+    } else if phase > 1.0 - dt {
+        let t = (phase - 1.0) / dt + 1.0;
+        t * t * t / 3.0
+    } else {
+        0.0
+    }
+}
+
+impl Display for Waveform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Waveform::Sine => write!(f, "Sine"),
+            Waveform::Square => write!(f, "Square"),
+            Waveform::Saw => write!(f, "Saw"),
+            Waveform::Triangle => write!(f, "Triangle"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::filter::decimate;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+    // a tenth of a second so every bin is 10 Hz and every harmonic and alias of 3 kHz lands on a bin
+    const NUM_SAMPLES: usize = 4410;
+    const FUNDAMENTAL: f32 = 3000.0;
+
+    fn render(waveform: Waveform, oversampling: usize, band_limited: bool) -> Vec<f32> {
+        let increment = FUNDAMENTAL / (SAMPLE_RATE * oversampling as f32);
+        (0..NUM_SAMPLES * oversampling)
+            .map(|i| waveform.sample((i as f64 * increment as f64).fract() as f32, if band_limited { increment } else { 0.0 }))
+            .collect()
+    }
+
+    /// Energy in bins that aren't harmonics of the fundamental against the energy in its harmonics
+    fn alias_ratio(samples: &[f32]) -> f64 {
+        let harmonic_spacing = (FUNDAMENTAL / (SAMPLE_RATE / samples.len() as f32)).round() as usize;
+        let (mut alias, mut harmonic) = (0.0, 0.0);
+        for bin in 1..samples.len() / 2 {
+            let (mut re, mut im) = (0.0_f64, 0.0_f64);
+            for (n, sample) in samples.iter().enumerate() {
+                let angle = 2.0 * std::f64::consts::PI * ((bin * n) % samples.len()) as f64 / samples.len() as f64;
+                re += *sample as f64 * angle.cos();
+                im -= *sample as f64 * angle.sin();
+            }
+            if bin % harmonic_spacing == 0 { harmonic += re * re + im * im } else { alias += re * re + im * im }
+        }
+        alias / harmonic
+    }
+
+    #[test]
+    fn band_limited_waveforms_alias_less_than_naive_ones() {
+        for waveform in [Waveform::Saw, Waveform::Square, Waveform::Triangle] {
+            let naive = alias_ratio(&render(waveform, 1, false));
+            let band_limited = alias_ratio(&render(waveform, 1, true));
+            assert!(band_limited < naive / 10.0, "{waveform} aliases {band_limited} against {naive} naive");
+        }
+    }
+
+    #[test]
+    fn oversampling_removes_most_of_what_aliasing_is_left() {
+        let direct = alias_ratio(&render(Waveform::Saw, 1, true));
+        let oversampled = alias_ratio(&decimate(&render(Waveform::Saw, 4, true), 4));
+        assert!(oversampled < direct / 10.0, "oversampled saw aliases {oversampled} against {direct} direct");
+    }
+}
@@ -1,17 +1,42 @@
 use std::fmt::Display;
 
-use super::note::Note;
-use super::serializable::Serializable;
+use super::arpeggiator::{ArpeggioMode, Arpeggiator};
+use super::bus::BusSend;
+use super::filter::{Biquad, BiquadKind, PartFilter};
+use super::instrument::Instrument;
+use super::lfo::{Lfo, LfoTarget};
+use super::note::{BendPoint, Note};
+use super::pattern::PatternInstance;
+use super::serializable::{write_sized, ByteReader, Serializable};
 
-/// Represents a musical instrument or part. Can only play one [Note] at a time and multiple Parts are part of a [Song]
+/// Represents a musical instrument or part. Multiple Parts are part of a [Song]. A part can play several [Note]s
+/// at once like the notes of a chord, but notes of the same pitch never overlap. Every way of changing the notes keeps to this
+#[derive(Clone)]
 pub struct Part {
     pub name: String,
-    pub notes: Vec<Note>
+    pub notes: Vec<Note>,
+    // copies of the part's output sent to the song's aux buses
+    pub sends: Vec<BusSend>,
+    // the voice the notes are played with
+    pub instrument: Instrument,
+    // notes that start as the previous note ends continue its waveform instead of restarting
+    pub legato: bool,
+    // seconds it takes to slide from the pitch of the previous note to the pitch of the next, only between notes
+    // joined in legato so the waveform carries on through the slide
+    pub glide: f32,
+    // low frequency oscillators modulating pitch, volume or the filter cutoff
+    pub lfos: Vec<Lfo>,
+    // low pass filter on the output of the part
+    pub filter: Option<PartFilter>,
+    // patterns of the song the part plays on top of its own notes
+    pub pattern_instances: Vec<PatternInstance>,
+    // plays the notes held together one after another when the part is rendered, leaving the notes as written
+    pub arpeggiator: Option<Arpeggiator>
 }
 
 impl Part {
     pub fn new(name: String) -> Self {
-        Part { name, notes: Vec::new()}
+        Part { name, notes: Vec::new(), sends: Vec::new(), instrument: Instrument::default(), legato: false, glide: 0.0, lfos: Vec::new(), filter: None, pattern_instances: Vec::new(), arpeggiator: None }
     }
 
     // Checks if the part has a note at a certain time
@@ -24,16 +49,54 @@ impl Part {
         None
     }
 
+    /// Adds a note, which may sound together with notes of other pitches like the notes of a chord
     pub fn add_note(&mut self, note: Note) -> Result<(), &'static str>{
-        for note_i in &self.notes {
-            if note_i.plays_at(note.beat) || note_i.plays_at(note.end_beat()) {
-                return Err("can't add note inside another notes play time");
-            }
+        if self.overlaps_same_pitch(&note, None) {
+            return Err("can't add note inside another note of the same pitch");
         }
         self.notes.push(note);
         Ok(())
     }
 
+    /// Whether `note` would break the rule that notes of the same pitch can't overlap, leaving out the note at `skip`
+    pub(crate) fn overlaps_same_pitch(&self, note: &Note, skip: Option<usize>) -> bool {
+        self.notes.iter().enumerate().any(|(index, other)| {
+            Some(index) != skip && (other.frequency - note.frequency).abs() < 0.01 && other.beat < note.end_beat() && note.beat < other.end_beat()
+        })
+    }
+
+    /// Adds a note like [Part::add_note], but where a note of the same pitch already sounds the two are merged
+    /// into one note lasting as long as both, short of running into other notes of the pitch. Scores can have two
+    /// voices play the same pitch at once which a part plays only once. Returns the index of the note that holds it
+    pub(crate) fn merge_chord_note(&mut self, note: Note) -> usize {
+        let same_pitch = |other: &&Note| other.frequency == note.frequency;
+        let Some(index) = self.notes.iter().position(|other| same_pitch(&other) && other.beat < note.end_beat() && note.beat < other.end_beat()) else {
+            self.notes.push(note);
+            return self.notes.len() - 1;
+        };
+        let existing = &self.notes[index];
+        let previous_end = self.notes.iter().filter(same_pitch).filter(|other| other.beat < existing.beat).map(|other| other.end_beat()).fold(f32::NEG_INFINITY, f32::max);
+        let next_start = self.notes.iter().filter(same_pitch).filter(|other| other.beat > existing.beat).map(|other| other.beat).fold(f32::INFINITY, f32::min);
+        let start = note.beat.min(existing.beat).max(previous_end);
+        let end = note.end_beat().max(existing.end_beat()).min(next_start);
+        let existing = &mut self.notes[index];
+        existing.beat = start;
+        existing.duration = end - start;
+        index
+    }
+
+    /// Adds every note or none of them if any of them can't be added
+    pub fn add_notes(&mut self, notes: Vec<Note>) -> Result<(), &'static str> {
+        let old_notes = self.notes.clone();
+        for note in notes {
+            if let Err(err) = self.add_note(note) {
+                self.notes = old_notes;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
     pub fn delete_note(&mut self, index: usize) {
         self.notes.remove(index);
     }
@@ -48,6 +111,121 @@ impl Part {
         }
         final_note_end
     }
+
+    /// Adds the sound of every note in the part onto `samples`, where the first sample is at beat 0.
+    /// Each note starts its waveform at phase zero, or in legato mode carries on the phase and envelope
+    /// of the note it directly follows so the waveform has no jumps at the note boundary.
+    /// Fails if the part's arpeggiator can't play its notes
+    pub fn render(&self, samples: &mut [f32], bpm: f32, sample_rate: u32) -> Result<(), &'static str> {
+        if let Some(arpeggiator) = self.arpeggiator {
+            let part = Part { notes: arpeggiator.arpeggiate(&self.notes)?, arpeggiator: None, ..self.clone() };
+            return part.render(samples, bpm, sample_rate);
+        }
+        let filter = match self.filter {
+            Some(filter) => filter,
+            None => {
+                self.render_notes(samples, None, bpm, sample_rate);
+                return Ok(());
+            }
+        };
+        let mut unfiltered = vec![0.0; samples.len()];
+        let mut cutoff_octaves = vec![0.0; samples.len()];
+        self.render_notes(&mut unfiltered, Some(&mut cutoff_octaves), bpm, sample_rate);
+        let mut biquad = Biquad::new(BiquadKind::LowPass, filter.cutoff, filter.resonance, 0.0, sample_rate);
+        for (i, sample) in samples.iter_mut().enumerate() {
+            // moving the cutoff is expensive so it only follows the LFOs every few samples
+            if i % 16 == 0 {
+                let cutoff = filter.cutoff * 2_f32.powf(cutoff_octaves[i]);
+                biquad.set(BiquadKind::LowPass, cutoff, filter.resonance, 0.0, sample_rate);
+            }
+            *sample += biquad.process(unfiltered[i]);
+        }
+        Ok(())
+    }
+
+    /// Adds the notes onto `samples` and the octaves the LFOs move the filter cutoff by onto `cutoff_octaves`
+    fn render_notes(&self, samples: &mut [f32], mut cutoff_octaves: Option<&mut [f32]>, bpm: f32, sample_rate: u32) {
+        let seconds_per_beat = 60.0 / bpm;
+        let samples_per_beat = seconds_per_beat * sample_rate as f32;
+        let release_seconds = self.instrument.release_seconds();
+        let num_samples = samples.len();
+        let to_sample = |beat: f32| ((beat * samples_per_beat).ceil().max(0.0) as usize).min(num_samples);
+        let mut notes: Vec<&Note> = self.notes.iter().collect();
+        notes.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        // phase in cycles since the start of the phrase, kept as f64 so long notes stay accurate
+        let mut phase: f64 = 0.0;
+        let mut phrase_start: f32 = 0.0;
+        for (index, note) in notes.iter().enumerate() {
+            let continues_phrase = index > 0 && self.continues_legato(notes[index - 1], note);
+            if !continues_phrase {
+                phase = 0.0;
+                phrase_start = note.beat * seconds_per_beat;
+            }
+            // a legato note hands over to the next note instead of releasing
+            let end = match notes.get(index + 1) {
+                Some(next) if self.continues_legato(note, next) => to_sample(next.beat),
+                _ => ((note.end_beat() * seconds_per_beat + release_seconds) * sample_rate as f32).ceil() as usize
+            }.min(num_samples);
+            let held = note.end_beat() * seconds_per_beat - phrase_start;
+            let previous = if index > 0 { Some(notes[index - 1]) } else { None };
+            for (i, sample) in samples.iter_mut().enumerate().take(end).skip(to_sample(note.beat)) {
+                let time = i as f32 / sample_rate as f32;
+                let note_time = time - phrase_start;
+                let mut semitones = self.pitch_offset(note, previous, time - note.beat * seconds_per_beat, seconds_per_beat);
+                let mut volume = note.volume;
+                for lfo in &self.lfos {
+                    match lfo.target {
+                        LfoTarget::Pitch => semitones += lfo.value(note_time),
+                        LfoTarget::Amplitude => volume *= lfo.gain(note_time),
+                        LfoTarget::FilterCutoff => {
+                            if let Some(cutoff_octaves) = cutoff_octaves.as_mut() {
+                                cutoff_octaves[i] += lfo.value(note_time);
+                            }
+                        }
+                    }
+                }
+                let frequency = note.frequency * 2_f32.powf(semitones / 12.0);
+                *sample += self.instrument.sample(phase, frequency, note_time, held, sample_rate) * volume;
+                phase += frequency as f64 / sample_rate as f64;
+            }
+        }
+    }
+
+    /// Gets how many semitones `note` is away from its own pitch `note_seconds` after it started,
+    /// from gliding from the `previous` note and from its bend curve. Notes only glide from a note they
+    /// continue in legato, so there is no glide across rests or when legato is off
+    fn pitch_offset(&self, note: &Note, previous: Option<&Note>, note_seconds: f32, seconds_per_beat: f32) -> f32 {
+        // drums are picked by the pitch of the note so bending it would change the drum
+        if self.is_percussion() {
+            return 0.0;
+        }
+        let mut semitones = note.bend_at(note_seconds / seconds_per_beat);
+        if let Some(previous) = previous.filter(|previous| self.continues_legato(previous, note)) {
+            if self.glide > 0.0 && note_seconds < self.glide && previous.frequency > 0.0 && note.frequency > 0.0 {
+                let distance = 12.0 * (previous.frequency / note.frequency).log2();
+                semitones += distance * (1.0 - note_seconds.max(0.0) / self.glide);
+            }
+        }
+        semitones
+    }
+
+    /// Whether `next` picks up the phase of `note` because the part is legato and `next` starts before `note` ends
+    fn continues_legato(&self, note: &Note, next: &Note) -> bool {
+        self.legato && !self.is_percussion() && next.beat > note.beat && next.beat <= note.end_beat()
+    }
+
+    /// Whether the part plays drums, where each note picks a drum instead of a pitch
+    pub fn is_percussion(&self) -> bool {
+        matches!(self.instrument, Instrument::Drums)
+    }
+
+    /// Sets the level the part sends to an aux bus at. A level of 0 removes the send
+    pub fn set_send(&mut self, bus: usize, level: f32) {
+        self.sends.retain(|send| send.bus != bus);
+        if level > 0.0 {
+            self.sends.push(BusSend { bus, level });
+        }
+    }
 }
 
 
@@ -59,7 +237,15 @@ impl Default for Part {
             Note::new(0.0, 1.0, 440.0, 0.25).unwrap(),
             Note::new(2.0, 1.0, 440.0, 0.5).unwrap(),
             Note::new(3.0, 1.0, 293.99, 0.5).unwrap()
-            ]
+            ],
+            sends: Vec::new(),
+            instrument: Instrument::default(),
+            legato: false,
+            glide: 0.0,
+            lfos: Vec::new(),
+            filter: None,
+            pattern_instances: Vec::new(),
+            arpeggiator: None
         }
     }
 }
@@ -67,11 +253,36 @@ impl Default for Part {
 impl Display for Part {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "name: {}\n", self.name)?;
+        write!(f, "instrument: {}", self.instrument)?;
+        if self.legato {
+            writeln!(f, "legato")?;
+        }
+        if self.glide > 0.0 {
+            writeln!(f, "glide: {}s", self.glide)?;
+        }
+        if let Some(filter) = self.filter {
+            writeln!(f, "low pass filter: {}Hz, resonance {}", filter.cutoff, filter.resonance)?;
+        }
+        if let Some(arpeggiator) = self.arpeggiator {
+            writeln!(f, "{arpeggiator}")?;
+        }
+        for lfo in &self.lfos {
+            writeln!(f, "{lfo}")?;
+        }
         write!(f, "notes:\n")?;
         for note in self.notes.iter() {
             write!(f, "\t{note}\n")?;
         }
         write!(f, "\t]")?;
+        for instance in &self.pattern_instances {
+            write!(f, "\nplays pattern {} at beat {}", instance.pattern + 1, instance.beat)?;
+            if instance.transpose != 0.0 {
+                write!(f, " moved {} semitones", instance.transpose)?;
+            }
+        }
+        for send in self.sends.iter() {
+            write!(f, "\nsend: bus {} at {}", send.bus + 1, send.level)?;
+        }
         write!(f, "}}")
     }
 }
@@ -82,6 +293,34 @@ impl Serializable for Part {
     /// name_len: name
     /// u16: num_notes
     /// (notes)
+    /// u16: num_sends
+    /// (sends) u16: bus
+    /// (sends) f32: level
+    /// u16: size_of_instrument
+    /// size_of_instrument: instrument
+    /// u8: legato
+    /// f32: glide
+    /// u16: num_bent_notes
+    /// (bent notes) u16: note_index
+    /// (bent notes) u16: num_points
+    /// (bent notes) (points) f32: offset
+    /// (bent notes) (points) f32: semitones
+    /// u16: num_lfos
+    /// (lfos) u16: size_of_lfo
+    /// (lfos) size_of_lfo: lfo
+    /// u8: has_filter
+    /// (filter) f32: cutoff
+    /// (filter) f32: resonance
+    /// u16: num_pattern_instances
+    /// (pattern instances) u16: pattern
+    /// (pattern instances) f32: beat
+    /// (pattern instances) f32: transpose
+    /// u8: has_arpeggiator
+    /// (arpeggiator) u8: mode
+    /// (arpeggiator) f32: rate
+    /// (arpeggiator) f32: gate
+    /// (arpeggiator) u8: octaves
+    /// (arpeggiator) u64: seed
     fn serialize(&self) -> Result<Vec<u8>, &'static str> {
         let mut serialized_data = Vec::new();
         // Serialize the name
@@ -107,6 +346,59 @@ impl Serializable for Part {
                 }
             }
         }
+        // Serialize sends
+        serialized_data.extend((self.sends.len() as u16).to_le_bytes());
+        for send in &self.sends {
+            serialized_data.extend((send.bus as u16).to_le_bytes());
+            serialized_data.extend(send.level.to_le_bytes());
+        }
+        // Serialize instrument
+        write_sized(&mut serialized_data, &self.instrument, "Could not serialize part. Instrument too large!")?;
+        serialized_data.push(self.legato as u8);
+        // Serialize glide and bends
+        serialized_data.extend(self.glide.to_le_bytes());
+        let bent_notes: Vec<(usize, &Note)> = self.notes.iter().enumerate().filter(|(_, note)| !note.bend.is_empty()).collect();
+        serialized_data.extend((bent_notes.len() as u16).to_le_bytes());
+        for (index, note) in bent_notes {
+            serialized_data.extend((index as u16).to_le_bytes());
+            serialized_data.extend((note.bend.len() as u16).to_le_bytes());
+            for point in &note.bend {
+                serialized_data.extend(point.offset.to_le_bytes());
+                serialized_data.extend(point.semitones.to_le_bytes());
+            }
+        }
+        // Serialize lfos and filter
+        serialized_data.extend((self.lfos.len() as u16).to_le_bytes());
+        for lfo in &self.lfos {
+            write_sized(&mut serialized_data, lfo, "Could not serialize part. LFO too large!")?;
+        }
+        match self.filter {
+            Some(filter) => {
+                serialized_data.push(1);
+                serialized_data.extend(filter.cutoff.to_le_bytes());
+                serialized_data.extend(filter.resonance.to_le_bytes());
+            }
+            None => serialized_data.push(0)
+        }
+        // Serialize pattern instances
+        serialized_data.extend((self.pattern_instances.len() as u16).to_le_bytes());
+        for instance in &self.pattern_instances {
+            serialized_data.extend((instance.pattern as u16).to_le_bytes());
+            serialized_data.extend(instance.beat.to_le_bytes());
+            serialized_data.extend(instance.transpose.to_le_bytes());
+        }
+        // Serialize arpeggiator
+        match self.arpeggiator {
+            Some(arpeggiator) => {
+                serialized_data.push(1);
+                serialized_data.push(arpeggiator.mode as u8);
+                serialized_data.extend(arpeggiator.rate.to_le_bytes());
+                serialized_data.extend(arpeggiator.gate.to_le_bytes());
+                serialized_data.push(arpeggiator.octaves);
+                serialized_data.extend(arpeggiator.seed.to_le_bytes());
+            }
+            None => serialized_data.push(0)
+        }
         Ok(serialized_data)
     }
 
@@ -139,6 +431,223 @@ impl Serializable for Part {
             notes.push(Note::deserialize(note_bytes)?);
             remaining_bytes = &remaining_bytes[16..];
         }
-        Ok(Self { name, notes })
+        // Deserialize sends. Parts saved before buses existed end here
+        let mut reader = ByteReader::new(remaining_bytes);
+        let mut sends = Vec::new();
+        if !reader.is_empty() {
+            let num_sends = reader.read_u16("Invalid serialized data! Insufficient length for number of sends!")?;
+            for _ in 0..num_sends {
+                let bus = reader.read_u16("Invalid serialized data! Insufficient length for send")? as usize;
+                let level = reader.read_f32("Invalid serialized data! Insufficient length for send")?;
+                sends.push(BusSend { bus, level });
+            }
+        }
+        // Deserialize instrument. Parts saved before instruments existed end here
+        let mut instrument = Instrument::default();
+        if !reader.is_empty() {
+            instrument = reader.read_sized("Invalid serialized data! Missing instrument data!")?;
+        }
+        // Deserialize legato. Parts saved before legato existed end here
+        let mut legato = false;
+        if !reader.is_empty() {
+            legato = reader.read_u8("Invalid serialized data! Missing legato data!")? != 0;
+        }
+        // Deserialize glide and bends. Parts saved before bends existed end here
+        let mut glide = 0.0;
+        if !reader.is_empty() {
+            let err = "Invalid serialized data! Missing pitch bend data!";
+            glide = reader.read_f32(err)?;
+            let num_bent_notes = reader.read_u16(err)?;
+            for _ in 0..num_bent_notes {
+                let index = reader.read_u16(err)? as usize;
+                let num_points = reader.read_u16(err)?;
+                let mut bend = Vec::new();
+                for _ in 0..num_points {
+                    bend.push(BendPoint { offset: reader.read_f32(err)?, semitones: reader.read_f32(err)? });
+                }
+                match notes.get_mut(index) {
+                    Some(note) => note.set_bend(bend),
+                    None => return Err("Invalid serialized data! Pitch bend for a missing note!")
+                }
+            }
+        }
+        // Deserialize lfos and filter. Parts saved before LFOs existed end here
+        let mut lfos = Vec::new();
+        let mut filter = None;
+        if !reader.is_empty() {
+            let err = "Invalid serialized data! Missing LFO data!";
+            let num_lfos = reader.read_u16(err)?;
+            for _ in 0..num_lfos {
+                lfos.push(reader.read_sized(err)?);
+            }
+            if reader.read_u8("Invalid serialized data! Missing filter data!")? != 0 {
+                filter = Some(PartFilter {
+                    cutoff: reader.read_f32("Invalid serialized data! Missing filter data!")?,
+                    resonance: reader.read_f32("Invalid serialized data! Missing filter data!")?
+                });
+            }
+        }
+        // Deserialize pattern instances. Parts saved before patterns existed end here
+        let mut pattern_instances = Vec::new();
+        if !reader.is_empty() {
+            let err = "Invalid serialized data! Missing pattern instance data!";
+            let num_instances = reader.read_u16(err)?;
+            for _ in 0..num_instances {
+                pattern_instances.push(PatternInstance {
+                    pattern: reader.read_u16(err)? as usize,
+                    beat: reader.read_f32(err)?,
+                    transpose: reader.read_f32(err)?
+                });
+            }
+        }
+        // Deserialize arpeggiator. Parts saved before arpeggiators existed end here
+        let mut arpeggiator = None;
+        if !reader.is_empty() && reader.read_u8("Invalid serialized data! Missing arpeggiator data!")? != 0 {
+            let err = "Invalid serialized data! Missing arpeggiator data!";
+            arpeggiator = Some(Arpeggiator {
+                mode: ArpeggioMode::from_u8(reader.read_u8(err)?).ok_or("Invalid serialized data! Unknown arpeggiator mode!")?,
+                rate: reader.read_f32(err)?,
+                gate: reader.read_f32(err)?,
+                octaves: reader.read_u8(err)?,
+                seed: u64::from_le_bytes(reader.read_bytes(8, err)?.try_into().unwrap())
+            });
+        }
+        if let Some(arpeggiator) = arpeggiator {
+            arpeggiator.check()?;
+        }
+        Ok(Self { name, notes, sends, instrument, legato, glide, lfos, filter, pattern_instances, arpeggiator })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::drums::DrumVoice;
+    use crate::music::lfo::LfoShape;
+
+    fn part_with(notes: &[(f32, f32, f32)]) -> Part {
+        let mut part = Part::new("test".to_string());
+        for (beat, duration, frequency) in notes {
+            part.add_note(Note::new(*beat, *duration, *frequency, 0.5).unwrap()).unwrap();
+        }
+        part
+    }
+
+    fn render(part: &Part, sample_rate: u32) -> Vec<f32> {
+        let mut samples = vec![0.0; (part.duration() * sample_rate as f32) as usize];
+        // at 60 bpm every beat lasts a second
+        part.render(&mut samples, 60.0, sample_rate).unwrap();
+        samples
+    }
+
+    #[test]
+    fn legato_notes_carry_on_the_phase_without_jumps() {
+        // the first note ends a quarter of the way through a cycle, where restarting would jump by the full volume
+        let mut part = part_with(&[(0.0, 1.0, 440.25), (1.0, 1.0, 660.0)]);
+        part.legato = true;
+        let samples = render(&part, 8000);
+        // a sine can move no further between samples than its steepest slope allows
+        let steepest = 2.0 * std::f32::consts::PI * 660.0 / 8000.0 * 0.5;
+        let largest_jump = samples.windows(2).map(|pair| (pair[1] - pair[0]).abs()).fold(0.0, f32::max);
+        assert!(largest_jump <= steepest * 1.01, "jump of {largest_jump} is steeper than {steepest}");
+    }
+
+    #[test]
+    fn notes_without_legato_start_at_phase_zero() {
+        let part = part_with(&[(0.0, 1.0, 440.0), (1.0, 1.0, 660.0)]);
+        let samples = render(&part, 8000);
+        assert_eq!(samples[0], 0.0);
+        assert_eq!(samples[8000], 0.0);
+    }
+
+    #[test]
+    fn glide_starts_from_the_pitch_of_a_legato_note() {
+        let mut part = part_with(&[(0.0, 1.0, 440.0), (1.0, 1.0, 880.0)]);
+        part.glide = 0.1;
+        part.legato = true;
+        let offset = |seconds: f32| part.pitch_offset(&part.notes[1], Some(&part.notes[0]), seconds, 0.5);
+        assert!((offset(0.0) + 12.0).abs() < 0.001);
+        assert!((offset(0.05) + 6.0).abs() < 0.001);
+        assert_eq!(offset(0.1), 0.0);
+    }
+
+    #[test]
+    fn glide_needs_legato_and_notes_without_a_rest_between() {
+        let mut part = part_with(&[(0.0, 1.0, 440.0), (1.0, 1.0, 880.0), (3.0, 1.0, 440.0)]);
+        part.glide = 0.1;
+        assert_eq!(part.pitch_offset(&part.notes[1], Some(&part.notes[0]), 0.0, 0.5), 0.0);
+        part.legato = true;
+        assert_eq!(part.pitch_offset(&part.notes[2], Some(&part.notes[1]), 0.0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn bends_follow_their_curve_and_are_saved() {
+        let mut part = part_with(&[(0.0, 2.0, 440.0)]);
+        part.glide = 0.25;
+        part.notes[0].set_bend(vec![BendPoint { offset: 1.5, semitones: 0.0 }, BendPoint { offset: 0.5, semitones: 2.0 }]);
+        let loaded = Part::deserialize(&part.serialize().unwrap()).unwrap();
+        assert_eq!(loaded.glide, 0.25);
+        for note in [&part.notes[0], &loaded.notes[0]] {
+            assert_eq!(note.bend_at(0.0), 2.0);
+            assert_eq!(note.bend_at(1.0), 1.0);
+            assert_eq!(note.bend_at(2.0), 0.0);
+        }
+    }
+
+    #[test]
+    fn tremolo_dips_the_volume() {
+        let mut part = part_with(&[(0.0, 1.0, 440.0)]);
+        let steady = render(&part, 8000);
+        part.lfos.push(Lfo::new(LfoShape::Square, LfoTarget::Amplitude, 1.0, 0.5, 0.0, 0.0));
+        let tremolo = render(&part, 8000);
+        let peak = |samples: &[f32]| samples.iter().fold(0.0, |peak: f32, sample| peak.max(sample.abs()));
+        // full volume for the first half of the cycle, half volume for the second
+        assert!((peak(&tremolo[..4000]) - peak(&steady[..4000])).abs() < 1e-4);
+        assert!((peak(&tremolo[4000..]) - peak(&steady[4000..]) * 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn the_filter_takes_out_frequencies_over_the_cutoff() {
+        let mut part = part_with(&[(0.0, 1.0, 3000.0)]);
+        let unfiltered = render(&part, 16000);
+        part.filter = Some(PartFilter { cutoff: 200.0, resonance: 0.707 });
+        let filtered = render(&part, 16000);
+        let energy = |samples: &[f32]| samples.iter().map(|sample| sample * sample).sum::<f32>();
+        assert!(energy(&filtered) < energy(&unfiltered) * 0.01);
+    }
+
+    #[test]
+    fn lfos_and_the_filter_are_saved() {
+        let mut part = part_with(&[(0.0, 1.0, 440.0)]);
+        part.lfos.push(Lfo::new(LfoShape::Sine, LfoTarget::Pitch, 5.0, 0.3, 0.2, 0.5));
+        part.lfos.push(Lfo::new(LfoShape::Random, LfoTarget::FilterCutoff, 2.0, 1.0, 0.0, 0.0));
+        part.filter = Some(PartFilter { cutoff: 1200.0, resonance: 2.0 });
+        let loaded = Part::deserialize(&part.serialize().unwrap()).unwrap();
+        assert_eq!(loaded.to_string(), part.to_string());
+        assert_eq!(loaded.lfos.len(), 2);
+        assert!(loaded.filter.is_some());
+    }
+
+    #[test]
+    fn drum_parts_hit_different_drums_at_once_and_are_saved() {
+        let mut part = Part::new("drums".to_string());
+        part.instrument = Instrument::Drums;
+        let kick = DrumVoice::Kick.frequency();
+        part.add_note(Note::new(0.0, 1.0, kick, 1.0).unwrap()).unwrap();
+        part.add_note(Note::new(0.0, 1.0, DrumVoice::ClosedHiHat.frequency(), 1.0).unwrap()).unwrap();
+        assert!(part.add_note(Note::new(0.5, 1.0, kick, 1.0).unwrap()).is_err());
+        let loaded = Part::deserialize(&part.serialize().unwrap()).unwrap();
+        assert!(loaded.is_percussion());
+        assert_eq!(loaded.notes.len(), 2);
+    }
+
+    #[test]
+    fn only_notes_of_the_same_pitch_cant_overlap() {
+        let mut part = part_with(&[(0.0, 2.0, 440.0), (0.0, 2.0, 550.0)]);
+        assert!(part.add_note(Note::new(1.0, 2.0, 440.0, 0.5).unwrap()).is_err());
+        // a note around another one overlaps it as well
+        assert!(part.add_note(Note::new(0.0, 3.0, 550.0, 0.5).unwrap()).is_err());
+        part.add_note(Note::new(2.0, 1.0, 440.0, 0.5).unwrap()).unwrap();
+        assert_eq!(part.notes.len(), 3);
     }
 }
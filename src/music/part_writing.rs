@@ -0,0 +1,254 @@
+use std::fmt::Display;
+
+use super::part::Part;
+use super::pitch::{midi_from_frequency, name_from_midi};
+use super::song::Song;
+
+/// Leeway when checking whether a note starts as the one before it ends
+const TOUCH_TOLERANCE: f32 = 0.0001;
+/// Widest leap in semitones a voice can make between notes following each other without a rest
+const MAX_LEAP: i32 = 12;
+
+/// The voices of four part harmony, highest first
+#[derive(Clone, Copy, PartialEq)]
+pub enum Voice {
+    Soprano,
+    Alto,
+    Tenor,
+    Bass,
+}
+
+/// A rule of part writing that [Song::check_part_writing] found broken
+#[derive(Clone, Copy, PartialEq)]
+pub enum PartWritingRule {
+    // two voices a fifth apart both moving the same way to another fifth
+    ParallelFifths,
+    // two voices an octave or unison apart both moving the same way to another octave or unison
+    ParallelOctaves,
+    // a voice going below the voice under it
+    VoiceCrossing,
+    OutOfRange,
+    LargeLeap,
+}
+
+/// A place a song breaks a rule of part writing
+pub struct PartWritingIssue {
+    pub rule: PartWritingRule,
+    pub beat: f32,
+    // names of the parts breaking the rule, the higher voice first
+    pub parts: Vec<String>,
+    // the notes breaking the rule
+    pub detail: String,
+}
+
+/// The notes of a part as a single voice, one pitch at a time
+struct Line {
+    name: String,
+    // start beat, end beat and MIDI note number of every note in order
+    notes: Vec<(f32, f32, u8)>,
+    voice: Option<Voice>,
+}
+
+impl Voice {
+    /// Lowest and highest MIDI notes the voice is usually written for
+    pub fn range(&self) -> (u8, u8) {
+        match self {
+            Voice::Soprano => (60, 79),
+            Voice::Alto => (55, 74),
+            Voice::Tenor => (48, 67),
+            Voice::Bass => (40, 60),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Voice::Soprano => "soprano",
+            Voice::Alto => "alto",
+            Voice::Tenor => "tenor",
+            Voice::Bass => "bass",
+        }
+    }
+
+    /// The voice a part is named after, like `Tenor` or `Bass 2`
+    fn from_part_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        [Voice::Soprano, Voice::Alto, Voice::Tenor, Voice::Bass].into_iter().find(|voice| name.contains(voice.name()))
+    }
+}
+
+impl PartWritingRule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PartWritingRule::ParallelFifths => "parallel fifths",
+            PartWritingRule::ParallelOctaves => "parallel octaves",
+            PartWritingRule::VoiceCrossing => "voice crossing",
+            PartWritingRule::OutOfRange => "out of range",
+            PartWritingRule::LargeLeap => "large leap",
+        }
+    }
+}
+
+impl Line {
+    /// Reads the notes of a part as one voice. Where the part plays several notes at once the highest is its pitch
+    fn new(part: &Part) -> Self {
+        let mut notes: Vec<(f32, f32, u8)> = part.notes.iter().map(|note| (note.beat, note.end_beat(), midi_from_frequency(note.frequency))).collect();
+        notes.sort_by(|a, b| a.0.total_cmp(&b.0).then(b.2.cmp(&a.2)));
+        notes.dedup_by(|note, kept| note.0 == kept.0);
+        Line { name: part.name.clone(), notes, voice: None }
+    }
+
+    /// Pitch the voice is singing on a beat
+    fn pitch_at(&self, beat: f32) -> Option<u8> {
+        self.notes.iter().rev().find(|(start, end, _)| *start <= beat && beat < *end).map(|(_, _, midi)| *midi)
+    }
+
+    fn mean_pitch(&self) -> f32 {
+        self.notes.iter().map(|(_, _, midi)| *midi as f32).sum::<f32>() / self.notes.len() as f32
+    }
+}
+
+impl Song {
+    /// Checks the parts of the song as the voices of a harmony exercise for parallel fifths and octaves, voices
+    /// crossing, notes outside the range of their voice and leaps wider than an octave. Parts are ordered from the
+    /// highest voice down by their average pitch and drum parts are left out. Parts named after a voice like `Alto`
+    /// are checked against its range, as are all four parts when there are exactly four. Returns every issue in
+    /// order of the beat it happens on
+    pub fn check_part_writing(&self) -> Vec<PartWritingIssue> {
+        let mut lines: Vec<Line> = self.arranged_parts().iter().filter(|part| !part.is_percussion() && !part.notes.is_empty()).map(Line::new).collect();
+        lines.sort_by(|a, b| b.mean_pitch().total_cmp(&a.mean_pitch()));
+        let four_parts = lines.len() == 4;
+        for (index, line) in lines.iter_mut().enumerate() {
+            line.voice = Voice::from_part_name(&line.name).or(if four_parts { Some([Voice::Soprano, Voice::Alto, Voice::Tenor, Voice::Bass][index]) } else { None });
+        }
+        let mut issues = Vec::new();
+        for line in &lines {
+            check_line(line, &mut issues);
+        }
+        for (index, upper) in lines.iter().enumerate() {
+            for lower in &lines[index + 1..] {
+                check_voice_pair(upper, lower, &mut issues);
+            }
+        }
+        issues.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        issues
+    }
+}
+
+/// Checks the range and leaps of a single voice
+fn check_line(line: &Line, issues: &mut Vec<PartWritingIssue>) {
+    let issue = |rule: PartWritingRule, beat: f32, detail: String| PartWritingIssue { rule, beat, parts: vec![line.name.clone()], detail };
+    if let Some(voice) = line.voice {
+        let (low, high) = voice.range();
+        for (beat, _, midi) in line.notes.iter().filter(|(_, _, midi)| *midi < low || *midi > high) {
+            let detail = format!("{} is outside the {} range of {} to {}", name_from_midi(*midi), voice.name(), name_from_midi(low), name_from_midi(high));
+            issues.push(issue(PartWritingRule::OutOfRange, *beat, detail));
+        }
+    }
+    for pair in line.notes.windows(2) {
+        let ((_, end, from), (beat, _, to)) = (pair[0], pair[1]);
+        let leap = (to as i32 - from as i32).abs();
+        // a rest between the notes starts a new phrase
+        if beat <= end + TOUCH_TOLERANCE && leap > MAX_LEAP {
+            issues.push(issue(PartWritingRule::LargeLeap, beat, format!("{} to {} leaps {leap} semitones", name_from_midi(from), name_from_midi(to))));
+        }
+    }
+}
+
+/// Checks two voices for crossing and parallel fifths and octaves everywhere either of them moves
+fn check_voice_pair(upper: &Line, lower: &Line, issues: &mut Vec<PartWritingIssue>) {
+    let mut moments: Vec<f32> = upper.notes.iter().chain(&lower.notes).map(|(beat, _, _)| *beat).collect();
+    moments.sort_by(|a, b| a.total_cmp(b));
+    moments.dedup();
+    let issue = |rule: PartWritingRule, beat: f32, detail: String| PartWritingIssue { rule, beat, parts: vec![upper.name.clone(), lower.name.clone()], detail };
+    let mut previous: Option<(u8, u8)> = None;
+    for beat in moments {
+        let (Some(high), Some(low)) = (upper.pitch_at(beat), lower.pitch_at(beat)) else {
+            previous = None;
+            continue;
+        };
+        // crossings are reported where they start rather than on every beat they last
+        if high < low && previous.is_none_or(|(high, low)| high >= low) {
+            issues.push(issue(PartWritingRule::VoiceCrossing, beat, format!("{} is below {}", name_from_midi(high), name_from_midi(low))));
+        }
+        if let Some((previous_high, previous_low)) = previous {
+            let upper_motion = (high as i32 - previous_high as i32).signum();
+            let lower_motion = (low as i32 - previous_low as i32).signum();
+            let interval = (high as i32 - low as i32).abs() % 12;
+            let previous_interval = (previous_high as i32 - previous_low as i32).abs() % 12;
+            if upper_motion != 0 && upper_motion == lower_motion && interval == previous_interval && (interval == 0 || interval == 7) {
+                let rule = if interval == 7 { PartWritingRule::ParallelFifths } else { PartWritingRule::ParallelOctaves };
+                let detail = format!("{}-{} to {}-{}", name_from_midi(previous_high), name_from_midi(previous_low), name_from_midi(high), name_from_midi(low));
+                issues.push(issue(rule, beat, detail));
+            }
+        }
+        previous = Some((high, low));
+    }
+}
+
+impl Display for PartWritingIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "beat {}: {} in {}: {}", self.beat, self.rule.name(), self.parts.join(" and "), self.detail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::{parse_melody, Instrument};
+
+    fn song_of(voices: &[(&str, &str)]) -> Song {
+        let mut song = Song::new("Chorale".to_string(), 120);
+        for (name, melody) in voices {
+            let mut part = Part::new(name.to_string());
+            part.notes = parse_melody(melody, 0.0, 0.5).unwrap();
+            song.parts.push(part);
+        }
+        song
+    }
+
+    fn rules(song: &Song) -> Vec<(f32, &'static str)> {
+        song.check_part_writing().iter().map(|issue| (issue.beat, issue.rule.name())).collect()
+    }
+
+    #[test]
+    fn good_four_part_writing_has_no_issues() {
+        let song = song_of(&[("Soprano", "E5/4 F5/4 D5/4 E5/4"), ("Alto", "G4/4 A4/4 G4/4 G4/4"), ("Tenor", "C4/4 C4/4 B3/4 C4/4"), ("Bass", "C3/4 F3/4 G3/4 C3/4")]);
+        assert!(rules(&song).is_empty());
+    }
+
+    #[test]
+    fn finds_parallel_fifths_and_octaves() {
+        let song = song_of(&[("Upper", "G4/4 A4/4 C5/4 D5/4"), ("Lower", "C4/4 D4/4 C4/4 D4/4")]);
+        assert_eq!(rules(&song), [(1.0, "parallel fifths"), (3.0, "parallel octaves")]);
+        let issue = &song.check_part_writing()[0];
+        assert_eq!(issue.parts, ["Upper", "Lower"]);
+        assert_eq!(issue.to_string(), "beat 1: parallel fifths in Upper and Lower: G4-C4 to A4-D4");
+    }
+
+    #[test]
+    fn contrary_motion_to_a_fifth_is_allowed() {
+        let song = song_of(&[("Upper", "C5/4 D5/4"), ("Lower", "C4/4 G3/4")]);
+        assert!(rules(&song).is_empty());
+    }
+
+    #[test]
+    fn reports_crossing_where_it_starts() {
+        let song = song_of(&[("Upper", "E4/4 E4/4 C4/4 C4/4 E4/4"), ("Lower", "C4/4 C4/4 E4/4 E4/4 C4/4")]);
+        assert_eq!(rules(&song), [(2.0, "voice crossing")]);
+    }
+
+    #[test]
+    fn checks_ranges_of_named_voices_and_leaps() {
+        let song = song_of(&[("Soprano", "C6/4 G5/4"), ("Bass 2", "C2/4 D3/4 r/4 E2/4")]);
+        assert_eq!(rules(&song), [(0.0, "out of range"), (0.0, "out of range"), (1.0, "large leap")]);
+        // a rest between the notes makes a new phrase rather than a leap
+        assert_eq!(song.check_part_writing()[2].detail, "C2 to D3 leaps 14 semitones");
+    }
+
+    #[test]
+    fn leaves_out_drum_parts() {
+        let mut song = song_of(&[("Upper", "G4/4 A4/4"), ("Drums", "C4/4 D4/4")]);
+        song.parts[1].instrument = Instrument::Drums;
+        assert!(rules(&song).is_empty());
+    }
+}
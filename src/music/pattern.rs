@@ -0,0 +1,241 @@
+use super::note::{BendPoint, Note};
+use super::part::Part;
+use super::serializable::{write_string, ByteReader, Serializable};
+use super::song::Song;
+
+/// A named sequence of notes owned by a [Song] that its parts can play at any beat, like a chorus played
+/// several times. Parts place a [PatternInstance] wherever the pattern should play instead of holding copies of its notes
+#[derive(Clone)]
+pub struct Pattern {
+    pub name: String,
+    // kept with the first note on beat 0 so instances start on their own beat
+    pub notes: Vec<Note>,
+}
+
+/// A pattern of the song played by a [Part] from a beat, moved up or down by a number of semitones
+#[derive(Clone, Copy)]
+pub struct PatternInstance {
+    // index of the pattern in the song
+    pub pattern: usize,
+    pub beat: f32,
+    pub transpose: f32,
+}
+
+impl Pattern {
+    /// Makes a pattern of notes, moving them so the first one starts on beat 0
+    pub fn new(name: String, mut notes: Vec<Note>) -> Result<Self, &'static str> {
+        let first_beat = notes.iter().map(|note| note.beat).min_by(|a, b| a.total_cmp(b)).ok_or("A pattern needs at least one note!")?;
+        for note in &mut notes {
+            note.beat -= first_beat;
+        }
+        Ok(Pattern { name, notes })
+    }
+
+    /// The notes of the pattern as an instance of it plays them
+    fn instance_notes(&self, instance: &PatternInstance) -> Vec<Note> {
+        self.notes.iter().map(|note| {
+            let mut note = note.clone();
+            note.beat += instance.beat;
+            note.frequency *= 2_f32.powf(instance.transpose / 12.0);
+            note
+        }).collect()
+    }
+
+    /// Beats from the start of the pattern to the end of its last note
+    pub fn duration(&self) -> f32 {
+        self.notes.iter().map(|note| note.end_beat()).fold(0.0, f32::max)
+    }
+}
+
+impl Part {
+    /// Gets a copy of the part as it sounds, with the notes of every pattern instance added to its own notes.
+    /// Notes are added like [Part::add_note] adds them, so pattern notes overlapping a note of the same pitch are
+    /// left out. The copy has no pattern instances left so it plays the same in any song
+    pub fn with_patterns(&self, patterns: &[Pattern]) -> Part {
+        let mut part = self.clone();
+        part.pattern_instances.clear();
+        for instance in &self.pattern_instances {
+            let Some(pattern) = patterns.get(instance.pattern) else { continue };
+            for note in pattern.instance_notes(instance) {
+                // a note breaking the rule is left out, the rest of the pattern still plays
+                let _ = part.add_note(note);
+            }
+        }
+        part
+    }
+}
+
+impl Song {
+    /// Gets every part as it sounds, with the notes of the patterns placed in it and its sections in the order of the arrangement
+    pub fn arranged_parts(&self) -> Vec<Part> {
+        self.arrange(self.parts.iter().map(|part| part.with_patterns(&self.patterns)).collect())
+    }
+
+    /// Places the pattern at index `pattern` in the part at index `part`, starting on `beat` and moved by `transpose` semitones.
+    /// Nothing is placed if a note of the pattern would overlap a note of the same pitch the part already plays
+    pub fn place_pattern(&mut self, part: usize, pattern: usize, beat: f32, transpose: f32) -> Result<(), &'static str> {
+        let Some(placed) = self.patterns.get(pattern) else {
+            return Err("No pattern at that index!");
+        };
+        if beat < 0.0 {
+            return Err("Patterns can't be placed before beat 0!");
+        }
+        let part = self.parts.get_mut(part).ok_or("No part at that index!")?;
+        if part.is_percussion() && transpose != 0.0 {
+            return Err("Drum notes pick their drum by pitch so drum patterns can't be transposed!");
+        }
+        let instance = PatternInstance { pattern, beat, transpose };
+        let sounding = part.with_patterns(&self.patterns);
+        if placed.instance_notes(&instance).iter().any(|note| sounding.overlaps_same_pitch(note, None)) {
+            return Err("The pattern would overlap notes of the same pitch!");
+        }
+        part.pattern_instances.push(instance);
+        Ok(())
+    }
+
+    /// Removes a pattern along with every instance of it
+    pub fn delete_pattern(&mut self, index: usize) {
+        self.patterns.remove(index);
+        for part in self.parts.iter_mut() {
+            part.pattern_instances.retain(|instance| instance.pattern != index);
+            for instance in part.pattern_instances.iter_mut() {
+                if instance.pattern > index {
+                    instance.pattern -= 1;
+                }
+            }
+        }
+    }
+}
+
+impl Serializable for Pattern {
+    /// Serializes a `Pattern` struct into a byte representation
+    /// u16: name_len
+    /// name_len: name
+    /// u16: num_notes
+    /// (notes)
+    /// u16: num_bent_notes
+    /// (bent notes) u16: note_index
+    /// (bent notes) u16: num_points
+    /// (bent notes) (points) f32: offset
+    /// (bent notes) (points) f32: semitones
+    fn serialize(&self) -> Result<Vec<u8>, &'static str> {
+        let mut serialized_data = Vec::new();
+        write_string(&mut serialized_data, &self.name, "Could not serialize pattern. Name too long!")?;
+        serialized_data.extend((self.notes.len() as u16).to_le_bytes());
+        for note in &self.notes {
+            serialized_data.extend(note.serialize()?);
+        }
+        let bent_notes: Vec<(usize, &Note)> = self.notes.iter().enumerate().filter(|(_, note)| !note.bend.is_empty()).collect();
+        serialized_data.extend((bent_notes.len() as u16).to_le_bytes());
+        for (index, note) in bent_notes {
+            serialized_data.extend((index as u16).to_le_bytes());
+            serialized_data.extend((note.bend.len() as u16).to_le_bytes());
+            for point in &note.bend {
+                serialized_data.extend(point.offset.to_le_bytes());
+                serialized_data.extend(point.semitones.to_le_bytes());
+            }
+        }
+        Ok(serialized_data)
+    }
+
+    fn deserialize(serialized_data: &[u8]) -> Result<Self, &'static str> {
+        let err = "Invalid serialized data! Missing pattern data!";
+        let mut reader = ByteReader::new(serialized_data);
+        let name = reader.read_string(err)?;
+        let num_notes = reader.read_u16(err)?;
+        let mut notes = Vec::new();
+        for _ in 0..num_notes {
+            notes.push(Note::deserialize(reader.read_bytes(16, err)?)?);
+        }
+        let num_bent_notes = reader.read_u16(err)?;
+        for _ in 0..num_bent_notes {
+            let index = reader.read_u16(err)? as usize;
+            let num_points = reader.read_u16(err)?;
+            let mut bend = Vec::new();
+            for _ in 0..num_points {
+                bend.push(BendPoint { offset: reader.read_f32(err)?, semitones: reader.read_f32(err)? });
+            }
+            match notes.get_mut(index) {
+                Some(note) => note.set_bend(bend),
+                None => return Err("Invalid serialized data! Pitch bend for a missing note!")
+            }
+        }
+        Ok(Pattern { name, notes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::{frequency_from_name, name_from_frequency, parse_melody};
+
+    fn song_with_pattern() -> Song {
+        let mut song = Song::new("Patterns".to_string(), 120);
+        song.patterns.push(Pattern::new("Riff".to_string(), parse_melody("r/4 C4/4 E4/8 G4/8", 0.0, 0.5).unwrap()).unwrap());
+        song.parts.push(Part::new("Lead".to_string()));
+        song
+    }
+
+    fn names(part: &Part) -> Vec<(f32, String)> {
+        let mut notes: Vec<(f32, String)> = part.notes.iter().map(|note| (note.beat, name_from_frequency(note.frequency))).collect();
+        notes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        notes
+    }
+
+    #[test]
+    fn patterns_start_on_their_first_note() {
+        let song = song_with_pattern();
+        assert_eq!(song.patterns[0].notes[0].beat, 0.0);
+        assert_eq!(song.patterns[0].duration(), 2.0);
+        assert!(Pattern::new("Empty".to_string(), Vec::new()).is_err());
+    }
+
+    #[test]
+    fn instances_play_the_pattern_moved_and_transposed() {
+        let mut song = song_with_pattern();
+        song.place_pattern(0, 0, 4.0, 0.0).unwrap();
+        song.place_pattern(0, 0, 8.0, 2.0).unwrap();
+        let arranged = song.arranged_parts();
+        let expected = [(4.0, "C4"), (5.0, "E4"), (5.5, "G4"), (8.0, "D4"), (9.0, "F#4"), (9.5, "A4")].map(|(beat, name)| (beat, name.to_string()));
+        assert_eq!(names(&arranged[0]), expected);
+        assert!(arranged[0].pattern_instances.is_empty());
+    }
+
+    #[test]
+    fn patterns_cant_overlap_notes_of_the_same_pitch() {
+        let mut song = song_with_pattern();
+        song.parts[0].add_note(Note::new(4.5, 1.0, frequency_from_name("C4").unwrap(), 0.5).unwrap()).unwrap();
+        assert!(song.place_pattern(0, 0, 4.0, 0.0).is_err());
+        // a chord with the part's note is fine
+        song.place_pattern(0, 0, 4.0, 4.0).unwrap();
+        assert!(song.place_pattern(0, 0, 4.5, 4.0).is_err());
+        assert_eq!(song.parts[0].pattern_instances.len(), 1);
+        // notes added to the part after the pattern was placed win over the pattern's
+        song.parts[0].add_note(Note::new(5.0, 0.5, frequency_from_name("G#4").unwrap(), 0.5).unwrap()).unwrap();
+        assert_eq!(song.arranged_parts()[0].notes.len(), 4);
+    }
+
+    #[test]
+    fn deleting_a_pattern_moves_the_instances_of_later_patterns_down() {
+        let mut song = song_with_pattern();
+        song.patterns.push(Pattern::new("Bass".to_string(), parse_melody("C3/2", 0.0, 0.5).unwrap()).unwrap());
+        song.place_pattern(0, 0, 0.0, 0.0).unwrap();
+        song.place_pattern(0, 1, 0.0, 0.0).unwrap();
+        song.delete_pattern(0);
+        assert_eq!(song.parts[0].pattern_instances.len(), 1);
+        assert_eq!(song.parts[0].pattern_instances[0].pattern, 0);
+        assert_eq!(song.patterns[0].name, "Bass");
+    }
+
+    #[test]
+    fn patterns_are_saved_with_their_bends() {
+        let mut pattern = song_with_pattern().patterns.remove(0);
+        pattern.notes[1].set_bend(vec![BendPoint { offset: 0.0, semitones: -1.0 }, BendPoint { offset: 0.5, semitones: 0.0 }]);
+        let loaded = Pattern::deserialize(&pattern.serialize().unwrap()).unwrap();
+        assert_eq!(loaded.name, "Riff");
+        assert_eq!(loaded.notes.len(), 3);
+        assert!(loaded.notes[0].bend.is_empty());
+        assert_eq!(loaded.notes[1].bend_at(0.25), -0.5);
+        assert!(Pattern::deserialize(&pattern.serialize().unwrap()[..20]).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// Gets the frequency of a MIDI note number where 69 is A4 at 440 Hz
+pub fn frequency_from_midi(number: u8) -> f32 {
+    440.0 * 2_f32.powf((number as f32 - 69.0) / 12.0)
+}
+
+/// Gets the nearest MIDI note number to a frequency
+pub fn midi_from_frequency(frequency: f32) -> u8 {
+    if frequency <= 0.0 {
+        return 0;
+    }
+    (69.0 + 12.0 * (frequency / 440.0).log2()).round().clamp(0.0, 127.0) as u8
+}
+
+/// Gets the MIDI note number of a note name in the form <Note a-g|A-G><Accidental b|#><Octave 0<=o<=9> e.g. A4 or C#6,
+/// where C4 is middle C
+pub fn midi_from_name(name: &str) -> Option<u8> {
+    let (semitone, octave) = read_pitch_letter(name)?;
+    let octave = octave.parse::<i32>().ok()?;
+    if !(0..=9).contains(&octave) {
+        return None;
+    }
+    let number = (octave + 1) * 12 + semitone;
+    u8::try_from(number).ok().filter(|number| *number <= 127)
+}
+
+/// Reads a note letter and an optional `#` or `b` from the start of `text`, returning the semitones above C,
+/// which are -1 for Cb and 12 for B#, and the rest of the text
+pub(crate) fn read_pitch_letter(text: &str) -> Option<(i32, &str)> {
+    let mut chars = text.chars();
+    let semitone: i32 = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None
+    };
+    let rest = chars.as_str();
+    Some(match rest.chars().next() {
+        Some('#') => (semitone + 1, &rest[1..]),
+        Some('b') => (semitone - 1, &rest[1..]),
+        _ => (semitone, rest)
+    })
+}
+
+pub fn frequency_from_name(name: &str) -> Option<f32> {
+    midi_from_name(name).map(frequency_from_midi)
+}
+
+/// Gets the name of a pitch class without an octave where 0 is C, spelling black keys with sharps
+pub(crate) fn pitch_class_name(pitch_class: u8) -> &'static str {
+    NOTE_NAMES[pitch_class as usize % 12]
+}
+
+/// Gets the name of a MIDI note number, spelling black keys with sharps
+pub fn name_from_midi(number: u8) -> String {
+    format!("{}{}", NOTE_NAMES[number as usize % 12], number as i32 / 12 - 1)
+}
+
+/// Gets the name of the nearest note to a frequency
+pub fn name_from_frequency(frequency: f32) -> String {
+    name_from_midi(midi_from_frequency(frequency))
+}
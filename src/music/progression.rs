@@ -0,0 +1,225 @@
+use super::note::Note;
+use super::part::Part;
+use super::pitch::frequency_from_midi;
+use super::signature::Key;
+use super::theory::{Chord, ChordQuality};
+
+/// Roman numerals of the seven scale degrees, longest first so `VII` isn't read as `V`
+const NUMERALS: [(&str, usize); 7] = [("VII", 6), ("III", 2), ("IV", 3), ("VI", 5), ("II", 1), ("V", 4), ("I", 0)];
+/// How strongly voicings are pulled back towards the register of the first chord, against moving the voices less
+const REGISTER_PULL: f32 = 0.5;
+
+/// How the notes of each chord of an [Accompaniment] are played
+#[derive(Clone, Copy, PartialEq)]
+pub enum AccompanimentStyle {
+    // every note of the chord struck together
+    Block,
+    // the notes of the chord one at a time from the lowest up, starting over at the top
+    Arpeggio,
+    // lowest, highest, middle, highest, over and over
+    Alberti,
+}
+
+/// Turns a chord progression into an accompaniment, moving between chords with as little movement of the voices as
+/// possible. Chords are struck or their notes played one after another every `rhythm` beats
+#[derive(Clone, Copy)]
+pub struct Accompaniment {
+    pub style: AccompanimentStyle,
+    pub rhythm: f32,
+    // octave the first chord is played in, 4 holds middle C
+    pub octave: i32,
+    pub volume: f32,
+}
+
+/// Reads a chord progression of Roman numerals like `I vi IV V` in a key, or of chord symbols like `Cmaj7 Am7 Dm7 G7`,
+/// the two can be mixed. Every chord lasts `beats_per_chord` beats and `-` holds the previous chord for as long again,
+/// `|` can be written between bars. Returns every chord with the number of beats it lasts
+pub fn parse_progression(text: &str, key: Key, beats_per_chord: f32) -> Result<Vec<(Chord, f32)>, &'static str> {
+    if beats_per_chord <= 0.0 {
+        return Err("Chords must last longer than 0 beats!");
+    }
+    let mut progression: Vec<(Chord, f32)> = Vec::new();
+    for token in text.split_whitespace().filter(|token| *token != "|") {
+        if token == "-" {
+            let (_, beats) = progression.last_mut().ok_or("A hold needs a chord before it!")?;
+            *beats += beats_per_chord;
+            continue;
+        }
+        let chord = match parse_numeral(token, key)? {
+            Some(chord) => chord,
+            None => Chord::parse(token).ok_or("Chord not recognised! Write Roman numerals like ii7 or chord symbols like Dm7")?,
+        };
+        progression.push((chord, beats_per_chord));
+    }
+    if progression.is_empty() {
+        return Err("The progression has no chords!");
+    }
+    Ok(progression)
+}
+
+/// Reads a Roman numeral chord like `V7`, `ii`, `bVII` or `vii°` in a key, or `None` when the token isn't one.
+/// Upper case numerals are major and lower case minor, degrees are counted on the major or natural minor scale of the key
+fn parse_numeral(token: &str, key: Key) -> Result<Option<Chord>, &'static str> {
+    let (accidental, rest) = match token.chars().next() {
+        Some('b') => (-1, &token[1..]),
+        Some('#') => (1, &token[1..]),
+        _ => (0, token),
+    };
+    let upper_case = rest.starts_with(['I', 'V']);
+    if !upper_case && !rest.starts_with(['i', 'v']) {
+        return Ok(None);
+    }
+    let case = |numeral: &str| if upper_case { numeral.to_string() } else { numeral.to_lowercase() };
+    let (numeral, degree) = NUMERALS.iter().find(|(numeral, _)| rest.starts_with(&case(numeral))).ok_or("Roman numeral not recognised!")?;
+    let pick = |major: ChordQuality, minor: ChordQuality| if upper_case { major } else { minor };
+    let quality = match &rest[numeral.len()..] {
+        "" => pick(ChordQuality::Major, ChordQuality::Minor),
+        "6" => pick(ChordQuality::Major6, ChordQuality::Minor6),
+        "7" => pick(ChordQuality::Dominant7, ChordQuality::Minor7),
+        "9" => pick(ChordQuality::Dominant9, ChordQuality::Minor9),
+        "11" => pick(ChordQuality::Dominant11, ChordQuality::Minor11),
+        "maj7" | "M7" | "Δ7" => pick(ChordQuality::Major7, ChordQuality::MinorMajor7),
+        "maj9" | "M9" => ChordQuality::Major9,
+        "13" => ChordQuality::Dominant13,
+        "°" | "o" | "dim" => ChordQuality::Diminished,
+        "°7" | "o7" | "dim7" => ChordQuality::Diminished7,
+        "ø" | "ø7" | "m7b5" => ChordQuality::HalfDiminished7,
+        "+" | "aug" => ChordQuality::Augmented,
+        "+7" | "aug7" => ChordQuality::Augmented7,
+        "sus2" => ChordQuality::Sus2,
+        "sus4" | "sus" => ChordQuality::Sus4,
+        "add9" => ChordQuality::Add9,
+        _ => return Err("Roman numeral has an unknown chord quality!"),
+    };
+    let root = (key.tonic as i32 + key.scale()[*degree] as i32 + accidental).rem_euclid(12) as u8;
+    Ok(Some(Chord { root, quality, inversion: 0 }))
+}
+
+/// Picks the MIDI notes every chord is played with. The first chord is played as written in `octave`, every
+/// chord after it in the inversion and octave that moves the voices the least while staying near the first chord.
+/// Chords written with a bass note keep their inversion
+pub fn voice_lead(chords: &[Chord], octave: i32) -> Result<Vec<Vec<u8>>, &'static str> {
+    let first = chords.first().ok_or("The progression has no chords!")?.midi_notes(octave)?;
+    let center = mean(&first);
+    let mut voicings = vec![first];
+    for chord in &chords[1..] {
+        let inversions = if chord.inversion > 0 { chord.inversion..chord.inversion + 1 } else { 0..chord.quality.intervals().len() as u8 };
+        let previous = &voicings[voicings.len() - 1];
+        let cost = |voicing: &Vec<u8>| movement(previous, voicing) + REGISTER_PULL * (mean(voicing) - center).abs();
+        let best = (octave - 1..=octave + 1)
+            .flat_map(|octave| inversions.clone().filter_map(move |inversion| Chord { inversion, ..*chord }.midi_notes(octave).ok()))
+            .min_by(|a, b| cost(a).total_cmp(&cost(b)))
+            .ok_or("Chord leaves the range of MIDI pitches!")?;
+        voicings.push(best);
+    }
+    Ok(voicings)
+}
+
+/// How far the voices move between two voicings, each note counted to the nearest note of the other voicing
+fn movement(from: &[u8], to: &[u8]) -> f32 {
+    let nearest = |midi: u8, notes: &[u8]| notes.iter().map(|other| (midi as i32 - *other as i32).abs()).min().unwrap_or(0);
+    (to.iter().map(|midi| nearest(*midi, from)).sum::<i32>() + from.iter().map(|midi| nearest(*midi, to)).sum::<i32>()) as f32
+}
+
+fn mean(notes: &[u8]) -> f32 {
+    notes.iter().map(|midi| *midi as f32).sum::<f32>() / notes.len().max(1) as f32
+}
+
+impl Accompaniment {
+    /// Makes the notes of the accompaniment for a progression of chords and the beats each lasts, starting on `beat`
+    pub fn notes(&self, progression: &[(Chord, f32)], beat: f32) -> Result<Vec<Note>, &'static str> {
+        if self.rhythm <= 0.0 {
+            return Err("Accompaniment rhythm must be longer than 0 beats!");
+        }
+        let chords: Vec<Chord> = progression.iter().map(|(chord, _)| *chord).collect();
+        let voicings = voice_lead(&chords, self.octave)?;
+        let mut notes = Vec::new();
+        let mut chord_beat = beat;
+        for ((_, beats), voicing) in progression.iter().zip(voicings) {
+            let end = chord_beat + beats;
+            let mut step = 0;
+            let mut hit = chord_beat;
+            // a little leeway so rounding doesn't add a sliver of a hit at the end of the chord
+            while hit < end - 0.0001 {
+                let duration = self.rhythm.min(end - hit);
+                let pitches = match self.style {
+                    AccompanimentStyle::Block => voicing.clone(),
+                    AccompanimentStyle::Arpeggio => vec![voicing[step % voicing.len()]],
+                    AccompanimentStyle::Alberti => {
+                        let top = voicing.len() - 1;
+                        vec![voicing[[0, top, voicing.len() / 2, top][step % 4]]]
+                    }
+                };
+                for midi in pitches {
+                    notes.push(Note::new(hit, duration, frequency_from_midi(midi), self.volume)?);
+                }
+                step += 1;
+                hit += self.rhythm;
+            }
+            chord_beat = end;
+        }
+        Ok(notes)
+    }
+
+    /// Makes a part playing the accompaniment from `beat`
+    pub fn part(&self, name: String, progression: &[(Chord, f32)], beat: f32) -> Result<Part, &'static str> {
+        let mut part = Part::new(name);
+        part.add_notes(self.notes(progression, beat)?)?;
+        Ok(part)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::midi_from_frequency;
+
+    fn symbols(progression: &[(Chord, f32)]) -> Vec<(String, f32)> {
+        progression.iter().map(|(chord, beats)| (chord.to_string(), *beats)).collect()
+    }
+
+    #[test]
+    fn reads_numerals_symbols_holds_and_bar_lines() {
+        let progression = parse_progression("I vi7 | bVII - | G7/B vii° Fmaj7", Key::parse("C").unwrap(), 2.0).unwrap();
+        let expected = [("C", 2.0), ("Am7", 2.0), ("A#", 4.0), ("G7/B", 2.0), ("Bdim", 2.0), ("Fmaj7", 2.0)];
+        assert_eq!(symbols(&progression), expected.map(|(symbol, beats)| (symbol.to_string(), beats)));
+    }
+
+    #[test]
+    fn reads_numerals_on_the_natural_minor_scale() {
+        let progression = parse_progression("i iv v VI VII #vii°", Key::parse("Am").unwrap(), 1.0).unwrap();
+        let roots: Vec<u8> = progression.iter().map(|(chord, _)| chord.root).collect();
+        assert_eq!(roots, [9, 2, 4, 5, 7, 8]);
+    }
+
+    #[test]
+    fn refuses_bad_progressions() {
+        let key = Key::default();
+        assert!(parse_progression("- I", key, 1.0).is_err());
+        assert!(parse_progression("| |", key, 1.0).is_err());
+        assert!(parse_progression("I Vx", key, 1.0).is_err());
+        assert!(parse_progression("I", key, 0.0).is_err());
+    }
+
+    #[test]
+    fn voice_leading_moves_the_voices_the_least() {
+        let progression = parse_progression("I vi IV V", Key::default(), 1.0).unwrap();
+        let chords: Vec<Chord> = progression.iter().map(|(chord, _)| *chord).collect();
+        assert_eq!(voice_lead(&chords, 4).unwrap(), [[60, 64, 67], [60, 64, 69], [60, 65, 69], [59, 62, 67]]);
+    }
+
+    #[test]
+    fn styles_play_the_chord_notes_in_their_order() {
+        let progression = parse_progression("I", Key::default(), 2.0).unwrap();
+        let played = |style: AccompanimentStyle| {
+            let accompaniment = Accompaniment { style, rhythm: 0.5, octave: 4, volume: 0.5 };
+            let notes = accompaniment.notes(&progression, 1.0).unwrap();
+            notes.iter().map(|note| (note.beat, midi_from_frequency(note.frequency))).collect::<Vec<_>>()
+        };
+        assert_eq!(played(AccompanimentStyle::Arpeggio), [(1.0, 60), (1.5, 64), (2.0, 67), (2.5, 60)]);
+        assert_eq!(played(AccompanimentStyle::Alberti), [(1.0, 60), (1.5, 67), (2.0, 64), (2.5, 67)]);
+        let block = played(AccompanimentStyle::Block);
+        assert_eq!(block.len(), 12);
+        assert_eq!(block[..3], [(1.0, 60), (1.0, 64), (1.0, 67)]);
+    }
+}
@@ -0,0 +1,35 @@
+/// Hashes a number into a value in range [-1, 1]. The same input always gives the same value
+pub fn hash_to_signed_unit(value: u32) -> f32 {
+    let mut x = value.wrapping_mul(0x9E37_79B9) ^ 0x85EB_CA6B;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846C_A68B);
+    x ^= x >> 16;
+    x as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+/// A small seeded random number generator. The same seed always gives the same numbers in the same order
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        // splitmix64, which gives well mixed numbers even for seeds like 0 and 1
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut x = self.state;
+        x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        (x ^ (x >> 31)) as u32
+    }
+
+    /// Gets a value in range [-1, 1]
+    pub fn next_signed_unit(&mut self) -> f32 {
+        self.next_u32() as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use super::note::Note;
+use super::pitch::midi_from_frequency;
+use super::signature::Key;
+
+/// How close two beats have to be to count as the same
+const EPSILON: f32 = 0.001;
+/// Middle C, parts that mostly play below it are written in bass clef
+const BASS_CLEF_BELOW: f32 = 60.0;
+/// Notes are moved onto a grid of this many steps per beat before they are written, the shortest length
+/// that can still be written as a 64th note triplet
+pub(crate) const GRID: f32 = 48.0;
+
+/// A pitch sounding during a [Segment]
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct SegmentPitch {
+    pub midi: u8,
+    // the pitch keeps sounding into the next segment so it is written tied to it
+    pub tied: bool,
+}
+
+/// A stretch of a bar where the same pitches sound. A segment without pitches is a rest
+#[derive(Clone, PartialEq)]
+pub(crate) struct Segment {
+    pub beat: f32,
+    pub duration: f32,
+    // sorted from low to high
+    pub pitches: Vec<SegmentPitch>,
+}
+
+/// Cuts the notes into bars of `bar_beats` beats and every bar into segments at each bar line, note start and note end.
+/// Gaps become rests and notes that sound across a cut are tied into the next segment
+pub(crate) fn bars(notes: &[Note], bar_beats: f32, num_bars: usize) -> Vec<Vec<Segment>> {
+    let end = bar_beats * num_bars as f32;
+    let notes: Vec<(f32, f32, u8)> = notes
+        .iter()
+        .map(|note| (snap(note.beat), snap(note.end_beat()), midi_from_frequency(note.frequency)))
+        .filter(|(start, stop, _)| stop > start)
+        .collect();
+    let mut cuts: Vec<f32> = (0..=num_bars).map(|bar| bar as f32 * bar_beats).collect();
+    for (start, stop, _) in &notes {
+        cuts.push(*start);
+        cuts.push(*stop);
+    }
+    cuts.retain(|cut| *cut >= 0.0 && *cut <= end + EPSILON);
+    cuts.sort_by(|a, b| a.total_cmp(b));
+    cuts.dedup_by(|a, b| (*a - *b).abs() < EPSILON);
+    let mut bars = vec![Vec::new(); num_bars];
+    for cut in cuts.windows(2) {
+        let (start, stop) = (cut[0], cut[1]);
+        let bar = ((start + EPSILON) / bar_beats).floor() as usize;
+        if bar >= num_bars {
+            break;
+        }
+        let mut pitches: Vec<SegmentPitch> = Vec::new();
+        for (_, note_stop, midi) in notes.iter().filter(|(note_start, note_stop, _)| *note_start <= start + EPSILON && *note_stop > start + EPSILON) {
+            let tied = *note_stop > stop + EPSILON;
+            match pitches.iter_mut().find(|pitch| pitch.midi == *midi) {
+                Some(pitch) => pitch.tied |= tied,
+                None => pitches.push(SegmentPitch { midi: *midi, tied }),
+            }
+        }
+        pitches.sort_by_key(|pitch| pitch.midi);
+        bars[bar].push(Segment { beat: start, duration: stop - start, pitches });
+    }
+    bars
+}
+
+fn snap(beat: f32) -> f32 {
+    (beat * GRID).round() / GRID
+}
+
+/// Number of bars needed to hold `beats` beats, at least one
+pub(crate) fn num_bars(beats: f32, bar_beats: f32) -> usize {
+    ((beats - EPSILON) / bar_beats).ceil().max(1.0) as usize
+}
+
+/// Whether notes sit low enough to be written in bass clef instead of treble clef
+pub(crate) fn uses_bass_clef(notes: &[Note]) -> bool {
+    if notes.is_empty() {
+        return false;
+    }
+    let average = notes.iter().map(|note| midi_from_frequency(note.frequency) as f32).sum::<f32>() / notes.len() as f32;
+    average < BASS_CLEF_BELOW
+}
+
+/// Gets the note volume of a dynamic marking like `mf` or `ff`
+pub(crate) fn volume_from_dynamic(dynamic: &str) -> Option<f32> {
+    match dynamic {
+        "ppp" => Some(0.1),
+        "pp" => Some(0.2),
+        "p" => Some(0.35),
+        "mp" => Some(0.45),
+        "mf" => Some(0.55),
+        "f" => Some(0.7),
+        "ff" => Some(0.85),
+        "fff" => Some(1.0),
+        _ => None,
+    }
+}
+
+/// A written note length like a dotted quarter note or one note of an eighth note triplet
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct NoteValue {
+    // 1 for a whole note, 4 for a quarter note and so on
+    pub denominator: u32,
+    pub dots: u8,
+    pub triplet: bool,
+}
+
+impl NoteValue {
+    pub fn beats(&self) -> f32 {
+        let dotted = match self.dots {
+            0 => 1.0,
+            1 => 1.5,
+            _ => 1.75,
+        };
+        let triplet = if self.triplet { 2.0 / 3.0 } else { 1.0 };
+        4.0 / self.denominator as f32 * dotted * triplet
+    }
+}
+
+/// Splits a length in beats into note values to be tied together. Lengths that fit a single value get it and
+/// other lengths are split into the longest values that fit, preferring plain and dotted values over triplets
+pub(crate) fn note_values(beats: f32) -> Vec<NoteValue> {
+    let mut values = Vec::new();
+    for denominator in [1, 2, 4, 8, 16, 32, 64] {
+        for dots in 0..=2 {
+            values.push(NoteValue { denominator, dots, triplet: false });
+        }
+        values.push(NoteValue { denominator, dots: 0, triplet: true });
+    }
+    if let Some(value) = values.iter().find(|value| (value.beats() - beats).abs() < EPSILON) {
+        return vec![*value];
+    }
+    values.sort_by(|a, b| b.beats().total_cmp(&a.beats()).then(a.triplet.cmp(&b.triplet)));
+    let mut remaining = beats;
+    let mut split = Vec::new();
+    while remaining > EPSILON {
+        let plain = values.iter().find(|value| !value.triplet && value.beats() <= remaining + EPSILON);
+        // triplets only fill what plain values can't
+        let value = match plain {
+            Some(value) => Some(value),
+            None => values.iter().find(|value| value.beats() <= remaining + EPSILON),
+        };
+        match value {
+            Some(value) => {
+                split.push(*value);
+                remaining -= value.beats();
+            }
+            // too short to write
+            None => break,
+        }
+    }
+    split
+}
+
+/// Accidentals written so far in a bar, to only write the ones the key signature and earlier notes don't already give
+#[derive(Default)]
+pub(crate) struct Accidentals {
+    altered: HashMap<(char, i32), i8>,
+}
+
+impl Accidentals {
+    /// Gets the alteration to write in front of a spelled pitch, if it needs one
+    pub fn write(&mut self, key: &Key, letter: char, alter: i8, octave: i32) -> Option<i8> {
+        let current = self.altered.get(&(letter, octave)).copied().unwrap_or(key.alter_of(letter));
+        if alter == current {
+            return None;
+        }
+        self.altered.insert((letter, octave), alter);
+        Some(alter)
+    }
+
+    /// Forgets the accidentals at a bar line
+    pub fn clear(&mut self) {
+        self.altered.clear();
+    }
+}
@@ -0,0 +1,208 @@
+use std::rc::Rc;
+
+use super::note::Note;
+use super::part::Part;
+use super::pitch::{midi_from_frequency, midi_from_name};
+
+/// A condition a note has to meet to be selected
+pub type NoteFilter = Rc<dyn Fn(&Note) -> bool>;
+
+/// Picks notes of a [Part] by their index, start beat, pitch, volume and length or by any other
+/// condition. A note is selected when every condition given holds, a selection without conditions picks every note
+#[derive(Clone, Default)]
+pub struct Selection {
+    // zero based inclusive ranges of note indices, notes in any of them are selected
+    pub indices: Vec<(usize, usize)>,
+    // notes starting at or after the first beat and before the second
+    pub beats: Option<(f32, f32)>,
+    // notes with a MIDI note number in the inclusive range
+    pub pitches: Option<(u8, u8)>,
+    // notes with a volume in the inclusive range
+    pub volumes: Option<(f32, f32)>,
+    // notes lasting a number of beats in the inclusive range
+    pub lengths: Option<(f32, f32)>,
+    // any other conditions notes have to meet
+    pub filters: Vec<NoteFilter>,
+}
+
+impl Selection {
+    /// A selection picking every note
+    pub fn all() -> Self {
+        Selection::default()
+    }
+
+    /// A selection picking the notes that start at or after `start` and before `end`
+    pub fn beats(start: f32, end: f32) -> Result<Self, &'static str> {
+        if start < 0.0 || end <= start {
+            return Err("Beat range must start at or after beat 0 and end after it starts");
+        }
+        Ok(Selection { beats: Some((start, end)), ..Selection::default() })
+    }
+
+    /// Adds a condition notes have to meet to be selected
+    pub fn with_filter(mut self, filter: impl Fn(&Note) -> bool + 'static) -> Self {
+        self.filters.push(Rc::new(filter));
+        self
+    }
+
+    /// Whether the note at `index` of its part is selected
+    pub fn selects(&self, index: usize, note: &Note) -> bool {
+        let in_range = |range: Option<(f32, f32)>, value: f32| range.is_none_or(|(low, high)| value >= low && value <= high);
+        (self.indices.is_empty() || self.indices.iter().any(|(first, last)| index >= *first && index <= *last))
+            && self.beats.is_none_or(|(start, end)| note.beat >= start && note.beat < end)
+            && self.pitches.is_none_or(|(low, high)| (low..=high).contains(&midi_from_frequency(note.frequency)))
+            && in_range(self.volumes, note.volume)
+            && in_range(self.lengths, note.duration)
+            && self.filters.iter().all(|filter| filter(note))
+    }
+
+    /// Indices of the selected notes
+    pub fn indices_in(&self, notes: &[Note]) -> Vec<usize> {
+        notes.iter().enumerate().filter(|(index, note)| self.selects(*index, note)).map(|(index, _)| index).collect()
+    }
+
+    /// Reads a selection written as conditions separated by spaces, like `3-10,12 beats 4-8 pitch C4-G4`.
+    /// Note numbers start at 1 and can be single numbers or ranges separated by commas. `beats 4-8` picks notes
+    /// starting from beat 4 up to before beat 8, `pitch`, `volume` and `length` take inclusive ranges or a single value.
+    /// `all` or nothing picks every note
+    pub fn parse(text: &str) -> Result<Self, &'static str> {
+        let mut selection = Selection::default();
+        let mut words = text.split_whitespace();
+        while let Some(word) = words.next() {
+            let mut value = || words.next().ok_or("Selection condition is missing its range!");
+            match word.to_lowercase().as_str() {
+                "all" => {}
+                "beats" | "beat" => {
+                    let (start, end) = parse_range(value()?, |beat| beat.parse::<f32>().ok())?;
+                    selection.beats = Selection::beats(start, end)?.beats;
+                }
+                "pitch" | "pitches" => selection.pitches = Some(parse_range(value()?, midi_from_name)?),
+                "volume" | "volumes" => selection.volumes = Some(parse_range(value()?, |volume| volume.parse::<f32>().ok())?),
+                "length" | "lengths" => selection.lengths = Some(parse_range(value()?, |length| length.parse::<f32>().ok())?),
+                _ => {
+                    for indices in word.split(',').filter(|indices| !indices.is_empty()) {
+                        let (first, last) = parse_range(indices, |number| number.parse::<usize>().ok().filter(|number| *number >= 1))?;
+                        selection.indices.push((first - 1, last - 1));
+                    }
+                }
+            }
+        }
+        Ok(selection)
+    }
+}
+
+/// Reads `low-high` or a single value meaning both, putting the lower value first
+fn parse_range<T: PartialOrd + Copy>(text: &str, parse: impl Fn(&str) -> Option<T>) -> Result<(T, T), &'static str> {
+    let err = "Could not read a selection range, write it like 3-10!";
+    // pitch names and numbers don't start with a dash so the first dash after the start separates the range
+    let (low, high) = match text.get(1..).and_then(|rest| rest.find('-')) {
+        Some(dash) => {
+            let (low, high) = text.split_at(dash + 1);
+            (parse(low).ok_or(err)?, parse(&high[1..]).ok_or(err)?)
+        }
+        None => {
+            let value = parse(text).ok_or(err)?;
+            (value, value)
+        }
+    };
+    Ok(if high < low { (high, low) } else { (low, high) })
+}
+
+impl Part {
+    /// Deletes the selected notes, returning how many were deleted
+    pub fn delete_notes(&mut self, selection: &Selection) -> usize {
+        let selected = selection.indices_in(&self.notes);
+        let mut index = 0;
+        self.notes.retain(|_| {
+            index += 1;
+            !selected.contains(&(index - 1))
+        });
+        selected.len()
+    }
+
+    /// Adds copies of the selected notes `offset` beats later, or earlier when negative, returning how many were copied.
+    /// Copies may sound together with other notes like chord notes, nothing is copied if a copy would start before
+    /// beat 0 or overlap a note of the same pitch
+    pub fn copy_notes(&mut self, selection: &Selection, offset: f32) -> Result<usize, &'static str> {
+        let selected = selection.indices_in(&self.notes);
+        if selected.is_empty() {
+            return Err("No notes are selected!");
+        }
+        let first_copy = self.notes.len();
+        for index in &selected {
+            let mut copy = self.notes[*index].clone();
+            copy.beat += offset;
+            self.notes.push(copy);
+        }
+        let copies: Vec<usize> = (first_copy..self.notes.len()).collect();
+        if let Err(err) = self.check_notes(&copies) {
+            self.notes.truncate(first_copy);
+            return Err(err);
+        }
+        Ok(selected.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::{frequency_from_name, parse_melody};
+
+    fn melody() -> Part {
+        let mut part = Part::new("test".to_string());
+        // C4 D4 E4 F4 G4 A4 on beats 0 to 5, the E4 played softly and the A4 held for 2 beats
+        part.notes = parse_melody("C4/4 D4 E4 F4 G4 A4/2", 0.0, 0.8).unwrap();
+        part.notes[2].volume = 0.2;
+        part
+    }
+
+    #[test]
+    fn reads_indices_and_conditions() {
+        let selection = Selection::parse("1-3,5 beats 1-8 pitch E4-C4 volume 0.5-1").unwrap();
+        assert_eq!(selection.indices, [(0, 2), (4, 4)]);
+        assert_eq!(selection.beats, Some((1.0, 8.0)));
+        // ranges written backwards are turned around
+        assert_eq!(selection.pitches, Some((60, 64)));
+        assert_eq!(selection.volumes, Some((0.5, 1.0)));
+        assert_eq!(selection.indices_in(&melody().notes), [1]);
+        assert_eq!(Selection::parse("pitch Bb3-C#4").unwrap().pitches, Some((58, 61)));
+    }
+
+    #[test]
+    fn empty_selections_pick_every_note() {
+        let notes = melody().notes;
+        assert_eq!(Selection::parse("").unwrap().indices_in(&notes).len(), notes.len());
+        assert_eq!(Selection::parse("all").unwrap().indices_in(&notes).len(), notes.len());
+        assert_eq!(Selection::parse("length 2").unwrap().indices_in(&notes), [5]);
+    }
+
+    #[test]
+    fn bad_selections_are_refused() {
+        assert!(Selection::parse("0").is_err());
+        assert!(Selection::parse("beats").is_err());
+        assert!(Selection::parse("beats 4-4").is_err());
+        assert!(Selection::parse("pitch H4").is_err());
+        assert!(Selection::parse("3-x").is_err());
+    }
+
+    #[test]
+    fn filters_narrow_the_selection() {
+        let selection = Selection::beats(0.0, 4.0).unwrap().with_filter(|note| note.volume > 0.5);
+        assert_eq!(selection.indices_in(&melody().notes), [0, 1, 3]);
+    }
+
+    #[test]
+    fn deletes_and_copies_the_selected_notes() {
+        let mut part = melody();
+        assert_eq!(part.delete_notes(&Selection::parse("volume 0-0.5").unwrap()), 1);
+        assert_eq!(part.notes.len(), 5);
+        assert_eq!(part.copy_notes(&Selection::parse("1-2").unwrap(), 8.0).unwrap(), 2);
+        assert_eq!((part.notes[5].beat, part.notes[6].beat), (8.0, 9.0));
+        // copies can make chords but not overlap a note of the same pitch
+        part.copy_notes(&Selection::parse("1").unwrap(), 1.0).unwrap();
+        assert!(part.copy_notes(&Selection::parse("1").unwrap(), 0.5).is_err());
+        assert!(part.copy_notes(&Selection::parse("1").unwrap(), -1.0).is_err());
+        assert_eq!(part.notes.len(), 8);
+        assert_eq!(part.notes[7].frequency, frequency_from_name("C4").unwrap());
+    }
+}
@@ -1,8 +1,84 @@
-
 ///
 pub trait Serializable {
     fn serialize(&self) -> Result<Vec<u8>, &'static str>;
     fn deserialize(serialized_data: &[u8]) -> Result<Self, &'static str>
-    where 
+    where
         Self: Sized;
-}
\ No newline at end of file
+}
+
+/// Walks through serialized data front to back, failing with the provided message when data runs out
+pub(crate) struct ByteReader<'a> {
+    data: &'a [u8]
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        ByteReader { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn read_bytes(&mut self, len: usize, err: &'static str) -> Result<&'a [u8], &'static str> {
+        if self.data.len() < len {
+            return Err(err);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    /// Reads everything that is left
+    pub fn read_rest(&mut self) -> &'a [u8] {
+        let rest = self.data;
+        self.data = &[];
+        rest
+    }
+
+    pub fn read_u8(&mut self, err: &'static str) -> Result<u8, &'static str> {
+        Ok(self.read_bytes(1, err)?[0])
+    }
+
+    pub fn read_u16(&mut self, err: &'static str) -> Result<u16, &'static str> {
+        Ok(u16::from_le_bytes(self.read_bytes(2, err)?.try_into().unwrap()))
+    }
+
+    pub fn read_f32(&mut self, err: &'static str) -> Result<f32, &'static str> {
+        Ok(f32::from_le_bytes(self.read_bytes(4, err)?.try_into().unwrap()))
+    }
+
+    /// Reads a u16 length followed by that many bytes of utf8
+    pub fn read_string(&mut self, err: &'static str) -> Result<String, &'static str> {
+        let len = self.read_u16(err)? as usize;
+        Ok(String::from_utf8_lossy(self.read_bytes(len, err)?).into_owned())
+    }
+
+    /// Reads a u16 size followed by a serialized item of that size
+    pub fn read_sized<T: Serializable>(&mut self, err: &'static str) -> Result<T, &'static str> {
+        let len = self.read_u16(err)? as usize;
+        T::deserialize(self.read_bytes(len, err)?)
+    }
+}
+
+/// Writes a u16 length followed by the utf8 bytes of the string
+pub(crate) fn write_string(serialized_data: &mut Vec<u8>, string: &str, err: &'static str) -> Result<(), &'static str> {
+    let bytes = string.as_bytes();
+    if bytes.len() > u16::MAX as usize {
+        return Err(err);
+    }
+    serialized_data.extend((bytes.len() as u16).to_le_bytes());
+    serialized_data.extend(bytes);
+    Ok(())
+}
+
+/// Writes a u16 size followed by the serialized item
+pub(crate) fn write_sized<T: Serializable>(serialized_data: &mut Vec<u8>, item: &T, err: &'static str) -> Result<(), &'static str> {
+    let bytes = item.serialize()?;
+    if bytes.len() > u16::MAX as usize {
+        return Err(err);
+    }
+    serialized_data.extend((bytes.len() as u16).to_le_bytes());
+    serialized_data.extend(bytes);
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use std::fmt::Display;
+
+use super::pitch::read_pitch_letter;
+
+const LETTERS: [char; 7] = ['C', 'D', 'E', 'F', 'G', 'A', 'B'];
+/// Pitch class of each natural letter in `LETTERS`
+const LETTER_SEMITONES: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+/// The scale degree, counted from 0, each semitone above the tonic is spelled as. Notes outside the scale
+/// take the degree they are usually read as, like the raised fourth and flat seventh in major
+const MAJOR_DEGREES: [i32; 12] = [0, 0, 1, 2, 2, 3, 3, 4, 5, 5, 6, 6];
+/// Like `MAJOR_DEGREES` but for minor keys, where the sixth and seventh are raised in the melodic and harmonic minor
+const MINOR_DEGREES: [i32; 12] = [0, 1, 1, 2, 2, 3, 3, 4, 5, 5, 6, 6];
+const MAJOR_SCALE: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+const MINOR_SCALE: [u8; 7] = [0, 2, 3, 5, 7, 8, 10];
+/// Order sharps are added to a key signature, flats are added in the reverse order
+const SHARP_ORDER: [char; 7] = ['F', 'C', 'G', 'D', 'A', 'E', 'B'];
+
+/// How many beats a bar holds and which note value counts as one beat, like 3/4 or 6/8
+#[derive(Clone, Copy, PartialEq)]
+pub struct TimeSignature {
+    pub beats: u8,
+    pub beat_unit: u8,
+}
+
+impl TimeSignature {
+    pub fn new(beats: u8, beat_unit: u8) -> Result<Self, &'static str> {
+        if beats == 0 || !beat_unit.is_power_of_two() || beat_unit > 64 {
+            return Err("Time signature needs at least 1 beat and a beat unit like 2, 4 or 8");
+        }
+        Ok(TimeSignature { beats, beat_unit })
+    }
+
+    /// Length of a bar in the quarter note beats notes are placed on
+    pub fn bar_beats(&self) -> f32 {
+        self.beats as f32 * 4.0 / self.beat_unit as f32
+    }
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        TimeSignature { beats: 4, beat_unit: 4 }
+    }
+}
+
+impl Display for TimeSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.beats, self.beat_unit)
+    }
+}
+
+/// The key a song is written in, used to spell pitches and write key signatures
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct Key {
+    // pitch class of the tonic where 0 is C
+    pub tonic: u8,
+    pub minor: bool,
+}
+
+impl Key {
+    pub fn new(tonic: u8, minor: bool) -> Self {
+        Key { tonic: tonic % 12, minor }
+    }
+
+    /// Reads a key like `G`, `F#m`, `Bb minor` or `Ebmaj`
+    pub fn parse(text: &str) -> Option<Self> {
+        let (tonic, rest) = read_pitch_letter(text.trim())?;
+        let minor = match rest.trim().to_lowercase().as_str() {
+            "" | "maj" | "major" => false,
+            "m" | "min" | "minor" => true,
+            _ => return None,
+        };
+        Some(Key::new(tonic.rem_euclid(12) as u8, minor))
+    }
+
+    /// Gets the major or minor key with `fifths` sharps, or flats when negative
+    pub fn from_fifths(fifths: i8, minor: bool) -> Self {
+        let major_tonic = (fifths as i32 * 7).rem_euclid(12);
+        let tonic = if minor { major_tonic + 9 } else { major_tonic };
+        Key::new(tonic as u8, minor)
+    }
+
+    /// Number of sharps in the key signature, negative for flats
+    pub fn fifths(&self) -> i8 {
+        let major_tonic = if self.minor { (self.tonic as i32 + 3) % 12 } else { self.tonic as i32 };
+        // walking up by fifths reaches every pitch class, keys past 6 sharps are written with flats
+        let fifths = (0..12).find(|fifths| (fifths * 7) % 12 == major_tonic).unwrap_or(0);
+        if fifths > 6 { fifths as i8 - 12 } else { fifths as i8 }
+    }
+
+    /// How the key signature alters a letter, 1 for sharp and -1 for flat
+    pub fn alter_of(&self, letter: char) -> i8 {
+        signature_alter(self.fifths(), letter)
+    }
+
+    /// Spells a MIDI note number as a letter, an alteration and an octave. The letter is the scale degree
+    /// the note belongs to counted from the letter of the tonic, so the leading tone of D minor is C# and
+    /// the seventh of F# major is E#
+    pub fn spell(&self, midi: u8) -> (char, i8, i32) {
+        let semitones_above_tonic = (midi as i32 - self.tonic as i32).rem_euclid(12) as usize;
+        let degrees = if self.minor { MINOR_DEGREES } else { MAJOR_DEGREES };
+        let mut degree = degrees[semitones_above_tonic];
+        let mut alter = self.alter_from_degree(midi, degree);
+        // a double sharp or flat is read more easily as the natural or single accidental of the next letter
+        if alter.abs() > 1 {
+            degree += alter.signum();
+            alter = self.alter_from_degree(midi, degree);
+        }
+        let letter = (self.tonic_letter() + degree).rem_euclid(7) as usize;
+        (LETTERS[letter], alter as i8, (midi as i32 - alter) / 12 - 1)
+    }
+
+    /// Index into `LETTERS` of the letter the tonic is spelled with
+    fn tonic_letter(&self) -> i32 {
+        // every fifth up moves the letter of the major tonic up by 4, and the relative minor is 2 letters below it
+        let major_letter = (self.fifths() as i32 * 4).rem_euclid(7);
+        if self.minor { (major_letter - 2).rem_euclid(7) } else { major_letter }
+    }
+
+    /// How many semitones `midi` is above the natural letter `degree` steps above the tonic, in range [-6, 5]
+    fn alter_from_degree(&self, midi: u8, degree: i32) -> i32 {
+        let letter = (self.tonic_letter() + degree).rem_euclid(7) as usize;
+        let difference = (midi as i32 - LETTER_SEMITONES[letter]).rem_euclid(12);
+        if difference > 5 { difference - 12 } else { difference }
+    }
+
+    /// Semitones from the tonic of each note of the key's major or natural minor scale
+    pub fn scale(&self) -> [u8; 7] {
+        if self.minor { MINOR_SCALE } else { MAJOR_SCALE }
+    }
+
+    /// Name of the tonic, spelled to match the key signature
+    pub fn tonic_name(&self) -> String {
+        let (letter, alter, _) = self.spell(self.tonic);
+        match alter {
+            1 => format!("{letter}#"),
+            -1 => format!("{letter}b"),
+            _ => letter.to_string(),
+        }
+    }
+}
+
+/// How a key signature with `fifths` sharps, or flats when negative, alters a letter
+pub(crate) fn signature_alter(fifths: i8, letter: char) -> i8 {
+    let position = SHARP_ORDER.iter().position(|sharp| *sharp == letter.to_ascii_uppercase()).unwrap_or(0) as i8;
+    if fifths > 0 && position < fifths {
+        1
+    } else if fifths < 0 && 6 - position < -fifths {
+        -1
+    } else {
+        0
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.tonic_name(), if self.minor { "minor" } else { "major" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(key: Key, midi: u8) -> String {
+        let (letter, alter, octave) = key.spell(midi);
+        let accidental = match alter {
+            1 => "#",
+            -1 => "b",
+            _ => "",
+        };
+        format!("{letter}{accidental}{octave}")
+    }
+
+    #[test]
+    fn notes_of_the_scale_use_the_key_signature() {
+        let e_flat = Key::parse("Eb").unwrap();
+        let names: Vec<String> = [63, 65, 67, 68, 70, 72, 74].iter().map(|midi| name(e_flat, *midi)).collect();
+        assert_eq!(names, ["Eb4", "F4", "G4", "Ab4", "Bb4", "C5", "D5"]);
+        assert_eq!(Key::parse("B").unwrap().tonic_name(), "B");
+        assert_eq!(Key::parse("Bbm").unwrap().tonic_name(), "Bb");
+    }
+
+    #[test]
+    fn harmonic_minor_leading_tones_are_sharpened_sevenths() {
+        assert_eq!(name(Key::parse("Dm").unwrap(), 61), "C#4");
+        assert_eq!(name(Key::parse("Gm").unwrap(), 66), "F#4");
+        assert_eq!(name(Key::parse("Cm").unwrap(), 71), "B4");
+        // the leading tone of C# minor is in the octave below the C it sounds as
+        assert_eq!(name(Key::parse("C#m").unwrap(), 60), "B#3");
+        assert_eq!(name(Key::parse("Dm").unwrap(), 70), "Bb4");
+    }
+
+    #[test]
+    fn sharp_keys_keep_their_letters() {
+        let f_sharp = Key::parse("F#").unwrap();
+        assert_eq!(name(f_sharp, 65), "E#4");
+        assert_eq!(name(f_sharp, 66), "F#4");
+        // a note between F# and G# is a G rather than an F double sharp
+        assert_eq!(name(f_sharp, 67), "G4");
+    }
+
+    #[test]
+    fn notes_outside_the_scale_are_raised_or_lowered_degrees() {
+        let c = Key::default();
+        let names: Vec<String> = [61, 63, 66, 68, 70].iter().map(|midi| name(c, *midi)).collect();
+        assert_eq!(names, ["C#4", "Eb4", "F#4", "Ab4", "Bb4"]);
+    }
+
+    #[test]
+    fn keys_read_back_from_their_signature() {
+        // Gb is the same key as F#, which is written with sharps
+        for fifths in -5..=6 {
+            for minor in [false, true] {
+                assert_eq!(Key::from_fifths(fifths, minor).fifths(), fifths);
+            }
+        }
+        assert!(Key::parse("H").is_none());
+        assert!(TimeSignature::new(6, 8).unwrap().bar_beats() == 3.0);
+        assert!(TimeSignature::new(3, 5).is_err());
+    }
+}
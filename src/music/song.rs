@@ -1,15 +1,40 @@
 use std::fs::File;
 use std::io::Write;
-use crate::wav::{WavOptions, WavHeader};
+use crate::wav::{self, WavOptions, WavHeader};
+use super::abc;
+use super::musicxml;
+use super::lilypond;
+use super::bus::Bus;
+use super::filter;
+use super::instrument::Preset;
 use super::part::Part;
 use super::note::Note;
-use super::serializable::Serializable;
+use super::pattern::Pattern;
+use super::serializable::{write_sized, write_string, ByteReader, Serializable};
+use super::signature::{Key, TimeSignature};
+use super::structure::Marker;
 
 ///
+#[derive(Clone)]
 pub struct Song {
     pub name: String,
     pub bpm: u16,
-    pub parts: Vec<Part>
+    pub parts: Vec<Part>,
+    // buses parts can send to, mixed into the master bus
+    pub aux_buses: Vec<Bus>,
+    // everything is mixed through the master bus before being written
+    pub master_bus: Bus,
+    // instrument presets saved with the song
+    pub presets: Vec<Preset>,
+    // used to write bars and spell pitches when the song is exported as notation
+    pub time_signature: TimeSignature,
+    pub key: Key,
+    // note sequences the parts place at beats instead of repeating their notes
+    pub patterns: Vec<Pattern>,
+    // named points starting the sections of the song, in order of their beats
+    pub markers: Vec<Marker>,
+    // indices of the markers whose sections play, in order. Empty plays the song as it is written
+    pub arrangement: Vec<usize>
 }
 
 impl Song {
@@ -17,13 +42,21 @@ impl Song {
         Self {
             name,
             bpm, 
-            parts: Vec::new()
+            parts: Vec::new(),
+            aux_buses: Vec::new(),
+            master_bus: Bus::new("Master".to_string()),
+            presets: Vec::new(),
+            time_signature: TimeSignature::default(),
+            key: Key::default(),
+            patterns: Vec::new(),
+            markers: Vec::new(),
+            arrangement: Vec::new()
         }
     }
 
     pub fn duration(&self)-> f32 {
         let mut longest_part = 0.0;
-        for part in &self.parts {
+        for part in &self.arranged_parts() {
             let part_duration = part.duration();
             if part_duration > longest_part {
                 longest_part = part_duration
@@ -32,56 +65,90 @@ impl Song {
         longest_part
     }
 
-    pub fn compile_parts_into_samples(&self, options: &WavOptions) -> Vec<i16> {
-        let num_samples: usize = (beat_in_seconds(self.duration(), self.bpm as f32) * options.sample_rate as f32) as usize;
-        let mut samples = Vec::with_capacity(num_samples);
-        for i in 0..num_samples {
-            let time = i as f32 / options.sample_rate as f32;
-            let mut sample_amplitude: i16 = 0;
-            for part in &self.parts {
-                if let Some(note) = part.has_note_at_beat(second_in_beats(time, self.bpm as f32)) {
-                    sample_amplitude = match sample_amplitude.checked_add(note.get_sample_amplitude(time)) {
-                        Some(sum) => sum,
-                        None => {
-                            if sample_amplitude > 0 {
-                                crate::wav::MAX_AMPLITUDE
-                            }
-                            else {
-                                -crate::wav::MAX_AMPLITUDE
-                            }
-                        }
-                    }
-                };
+    pub fn compile_parts_into_samples(&self, options: &WavOptions) -> Result<Vec<i16>, &'static str> {
+        let mix = if options.oversampling > 1 {
+            // Render above the target rate so anything that would alias lands above nyquist and gets filtered out
+            let oversampled = self.mix(options.sample_rate * options.oversampling)?;
+            filter::decimate(&oversampled, options.oversampling as usize)
+        } else {
+            self.mix(options.sample_rate)?
+        };
+        Ok(mix.into_iter().map(wav::quantize).collect())
+    }
+
+    /// Renders every part with the patterns placed in it, routes them through the aux buses and the master bus and returns the mix in the range [-1, 1]
+    pub fn mix(&self, sample_rate: u32) -> Result<Vec<f32>, &'static str> {
+        let release = self.parts.iter().map(|part| part.instrument.release_seconds()).fold(0.0, f32::max);
+        let tail = release + self.aux_buses.iter().map(|bus| bus.tail_seconds()).fold(0.0, f32::max) + self.master_bus.tail_seconds();
+        let num_samples: usize = ((beat_in_seconds(self.duration(), self.bpm as f32) + tail) * sample_rate as f32) as usize;
+        let mut master = vec![0.0; num_samples];
+        let mut aux: Vec<Vec<f32>> = self.aux_buses.iter().map(|_| vec![0.0; num_samples]).collect();
+        let mut part_samples = vec![0.0; num_samples];
+        for part in &self.arranged_parts() {
+            part_samples.iter_mut().for_each(|sample| *sample = 0.0);
+            part.render(&mut part_samples, self.bpm as f32, sample_rate)?;
+            add_into(&mut master, &part_samples, 1.0);
+            for send in &part.sends {
+                if let Some(bus_samples) = aux.get_mut(send.bus) {
+                    add_into(bus_samples, &part_samples, send.level);
+                }
+            }
+        }
+        for (bus, bus_samples) in self.aux_buses.iter().zip(aux.iter_mut()) {
+            bus.process(bus_samples, sample_rate);
+            add_into(&mut master, bus_samples, 1.0);
+        }
+        self.master_bus.process(&mut master, sample_rate);
+        Ok(master)
+    }
+
+    /// Removes an aux bus along with every send to it
+    pub fn remove_aux_bus(&mut self, index: usize) {
+        self.aux_buses.remove(index);
+        for part in self.parts.iter_mut() {
+            part.sends.retain(|send| send.bus != index);
+            for send in part.sends.iter_mut() {
+                if send.bus > index {
+                    send.bus -= 1;
+                }
             }
-            samples.push(sample_amplitude);
         }
-        samples
     }
 
-    pub fn compile_parts_into_bytes(&self, options: &WavOptions) -> Vec<u8> {
-        let samples = self.compile_parts_into_samples(options);
+    pub fn compile_parts_into_bytes(&self, options: &WavOptions) -> Result<Vec<u8>, &'static str> {
+        let samples = self.compile_parts_into_samples(options)?;
         let mut bytes = Vec::with_capacity(samples.capacity() * 2);
 
         for sample in &samples {
             bytes.extend(&sample.to_le_bytes())
         }
-        bytes
+        Ok(bytes)
+    }
+
+    /// Adds a copy of the part at `index` to the end of the song, named after the original
+    pub fn duplicate_part(&mut self, index: usize) -> Result<(), &'static str> {
+        let mut part = self.parts.get(index).ok_or("No part at that index!")?.clone();
+        part.name.push_str(" copy");
+        self.parts.push(part);
+        Ok(())
     }
 
     pub fn write_to_wav_file(&self, file_name: String, options: &WavOptions) -> Result<(), &'static str> {
         let mut file_name = String::from(file_name);
         file_name.push_str(".wav");
+        // compiled before the file is created so a song that can't be rendered leaves no empty file behind
+        let bytes = self.compile_parts_into_bytes(options)?;
         println!("Writing to file {file_name}!");
         let mut file = File::create(file_name.as_str()).expect("Failed to create file");
 
-        let data_size: u32 = (self.duration() * (options.bits_per_sample as u32 * options.sample_rate * options.num_channels as u32) as f32 / 8.0) as u32;
+        let data_size: u32 = bytes.len() as u32;
 
         let header = WavHeader::new(data_size, &options);
         if let Err(_) = file.write_all(header.as_bytes()) {
             return Err("Failed to write to file!");
         }
 
-        if let Err(_) = file.write_all(&self.compile_parts_into_bytes(options)) {
+        if file.write_all(&bytes).is_err() {
             return Err("Failed to write to file!");
         }
         Ok(())
@@ -103,16 +170,52 @@ impl Song {
             }
         }
     }
+
+    pub fn write_to_abc_file(&self, mut file_name: String) -> Result<(), &'static str> {
+        file_name.push_str(".abc");
+        println!("Writing to file {file_name}!");
+        let abc = abc::song_to_abc(self)?;
+        let mut file = File::create(file_name.as_str()).expect("Failed to create file");
+        if file.write_all(abc.as_bytes()).is_err() {
+            return Err("Failed to write ABC tune to file!");
+        }
+        Ok(())
+    }
+
+    pub fn write_to_musicxml_file(&self, mut file_name: String) -> Result<(), &'static str> {
+        file_name.push_str(".musicxml");
+        println!("Writing to file {file_name}!");
+        let xml = musicxml::song_to_musicxml(self)?;
+        let mut file = File::create(file_name.as_str()).expect("Failed to create file");
+        if file.write_all(xml.as_bytes()).is_err() {
+            return Err("Failed to write MusicXML to file!");
+        }
+        Ok(())
+    }
+
+    pub fn write_to_lilypond_file(&self, mut file_name: String) -> Result<(), &'static str> {
+        file_name.push_str(".ly");
+        println!("Writing to file {file_name}!");
+        let ly = lilypond::song_to_lilypond(self)?;
+        let mut file = File::create(file_name.as_str()).expect("Failed to create file");
+        if file.write_all(ly.as_bytes()).is_err() {
+            return Err("Failed to write LilyPond source to file!");
+        }
+        Ok(())
+    }
 }
 
 impl Default for Song {
     fn default() -> Self {
         let mut base = Part::new("base".to_string());
-        let _ = base.add_note(Note { frequency: 293.99, volume: 0.25, beat: 0.0, duration: 1.0 });
-        _ = base.add_note(Note { frequency: 293.99, volume: 0.25, beat: 1.0, duration: 0.5 });
-        _ =base.add_note(Note { frequency: 150.00, volume: 0.25, beat: 1.5, duration: 1.5 });
+        let _ = base.add_note(Note::new(0.0, 1.0, 293.99, 0.25).unwrap());
+        _ = base.add_note(Note::new(1.0, 0.5, 293.99, 0.25).unwrap());
+        _ =base.add_note(Note::new(1.5, 1.5, 150.00, 0.25).unwrap());
 
-        Song { name: "Demo Song".to_string(), bpm: 60, parts: vec![Part::default(), base]
+        Song { name: "Demo Song".to_string(), bpm: 60, parts: vec![Part::default(), base],
+            aux_buses: Vec::new(), master_bus: Bus::new("Master".to_string()), presets: Vec::new(),
+            time_signature: TimeSignature::default(), key: Key::default(), patterns: Vec::new(),
+            markers: Vec::new(), arrangement: Vec::new()
         }
     }
 }
@@ -125,6 +228,27 @@ impl Serializable for Song {
     /// u16: num parts
     /// (parts) u16: size_of_part
     /// (parts) size_of_part: part
+    /// u16: num_aux_buses
+    /// (aux buses) u16: size_of_bus
+    /// (aux buses) size_of_bus: bus
+    /// u16: size_of_master_bus
+    /// size_of_master_bus: master_bus
+    /// u16: num_presets
+    /// (presets) u16: size_of_preset
+    /// (presets) size_of_preset: preset
+    /// u8: time_signature_beats
+    /// u8: time_signature_beat_unit
+    /// u8: key_tonic
+    /// u8: key_minor
+    /// u16: num_patterns
+    /// (patterns) u16: size_of_pattern
+    /// (patterns) size_of_pattern: pattern
+    /// u16: num_markers
+    /// (markers) u16: name_len
+    /// (markers) name_len: name
+    /// (markers) f32: beat
+    /// u16: arrangement_len
+    /// (arrangement) u16: marker
     fn serialize(&self) -> Result<Vec<u8>, &'static str> {
         let mut serialized_data: Vec<u8> = Vec::new();
         // Serialize the name
@@ -143,16 +267,34 @@ impl Serializable for Song {
         serialized_data.extend(num_parts.to_le_bytes());
         // Serialize each part
         for part in &self.parts {
-            match part.serialize() {
-                Ok(serialized_part) => {
-                    let size_of_part = serialized_part.len() as u16;
-                    serialized_data.extend(size_of_part.to_le_bytes());
-                    serialized_data.extend(serialized_part);
-                }
-                Err(err) => {
-                    return Err(err);
-                }
-            }
+            write_sized(&mut serialized_data, part, "Could not serialize song. Part too long!")?;
+        }
+        // Serialize buses
+        serialized_data.extend((self.aux_buses.len() as u16).to_le_bytes());
+        for bus in &self.aux_buses {
+            write_sized(&mut serialized_data, bus, "Could not serialize song. Bus too large!")?;
+        }
+        write_sized(&mut serialized_data, &self.master_bus, "Could not serialize song. Bus too large!")?;
+        // Serialize presets
+        serialized_data.extend((self.presets.len() as u16).to_le_bytes());
+        for preset in &self.presets {
+            write_sized(&mut serialized_data, preset, "Could not serialize song. Preset too large!")?;
+        }
+        serialized_data.extend([self.time_signature.beats, self.time_signature.beat_unit, self.key.tonic, self.key.minor as u8]);
+        // Serialize patterns
+        serialized_data.extend((self.patterns.len() as u16).to_le_bytes());
+        for pattern in &self.patterns {
+            write_sized(&mut serialized_data, pattern, "Could not serialize song. Pattern too large!")?;
+        }
+        // Serialize markers and arrangement
+        serialized_data.extend((self.markers.len() as u16).to_le_bytes());
+        for marker in &self.markers {
+            write_string(&mut serialized_data, &marker.name, "Could not serialize song. Marker name too long!")?;
+            serialized_data.extend(marker.beat.to_le_bytes());
+        }
+        serialized_data.extend((self.arrangement.len() as u16).to_le_bytes());
+        for section in &self.arrangement {
+            serialized_data.extend((*section as u16).to_le_bytes());
         }
         Ok(serialized_data)
     }
@@ -199,7 +341,63 @@ impl Serializable for Song {
             parts.push(Part::deserialize(part_bytes)?);
             remaining_data = &remaining_data[(2+part_size)..];
         }
-        Ok(Self { name, bpm, parts })
+        // Deserialize buses. Songs saved before buses existed end here
+        let mut reader = ByteReader::new(remaining_data);
+        let mut aux_buses = Vec::new();
+        let mut master_bus = Bus::new("Master".to_string());
+        if !reader.is_empty() {
+            let num_buses = reader.read_u16("Invalid serialized data! Insufficent length for number of buses!")?;
+            for _ in 0..num_buses {
+                aux_buses.push(reader.read_sized("Invalid serialized data! Missing bus data!")?);
+            }
+            master_bus = reader.read_sized("Invalid serialized data! Missing master bus data!")?;
+        }
+        // Deserialize presets. Songs saved before presets existed end here
+        let mut presets = Vec::new();
+        if !reader.is_empty() {
+            let num_presets = reader.read_u16("Invalid serialized data! Insufficent length for number of presets!")?;
+            for _ in 0..num_presets {
+                presets.push(reader.read_sized("Invalid serialized data! Missing preset data!")?);
+            }
+        }
+        // Deserialize time and key signature. Songs saved before signatures existed end here
+        let mut time_signature = TimeSignature::default();
+        let mut key = Key::default();
+        if !reader.is_empty() {
+            let err = "Invalid serialized data! Insufficent length for signatures!";
+            time_signature = TimeSignature::new(reader.read_u8(err)?, reader.read_u8(err)?)?;
+            key = Key::new(reader.read_u8(err)?, reader.read_u8(err)? != 0);
+        }
+        // Deserialize patterns. Songs saved before patterns existed end here
+        let mut patterns = Vec::new();
+        if !reader.is_empty() {
+            let num_patterns = reader.read_u16("Invalid serialized data! Insufficent length for number of patterns!")?;
+            for _ in 0..num_patterns {
+                patterns.push(reader.read_sized("Invalid serialized data! Missing pattern data!")?);
+            }
+        }
+        if parts.iter().any(|part| part.pattern_instances.iter().any(|instance| instance.pattern >= patterns.len())) {
+            return Err("Invalid serialized data! Pattern instance of a missing pattern!");
+        }
+        // Deserialize markers and arrangement. Songs saved before markers existed end here
+        let mut markers = Vec::new();
+        let mut arrangement = Vec::new();
+        if !reader.is_empty() {
+            let err = "Invalid serialized data! Missing marker data!";
+            let num_markers = reader.read_u16(err)?;
+            for _ in 0..num_markers {
+                markers.push(Marker { name: reader.read_string(err)?, beat: reader.read_f32(err)? });
+            }
+            let arrangement_len = reader.read_u16("Invalid serialized data! Missing arrangement data!")?;
+            for _ in 0..arrangement_len {
+                let section = reader.read_u16("Invalid serialized data! Missing arrangement data!")? as usize;
+                if section >= markers.len() {
+                    return Err("Invalid serialized data! Arrangement plays a missing section!");
+                }
+                arrangement.push(section);
+            }
+        }
+        Ok(Self { name, bpm, parts, aux_buses, master_bus, presets, time_signature, key, patterns, markers, arrangement })
     }
 }
 
@@ -207,6 +405,112 @@ fn beat_in_seconds(beat: f32, bpm: f32) -> f32 {
     beat / bpm * 60.0
 }
 
-fn second_in_beats(seconds: f32, bpm: f32) -> f32 {
-    seconds / 60.0 * bpm
-}
\ No newline at end of file
+fn add_into(destination: &mut [f32], source: &[f32], level: f32) {
+    for (destination, source) in destination.iter_mut().zip(source) {
+        *destination += source * level;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::effect::db_to_amplitude;
+    use crate::music::{BusSend, Effect, FmSynth, Instrument};
+
+    fn song_with_bus() -> Song {
+        let mut song = Song::new("Buses".to_string(), 60);
+        let mut part = Part::new("Lead".to_string());
+        part.add_note(Note::new(0.0, 1.0, 440.0, 0.25).unwrap()).unwrap();
+        part.set_send(0, 0.5);
+        song.parts.push(part);
+        let mut bus = Bus::new("Louder".to_string());
+        bus.effects.push(Effect::Gain { gain: 6.0 });
+        song.aux_buses.push(bus);
+        song
+    }
+
+    #[test]
+    fn sends_add_the_processed_part_to_the_mix() {
+        let mut song = song_with_bus();
+        let with_send = song.mix(8000).unwrap();
+        song.parts[0].sends.clear();
+        let dry = song.mix(8000).unwrap();
+        // the dry part plus half of it doubled by the bus
+        let level = 1.0 + 0.5 * db_to_amplitude(6.0);
+        for (wet, dry) in with_send.iter().zip(&dry) {
+            assert!((wet - dry * level).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn master_bus_processes_the_whole_mix() {
+        let mut song = song_with_bus();
+        let before = song.mix(8000).unwrap();
+        song.master_bus.effects.push(Effect::Gain { gain: -6.0 });
+        let after = song.mix(8000).unwrap();
+        for (after, before) in after.iter().zip(&before) {
+            assert!((after - before * db_to_amplitude(-6.0)).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn removing_a_bus_removes_its_sends_and_moves_the_others_down() {
+        let mut song = song_with_bus();
+        song.aux_buses.push(Bus::new("Second".to_string()));
+        song.parts[0].set_send(1, 0.25);
+        song.remove_aux_bus(0);
+        let sends: Vec<(usize, f32)> = song.parts[0].sends.iter().map(|send| (send.bus, send.level)).collect();
+        assert_eq!(sends, [(0, 0.25)]);
+        assert_eq!(song.aux_buses[0].name, "Second");
+    }
+
+    #[test]
+    fn buses_and_sends_are_saved() {
+        let mut song = song_with_bus();
+        song.master_bus.effects.push(Effect::Compressor { threshold: -12.0, ratio: 4.0, attack: 5.0, release: 50.0, makeup_gain: 3.0 });
+        song.master_bus.effects.push(Effect::Equalizer { low_gain: 2.0, mid_gain: 0.0, high_gain: -2.0 });
+        let loaded = Song::deserialize(&song.serialize().unwrap()).unwrap();
+        assert_eq!(loaded.aux_buses.len(), 1);
+        assert_eq!(loaded.aux_buses[0].to_string(), song.aux_buses[0].to_string());
+        assert_eq!(loaded.master_bus.to_string(), song.master_bus.to_string());
+        let sends: Vec<(usize, f32)> = loaded.parts[0].sends.iter().map(|BusSend { bus, level }| (*bus, *level)).collect();
+        assert_eq!(sends, [(0, 0.5)]);
+    }
+
+    #[test]
+    fn instruments_and_presets_are_saved() {
+        let mut song = song_with_bus();
+        let (name, synth) = FmSynth::presets().remove(1);
+        song.parts[0].instrument = Instrument::Fm(synth.clone());
+        song.presets.push(Preset::new(name, Instrument::Fm(synth)));
+        let loaded = Song::deserialize(&song.serialize().unwrap()).unwrap();
+        assert_eq!(loaded.parts[0].instrument.to_string(), song.parts[0].instrument.to_string());
+        assert_eq!(loaded.presets.len(), 1);
+        assert_eq!(loaded.presets[0].name, "Bell");
+        assert_eq!(loaded.presets[0].instrument.to_string(), song.presets[0].instrument.to_string());
+    }
+
+    #[test]
+    fn parts_too_long_to_save_are_refused() {
+        let mut song = Song::new("Long".to_string(), 60);
+        let mut part = Part::new("Long".to_string());
+        // every note takes 16 bytes, more than a part's u16 size can count
+        part.notes = (0..5000).map(|beat| Note::new(beat as f32, 1.0, 440.0, 0.5).unwrap()).collect();
+        song.parts.push(part);
+        assert_eq!(song.serialize().err(), Some("Could not serialize song. Part too long!"));
+    }
+
+    #[test]
+    fn patterns_and_their_instances_are_saved() {
+        let mut song = song_with_bus();
+        let riff = vec![Note::new(0.0, 1.0, 330.0, 0.5).unwrap(), Note::new(1.0, 1.0, 392.0, 0.5).unwrap()];
+        song.patterns.push(Pattern::new("Riff".to_string(), riff).unwrap());
+        song.place_pattern(0, 0, 4.0, -12.0).unwrap();
+        let loaded = Song::deserialize(&song.serialize().unwrap()).unwrap();
+        assert_eq!(loaded.patterns.len(), 1);
+        assert_eq!(loaded.patterns[0].name, "Riff");
+        let instance = loaded.parts[0].pattern_instances[0];
+        assert_eq!((instance.pattern, instance.beat, instance.transpose), (0, 4.0, -12.0));
+        assert_eq!(loaded.duration(), song.duration());
+    }
+}
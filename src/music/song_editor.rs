@@ -1,11 +1,13 @@
-use crate::music::Song;
+use crate::music::{Clipboard, Song};
 
 pub struct SongEditor {
-    pub loaded_songs: Vec<Song>
+    pub loaded_songs: Vec<Song>,
+    // notes or parts copied from one song to paste into another
+    pub clipboard: Clipboard
 }
 
 impl SongEditor {
     pub fn new() -> Self {
-        SongEditor { loaded_songs: vec![Song::default()] }
+        SongEditor { loaded_songs: vec![Song::default()], clipboard: Clipboard::default() }
     }
 }
\ No newline at end of file
@@ -0,0 +1,160 @@
+use std::fmt::Display;
+
+use super::note::Note;
+use super::part::Part;
+use super::pitch::{frequency_from_name, midi_from_frequency, name_from_frequency};
+
+/// Volume of accented hits
+const ACCENT_VOLUME: f32 = 1.0;
+
+/// One step of a [StepPattern]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Step {
+    Rest,
+    // keeps the note of the previous step sounding
+    Hold,
+    // a hit of the pattern's drum or pitch, accented hits play at full volume
+    Hit { accent: bool },
+    Note(f32),
+}
+
+/// A row of equally long steps that is entered or printed in one line.
+/// Drum rows are written as characters like `x..x..x.x...x...` where `x` is a hit, `X` an accented hit,
+/// `.` a rest and `-` holds the previous step. Melodic rows are note names and the same symbols separated by spaces
+/// like `C4 - E4 . G4 - - .`
+#[derive(Clone, PartialEq)]
+pub struct StepPattern {
+    pub steps: Vec<Step>,
+    // length of each step in beats
+    pub step_beats: f32,
+}
+
+impl StepPattern {
+    pub fn parse(text: &str, step_beats: f32) -> Result<Self, &'static str> {
+        if step_beats <= 0.0 {
+            return Err("Steps must be longer than 0 beats");
+        }
+        let text = text.trim();
+        let tokens: Vec<String> = if text.contains(char::is_whitespace) {
+            text.split_whitespace().map(str::to_string).collect()
+        } else {
+            text.chars().filter(|c| *c != '|').map(|c| c.to_string()).collect()
+        };
+        let mut steps = Vec::new();
+        for token in tokens {
+            let step = match token.as_str() {
+                "|" => continue,
+                "." => Step::Rest,
+                "-" => Step::Hold,
+                "x" => Step::Hit { accent: false },
+                "X" => Step::Hit { accent: true },
+                name => match frequency_from_name(name) {
+                    Some(frequency) => Step::Note(frequency),
+                    None => return Err("Unknown step! Use x, X, ., - or a note name like C#4"),
+                },
+            };
+            if step == Step::Hold && matches!(steps.last(), None | Some(Step::Rest)) {
+                return Err("A hold needs a note or hit before it");
+            }
+            steps.push(step);
+        }
+        if steps.is_empty() {
+            return Err("A step pattern needs at least one step");
+        }
+        Ok(StepPattern { steps, step_beats })
+    }
+
+    /// Reads `num_steps` steps starting at `start_beat` from notes. With a `hit_frequency` only notes of that
+    /// frequency are read and they become hits, as is done for one drum of a drum part
+    pub fn from_notes(notes: &[Note], start_beat: f32, step_beats: f32, num_steps: usize, hit_frequency: Option<f32>) -> Self {
+        let matches = |note: &&Note| hit_frequency.is_none_or(|frequency| same_pitch(note.frequency, frequency));
+        let steps = (0..num_steps)
+            .map(|index| {
+                let beat = start_beat + index as f32 * step_beats;
+                let starting = notes.iter().filter(matches).find(|note| note.beat >= beat && note.beat < beat + step_beats);
+                match starting {
+                    Some(note) if hit_frequency.is_some() => Step::Hit { accent: note.volume >= ACCENT_VOLUME },
+                    Some(note) => Step::Note(note.frequency),
+                    None if notes.iter().filter(matches).any(|note| note.plays_at(beat)) => Step::Hold,
+                    None => Step::Rest,
+                }
+            })
+            .collect();
+        StepPattern { steps, step_beats }
+    }
+
+    /// Beats the pattern lasts for
+    pub fn duration(&self) -> f32 {
+        self.steps.len() as f32 * self.step_beats
+    }
+
+    /// Turns the steps into notes starting at `start_beat`. Hits play at `hit_frequency` and every note that is
+    /// not accented plays at `volume`
+    pub fn to_notes(&self, start_beat: f32, hit_frequency: f32, volume: f32) -> Result<Vec<Note>, &'static str> {
+        let mut notes: Vec<Note> = Vec::new();
+        let mut holding = false;
+        for (index, step) in self.steps.iter().enumerate() {
+            let beat = start_beat + index as f32 * self.step_beats;
+            match step {
+                Step::Rest => holding = false,
+                Step::Hold => {
+                    if let Some(note) = notes.last_mut().filter(|_| holding) {
+                        note.duration += self.step_beats;
+                    }
+                }
+                Step::Hit { accent } => {
+                    let volume = if *accent { ACCENT_VOLUME } else { volume };
+                    notes.push(Note::new(beat, self.step_beats, hit_frequency, volume)?);
+                    holding = true;
+                }
+                Step::Note(frequency) => {
+                    notes.push(Note::new(beat, self.step_beats, *frequency, volume)?);
+                    holding = true;
+                }
+            }
+        }
+        Ok(notes)
+    }
+
+    /// Replaces the notes of the part during the pattern with the notes of the pattern. With a `hit_frequency`
+    /// only the notes of that drum or pitch are replaced and the pattern's hits play at it
+    pub fn write_into(&self, part: &mut Part, start_beat: f32, hit_frequency: Option<f32>, volume: f32) -> Result<(), &'static str> {
+        let notes = self.to_notes(start_beat, hit_frequency.unwrap_or(0.0), volume)?;
+        if hit_frequency.is_none() && self.steps.iter().any(|step| matches!(step, Step::Hit { .. })) {
+            return Err("Hits need a drum or pitch to play! Use note names for melodic parts");
+        }
+        let end_beat = start_beat + self.duration();
+        let old_notes = part.notes.clone();
+        part.notes.retain(|note| {
+            let inside = note.beat >= start_beat && note.beat < end_beat;
+            !inside || hit_frequency.is_some_and(|frequency| !same_pitch(note.frequency, frequency))
+        });
+        if let Err(err) = part.add_notes(notes) {
+            part.notes = old_notes;
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+fn same_pitch(a: f32, b: f32) -> bool {
+    midi_from_frequency(a) == midi_from_frequency(b)
+}
+
+impl Display for StepPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let melodic = self.steps.iter().any(|step| matches!(step, Step::Note(_)));
+        let tokens: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| match step {
+                Step::Rest => ".".to_string(),
+                Step::Hold => "-".to_string(),
+                Step::Hit { accent: false } => "x".to_string(),
+                Step::Hit { accent: true } => "X".to_string(),
+                Step::Note(frequency) => name_from_frequency(*frequency),
+            })
+            .collect();
+        write!(f, "{}", tokens.join(if melodic { " " } else { "" }))
+    }
+}
@@ -0,0 +1,199 @@
+use super::part::Part;
+use super::score;
+use super::song::Song;
+
+/// A named point of a [Song] like `Verse` or `Chorus`. Each marker starts a section that lasts until the next
+/// marker, the last one lasts until the end of the bar the song's last note ends in
+#[derive(Clone)]
+pub struct Marker {
+    pub name: String,
+    pub beat: f32,
+}
+
+impl Song {
+    /// Adds a marker starting a section, keeping the markers in order of their beats
+    pub fn add_marker(&mut self, name: String, beat: f32) -> Result<(), &'static str> {
+        if beat < 0.0 {
+            return Err("Markers can't be placed before beat 0!");
+        }
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '*' || c == ',') {
+            return Err("Marker names can't be empty or hold spaces, commas or *!");
+        }
+        if self.markers.iter().any(|marker| marker.name.eq_ignore_ascii_case(&name)) {
+            return Err("There already is a marker with that name!");
+        }
+        if self.markers.iter().any(|marker| marker.beat == beat) {
+            return Err("There already is a marker on that beat!");
+        }
+        let index = self.markers.iter().position(|marker| marker.beat > beat).unwrap_or(self.markers.len());
+        self.markers.insert(index, Marker { name, beat });
+        for section in self.arrangement.iter_mut() {
+            if *section >= index {
+                *section += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes a marker along with every time its section plays in the arrangement
+    pub fn delete_marker(&mut self, index: usize) {
+        self.markers.remove(index);
+        self.arrangement.retain(|section| *section != index);
+        for section in self.arrangement.iter_mut() {
+            if *section > index {
+                *section -= 1;
+            }
+        }
+    }
+
+    /// First beat and end beat of the section started by the marker at `index`
+    pub fn section_span(&self, index: usize) -> Option<(f32, f32)> {
+        let start = self.markers.get(index)?.beat;
+        let end = match self.markers.get(index + 1) {
+            Some(next) => next.beat,
+            None => {
+                let bar_beats = self.time_signature.bar_beats();
+                let end = self.parts.iter().map(|part| part.with_patterns(&self.patterns).duration()).fold(0.0, f32::max);
+                (score::num_bars(end, bar_beats) as f32 * bar_beats).max(start)
+            }
+        };
+        Some((start, end))
+    }
+
+    /// Sets the order sections play in from their names separated by spaces, like `Intro Verse Chorus Verse Chorus`.
+    /// `Verse*2` plays a section twice in a row. An empty order plays the song as it is written
+    pub fn set_arrangement(&mut self, text: &str) -> Result<(), &'static str> {
+        let mut arrangement = Vec::new();
+        for word in text.split(|c: char| c.is_whitespace() || c == ',').filter(|word| !word.is_empty()) {
+            let (name, times) = match word.split_once('*') {
+                Some((name, times)) => (name, times.parse::<usize>().map_err(|_| "Repeats must be written like Chorus*2!")?),
+                None => (word, 1),
+            };
+            let section = self.markers.iter().position(|marker| marker.name.eq_ignore_ascii_case(name)).ok_or("No marker has that name!")?;
+            arrangement.extend(std::iter::repeat_n(section, times));
+        }
+        self.arrangement = arrangement;
+        Ok(())
+    }
+
+    /// Names of the sections in the order they play
+    pub fn arrangement_text(&self) -> String {
+        self.arrangement.iter().map(|section| self.markers[*section].name.as_str()).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Gets a copy of the song that plays only the section started by the marker at `index`
+    pub fn section(&self, index: usize) -> Result<Song, &'static str> {
+        let marker = self.markers.get(index).ok_or("No marker at that index!")?;
+        let mut song = self.clone();
+        song.name = format!("{} - {}", self.name, marker.name);
+        song.arrangement = vec![index];
+        Ok(song)
+    }
+
+    /// Lays the sections of the parts out one after another in the order of the arrangement. Notes play in the
+    /// section they start in, notes before the first marker aren't in any section
+    pub(crate) fn arrange(&self, parts: Vec<Part>) -> Vec<Part> {
+        if self.arrangement.is_empty() {
+            return parts;
+        }
+        let spans: Vec<(f32, f32)> = (0..self.markers.len()).filter_map(|index| self.section_span(index)).collect();
+        parts
+            .into_iter()
+            .map(|mut part| {
+                let notes = std::mem::take(&mut part.notes);
+                let mut beat = 0.0;
+                for section in &self.arrangement {
+                    let (start, end) = spans[*section];
+                    for note in notes.iter().filter(|note| note.beat >= start && note.beat < end) {
+                        let mut note = note.clone();
+                        note.beat += beat - start;
+                        part.notes.push(note);
+                    }
+                    beat += end - start;
+                }
+                part
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::note::Note;
+    use crate::music::serializable::Serializable;
+
+    /// A song in 4/4 with a note at the start of each of its three bars
+    fn song_with_sections() -> Song {
+        let mut song = Song::new("Structure".to_string(), 120);
+        let mut part = Part::new("Lead".to_string());
+        for (beat, frequency) in [(0.0, 220.0), (4.0, 330.0), (8.0, 440.0)] {
+            part.add_note(Note::new(beat, 1.0, frequency, 0.5).unwrap()).unwrap();
+        }
+        song.parts.push(part);
+        song.add_marker("Verse".to_string(), 0.0).unwrap();
+        song.add_marker("Outro".to_string(), 8.0).unwrap();
+        song
+    }
+
+    fn names(song: &Song) -> Vec<&str> {
+        song.markers.iter().map(|marker| marker.name.as_str()).collect()
+    }
+
+    #[test]
+    fn markers_added_between_others_keep_the_arrangement_pointing_at_the_same_sections() {
+        let mut song = song_with_sections();
+        song.set_arrangement("Verse Outro").unwrap();
+        song.add_marker("Chorus".to_string(), 4.0).unwrap();
+        assert_eq!(names(&song), ["Verse", "Chorus", "Outro"]);
+        assert_eq!(song.arrangement, [0, 2]);
+        assert_eq!(song.arrangement_text(), "Verse Outro");
+        assert!(song.add_marker("chorus".to_string(), 2.0).is_err());
+        assert!(song.add_marker("Bridge".to_string(), 4.0).is_err());
+        assert!(song.add_marker("Two words".to_string(), 2.0).is_err());
+    }
+
+    #[test]
+    fn deleting_a_marker_removes_its_section_from_the_arrangement() {
+        let mut song = song_with_sections();
+        song.add_marker("Chorus".to_string(), 4.0).unwrap();
+        song.set_arrangement("Verse Chorus*2 Outro").unwrap();
+        assert_eq!(song.arrangement, [0, 1, 1, 2]);
+        song.delete_marker(1);
+        assert_eq!(names(&song), ["Verse", "Outro"]);
+        assert_eq!(song.arrangement, [0, 1]);
+        assert!(song.set_arrangement("Verse Bridge").is_err());
+        assert!(song.set_arrangement("Verse*x").is_err());
+    }
+
+    #[test]
+    fn the_last_section_lasts_until_the_end_of_its_bar() {
+        let song = song_with_sections();
+        assert_eq!(song.section_span(0), Some((0.0, 8.0)));
+        assert_eq!(song.section_span(1), Some((8.0, 12.0)));
+        assert_eq!(song.section_span(2), None);
+    }
+
+    #[test]
+    fn sections_play_in_arrangement_order() {
+        let mut song = song_with_sections();
+        song.set_arrangement("Outro Verse Outro").unwrap();
+        let arranged = song.arrange(song.parts.clone());
+        let notes: Vec<(f32, f32)> = arranged[0].notes.iter().map(|note| (note.beat, note.frequency)).collect();
+        assert_eq!(notes, [(0.0, 440.0), (4.0, 220.0), (8.0, 330.0), (12.0, 440.0)]);
+        let outro = song.section(1).unwrap();
+        assert_eq!(outro.name, "Structure - Outro");
+        assert_eq!(outro.arrange(outro.parts.clone())[0].notes.len(), 1);
+        assert!(song.section(2).is_err());
+    }
+
+    #[test]
+    fn markers_and_the_arrangement_are_saved() {
+        let mut song = song_with_sections();
+        song.set_arrangement("Verse*2 Outro").unwrap();
+        let loaded = Song::deserialize(&song.serialize().unwrap()).unwrap();
+        assert_eq!(names(&loaded), ["Verse", "Outro"]);
+        assert_eq!(loaded.markers[1].beat, 8.0);
+        assert_eq!(loaded.arrangement_text(), "Verse Verse Outro");
+    }
+}
@@ -0,0 +1,450 @@
+use std::fmt::Display;
+
+use super::note::Note;
+use super::part::Part;
+use super::pitch::{frequency_from_midi, pitch_class_name, read_pitch_letter};
+
+/// Scales with names, in the order they are listed
+pub const NAMED_SCALES: [Scale; 12] = [
+    Scale::Major,
+    Scale::NaturalMinor,
+    Scale::HarmonicMinor,
+    Scale::MelodicMinor,
+    Scale::Dorian,
+    Scale::Phrygian,
+    Scale::Lydian,
+    Scale::Mixolydian,
+    Scale::Locrian,
+    Scale::MajorPentatonic,
+    Scale::MinorPentatonic,
+    Scale::Blues,
+];
+
+/// A scale or mode, given by the semitones of its notes above the tonic
+#[derive(Clone, PartialEq)]
+pub enum Scale {
+    Major,
+    NaturalMinor,
+    HarmonicMinor,
+    // the ascending form, with a raised sixth and seventh
+    MelodicMinor,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+    // semitones above the tonic in rising order starting with 0, made with [Scale::custom]
+    Custom(Vec<u8>),
+}
+
+impl Scale {
+    /// Makes a scale of semitones above the tonic. They have to start at 0, rise and stay within an octave
+    pub fn custom(steps: Vec<u8>) -> Result<Self, &'static str> {
+        if steps.first() != Some(&0) || steps.windows(2).any(|pair| pair[1] <= pair[0]) || steps.iter().any(|step| *step >= 12) {
+            return Err("Scale steps must start at 0 and rise to at most 11 semitones!");
+        }
+        Ok(Scale::Custom(steps))
+    }
+
+    /// Semitones above the tonic of every note of the scale
+    pub fn steps(&self) -> &[u8] {
+        match self {
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::NaturalMinor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            Scale::MelodicMinor => &[0, 2, 3, 5, 7, 9, 11],
+            Scale::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            Scale::Phrygian => &[0, 1, 3, 5, 7, 8, 10],
+            Scale::Lydian => &[0, 2, 4, 6, 7, 9, 11],
+            Scale::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            Scale::Locrian => &[0, 1, 3, 5, 6, 8, 10],
+            Scale::MajorPentatonic => &[0, 2, 4, 7, 9],
+            Scale::MinorPentatonic => &[0, 3, 5, 7, 10],
+            Scale::Blues => &[0, 3, 5, 6, 7, 10],
+            Scale::Custom(steps) => steps,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scale::Major => "major",
+            Scale::NaturalMinor => "natural minor",
+            Scale::HarmonicMinor => "harmonic minor",
+            Scale::MelodicMinor => "melodic minor",
+            Scale::Dorian => "dorian",
+            Scale::Phrygian => "phrygian",
+            Scale::Lydian => "lydian",
+            Scale::Mixolydian => "mixolydian",
+            Scale::Locrian => "locrian",
+            Scale::MajorPentatonic => "major pentatonic",
+            Scale::MinorPentatonic => "minor pentatonic",
+            Scale::Blues => "blues",
+            Scale::Custom(_) => "custom",
+        }
+    }
+
+    /// Reads a scale by name like `dorian` or `harmonic minor`, or as semitones above the tonic like `0 2 3 7 9`
+    pub fn parse(text: &str) -> Result<Self, &'static str> {
+        let name = text.trim().to_lowercase();
+        let scale = match name.as_str() {
+            "ionian" => Some(Scale::Major),
+            "minor" | "aeolian" => Some(Scale::NaturalMinor),
+            _ => NAMED_SCALES.into_iter().find(|scale| scale.name() == name),
+        };
+        if let Some(scale) = scale {
+            return Ok(scale);
+        }
+        let steps: Result<Vec<u8>, _> = name.split(|c: char| c == ',' || c.is_whitespace()).filter(|step| !step.is_empty()).map(str::parse).collect();
+        Scale::custom(steps.map_err(|_| "Scale not recognised! Use a scale name or semitones like 0 2 3 7 9")?)
+    }
+
+    /// Semitones above the tonic of a degree of the scale counted from 0, going on into the octaves above and below
+    pub fn degree_semitones(&self, degree: i32) -> i32 {
+        let steps = self.steps();
+        let len = steps.len() as i32;
+        degree.div_euclid(len) * 12 + steps[degree.rem_euclid(len) as usize] as i32
+    }
+
+    /// Gets the chord built from every other note of the scale starting on a degree counted from 0, a triad or
+    /// with `seventh` a seventh chord. Only scales of seven notes stack into chords, and only into chords with a quality
+    pub fn chord_on(&self, tonic: u8, degree: usize, seventh: bool) -> Option<Chord> {
+        if self.steps().len() != 7 {
+            return None;
+        }
+        let degree = degree as i32;
+        let root = self.degree_semitones(degree);
+        let intervals: Vec<u8> = [0, 2, 4, 6].iter().take(if seventh { 4 } else { 3 }).map(|third| (self.degree_semitones(degree + third) - root) as u8).collect();
+        let quality = CHORD_QUALITIES.into_iter().find(|quality| quality.intervals() == intervals.as_slice())?;
+        Some(Chord { root: (tonic as i32 + root).rem_euclid(12) as u8, quality, inversion: 0 })
+    }
+
+    /// Makes notes running up the scale from the tonic in `octave` to the tonic an octave higher, one after another
+    /// from `beat`, each lasting `step_beats` beats
+    pub fn notes(&self, tonic: u8, octave: i32, beat: f32, step_beats: f32, volume: f32) -> Result<Vec<Note>, &'static str> {
+        let tonic_midi = (octave + 1) * 12 + tonic as i32;
+        let mut notes = Vec::new();
+        for degree in 0..=self.steps().len() as i32 {
+            let midi = u8::try_from(tonic_midi + self.degree_semitones(degree)).ok().filter(|midi| *midi <= 127).ok_or("Scale leaves the range of MIDI pitches!")?;
+            notes.push(Note::new(beat + degree as f32 * step_beats, step_beats, frequency_from_midi(midi), volume)?);
+        }
+        Ok(notes)
+    }
+}
+
+impl Display for Scale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scale::Custom(steps) => write!(f, "custom ({})", steps.iter().map(|step| step.to_string()).collect::<Vec<_>>().join(" ")),
+            scale => write!(f, "{}", scale.name()),
+        }
+    }
+}
+
+/// Every chord quality, in the order they are listed
+pub const CHORD_QUALITIES: [ChordQuality; 23] = [
+    ChordQuality::Major,
+    ChordQuality::Minor,
+    ChordQuality::Diminished,
+    ChordQuality::Augmented,
+    ChordQuality::Sus2,
+    ChordQuality::Sus4,
+    ChordQuality::Major6,
+    ChordQuality::Minor6,
+    ChordQuality::Dominant7,
+    ChordQuality::Major7,
+    ChordQuality::Minor7,
+    ChordQuality::MinorMajor7,
+    ChordQuality::HalfDiminished7,
+    ChordQuality::Diminished7,
+    ChordQuality::Augmented7,
+    ChordQuality::Add9,
+    ChordQuality::Dominant9,
+    ChordQuality::Major9,
+    ChordQuality::Minor9,
+    ChordQuality::Dominant11,
+    ChordQuality::Minor11,
+    ChordQuality::Dominant13,
+    ChordQuality::Major13,
+];
+
+/// The kind of a chord, given by the semitones of its notes above the root
+#[derive(Clone, Copy, PartialEq)]
+pub enum ChordQuality {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Sus2,
+    Sus4,
+    Major6,
+    Minor6,
+    Dominant7,
+    Major7,
+    Minor7,
+    MinorMajor7,
+    HalfDiminished7,
+    Diminished7,
+    Augmented7,
+    Add9,
+    Dominant9,
+    Major9,
+    Minor9,
+    Dominant11,
+    Minor11,
+    // the eleventh is left out as it clashes with the third
+    Dominant13,
+    Major13,
+}
+
+impl ChordQuality {
+    /// Semitones above the root of every note of the chord in root position
+    pub fn intervals(&self) -> &'static [u8] {
+        match self {
+            ChordQuality::Major => &[0, 4, 7],
+            ChordQuality::Minor => &[0, 3, 7],
+            ChordQuality::Diminished => &[0, 3, 6],
+            ChordQuality::Augmented => &[0, 4, 8],
+            ChordQuality::Sus2 => &[0, 2, 7],
+            ChordQuality::Sus4 => &[0, 5, 7],
+            ChordQuality::Major6 => &[0, 4, 7, 9],
+            ChordQuality::Minor6 => &[0, 3, 7, 9],
+            ChordQuality::Dominant7 => &[0, 4, 7, 10],
+            ChordQuality::Major7 => &[0, 4, 7, 11],
+            ChordQuality::Minor7 => &[0, 3, 7, 10],
+            ChordQuality::MinorMajor7 => &[0, 3, 7, 11],
+            ChordQuality::HalfDiminished7 => &[0, 3, 6, 10],
+            ChordQuality::Diminished7 => &[0, 3, 6, 9],
+            ChordQuality::Augmented7 => &[0, 4, 8, 10],
+            ChordQuality::Add9 => &[0, 4, 7, 14],
+            ChordQuality::Dominant9 => &[0, 4, 7, 10, 14],
+            ChordQuality::Major9 => &[0, 4, 7, 11, 14],
+            ChordQuality::Minor9 => &[0, 3, 7, 10, 14],
+            ChordQuality::Dominant11 => &[0, 4, 7, 10, 14, 17],
+            ChordQuality::Minor11 => &[0, 3, 7, 10, 14, 17],
+            ChordQuality::Dominant13 => &[0, 4, 7, 10, 14, 21],
+            ChordQuality::Major13 => &[0, 4, 7, 11, 14, 21],
+        }
+    }
+
+    /// How the quality is written after the root of a chord symbol, like `m7` in `Am7`
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ChordQuality::Major => "",
+            ChordQuality::Minor => "m",
+            ChordQuality::Diminished => "dim",
+            ChordQuality::Augmented => "aug",
+            ChordQuality::Sus2 => "sus2",
+            ChordQuality::Sus4 => "sus4",
+            ChordQuality::Major6 => "6",
+            ChordQuality::Minor6 => "m6",
+            ChordQuality::Dominant7 => "7",
+            ChordQuality::Major7 => "maj7",
+            ChordQuality::Minor7 => "m7",
+            ChordQuality::MinorMajor7 => "mMaj7",
+            ChordQuality::HalfDiminished7 => "m7b5",
+            ChordQuality::Diminished7 => "dim7",
+            ChordQuality::Augmented7 => "aug7",
+            ChordQuality::Add9 => "add9",
+            ChordQuality::Dominant9 => "9",
+            ChordQuality::Major9 => "maj9",
+            ChordQuality::Minor9 => "m9",
+            ChordQuality::Dominant11 => "11",
+            ChordQuality::Minor11 => "m11",
+            ChordQuality::Dominant13 => "13",
+            ChordQuality::Major13 => "maj13",
+        }
+    }
+
+    /// Reads the quality part of a chord symbol, accepting the usual other spellings like `min7`, `M7` or `°`
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        if let Some(quality) = CHORD_QUALITIES.into_iter().find(|quality| quality.symbol() == symbol) {
+            return Some(quality);
+        }
+        let quality = match symbol {
+            "maj" | "M" => ChordQuality::Major,
+            "min" | "-" => ChordQuality::Minor,
+            "°" | "o" => ChordQuality::Diminished,
+            "+" => ChordQuality::Augmented,
+            "sus" => ChordQuality::Sus4,
+            "M7" | "Δ" | "Δ7" => ChordQuality::Major7,
+            "min7" | "-7" => ChordQuality::Minor7,
+            "ø" | "ø7" | "min7b5" | "-7b5" => ChordQuality::HalfDiminished7,
+            "°7" | "o7" => ChordQuality::Diminished7,
+            "+7" => ChordQuality::Augmented7,
+            "M9" => ChordQuality::Major9,
+            "min9" | "-9" => ChordQuality::Minor9,
+            _ => return None,
+        };
+        Some(quality)
+    }
+}
+
+/// A chord of a quality built on a root, played in root position or with its lowest notes moved up an octave
+#[derive(Clone, Copy, PartialEq)]
+pub struct Chord {
+    // pitch class of the root where 0 is C
+    pub root: u8,
+    pub quality: ChordQuality,
+    // how many of the lowest notes are moved up an octave, 1 is the first inversion
+    pub inversion: u8,
+}
+
+impl Chord {
+    pub fn new(root: u8, quality: ChordQuality, inversion: u8) -> Result<Self, &'static str> {
+        if inversion as usize >= quality.intervals().len() {
+            return Err("A chord has fewer inversions than notes!");
+        }
+        Ok(Chord { root: root % 12, quality, inversion })
+    }
+
+    /// Reads a chord symbol like `C`, `F#m7`, `Bbmaj9` or `Gsus4`. A note of the chord after a slash like `C/E`
+    /// is the bass note of an inversion
+    pub fn parse(text: &str) -> Option<Self> {
+        let (symbol, bass) = match text.trim().split_once('/') {
+            Some((symbol, bass)) => (symbol, Some(bass)),
+            None => (text.trim(), None),
+        };
+        let (root, symbol) = read_pitch_letter(symbol)?;
+        let quality = ChordQuality::from_symbol(symbol)?;
+        let root = root.rem_euclid(12) as u8;
+        let inversion = match bass {
+            Some(bass) => {
+                let (bass, rest) = read_pitch_letter(bass)?;
+                if !rest.is_empty() {
+                    return None;
+                }
+                quality.intervals().iter().position(|interval| (root as i32 + *interval as i32 - bass).rem_euclid(12) == 0)? as u8
+            }
+            None => 0,
+        };
+        Some(Chord { root, quality, inversion })
+    }
+
+    /// MIDI note numbers of the chord from low to high, with the root position root in `octave` where octave 4
+    /// holds middle C
+    pub fn midi_notes(&self, octave: i32) -> Result<Vec<u8>, &'static str> {
+        let root = (octave + 1) * 12 + self.root as i32;
+        let intervals = self.quality.intervals();
+        let inversion = self.inversion as usize % intervals.len();
+        let mut notes: Vec<i32> = intervals.iter().map(|interval| root + *interval as i32).collect();
+        // the moved notes land above every note left in place, even when the chord spans more than an octave
+        for index in 0..inversion {
+            while notes[index] < notes[notes.len() - 1] {
+                notes[index] += 12;
+            }
+        }
+        notes.sort_unstable();
+        notes.into_iter().map(|midi| u8::try_from(midi).ok().filter(|midi| *midi <= 127).ok_or("Chord leaves the range of MIDI pitches!")).collect()
+    }
+
+    /// Makes a note for every pitch of the chord, all starting on `beat` and lasting `duration` beats
+    pub fn notes(&self, beat: f32, duration: f32, octave: i32, volume: f32) -> Result<Vec<Note>, &'static str> {
+        self.midi_notes(octave)?.into_iter().map(|midi| Note::new(beat, duration, frequency_from_midi(midi), volume)).collect()
+    }
+}
+
+impl Display for Chord {
+    /// Writes the chord symbol, with the bass note after a slash for inversions like `C/E`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", pitch_class_name(self.root), self.quality.symbol())?;
+        if self.inversion > 0 {
+            let intervals = self.quality.intervals();
+            let bass = self.root + intervals[self.inversion as usize % intervals.len()];
+            write!(f, "/{}", pitch_class_name(bass))?;
+        }
+        Ok(())
+    }
+}
+
+impl Part {
+    /// Adds every note of a chord, or none of them if any of them would overlap a note of the same pitch
+    pub fn add_chord(&mut self, chord: &Chord, beat: f32, duration: f32, octave: i32, volume: f32) -> Result<(), &'static str> {
+        if self.is_percussion() {
+            return Err("Drum parts pick drums by pitch so they can't play chords!");
+        }
+        self.add_notes(chord.notes(beat, duration, octave, volume)?)
+    }
+
+    /// Adds a run up a scale like [Scale::notes] makes it, or nothing if any note can't be added
+    pub fn add_scale(&mut self, scale: &Scale, tonic: u8, octave: i32, beat: f32, step_beats: f32, volume: f32) -> Result<(), &'static str> {
+        if self.is_percussion() {
+            return Err("Drum parts pick drums by pitch so they can't play scales!");
+        }
+        self.add_notes(scale.notes(tonic, octave, beat, step_beats, volume)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_chord_symbols_and_their_other_spellings() {
+        let chord = Chord::parse("F#m7").unwrap();
+        assert_eq!((chord.root, chord.quality.symbol()), (6, "m7"));
+        assert!(Chord::parse("Bbmaj9").unwrap() == Chord::new(10, ChordQuality::Major9, 0).unwrap());
+        assert!(Chord::parse("C-7b5").unwrap().quality == ChordQuality::HalfDiminished7);
+        assert!(Chord::parse("Gsus").unwrap().quality == ChordQuality::Sus4);
+        assert!(Chord::parse("Hm").is_none());
+        assert!(Chord::parse("Cm13b9").is_none());
+        assert_eq!(Chord::parse("Ebaug").unwrap().to_string(), "D#aug");
+    }
+
+    #[test]
+    fn inversions_move_the_lowest_notes_up_an_octave() {
+        assert_eq!(Chord::parse("C").unwrap().midi_notes(4).unwrap(), [60, 64, 67]);
+        let first = Chord::new(0, ChordQuality::Major, 1).unwrap();
+        assert_eq!(first.midi_notes(4).unwrap(), [64, 67, 72]);
+        assert_eq!(first.to_string(), "C/E");
+        let second = Chord::new(7, ChordQuality::Dominant7, 2).unwrap();
+        assert_eq!(second.midi_notes(3).unwrap(), [62, 65, 67, 71]);
+        // a ninth chord spans more than an octave so the moved root lands above the ninth
+        let ninth = Chord::new(0, ChordQuality::Dominant9, 1).unwrap();
+        assert_eq!(ninth.midi_notes(4).unwrap(), [64, 67, 70, 74, 84]);
+        assert!(Chord::new(0, ChordQuality::Major, 3).is_err());
+        assert!(Chord::parse("G").unwrap().midi_notes(9).is_err());
+    }
+
+    #[test]
+    fn scales_are_found_by_name_or_steps() {
+        assert!(Scale::parse("Harmonic Minor").unwrap() == Scale::HarmonicMinor);
+        assert!(Scale::parse("aeolian").unwrap() == Scale::NaturalMinor);
+        assert_eq!(Scale::parse("0 2 3 7 9").unwrap().steps(), [0, 2, 3, 7, 9]);
+        assert!(Scale::parse("0 3 2").is_err());
+        assert!(Scale::parse("1 2 3").is_err());
+        assert!(Scale::parse("gibberish").is_err());
+    }
+
+    #[test]
+    fn degrees_carry_on_into_other_octaves() {
+        let scale = Scale::MinorPentatonic;
+        assert_eq!(scale.degree_semitones(5), 12);
+        assert_eq!(scale.degree_semitones(-1), -2);
+        let notes = Scale::Dorian.notes(2, 4, 1.0, 0.5, 0.5).unwrap();
+        assert_eq!(notes.len(), 8);
+        assert_eq!((notes[7].beat, notes[7].frequency), (4.5, frequency_from_midi(74)));
+    }
+
+    #[test]
+    fn chords_on_scale_degrees_get_their_quality_from_the_scale() {
+        let symbols: Vec<String> = (0..7).map(|degree| Scale::Major.chord_on(0, degree, false).unwrap().to_string()).collect();
+        assert_eq!(symbols, ["C", "Dm", "Em", "F", "G", "Am", "Bdim"]);
+        assert_eq!(Scale::HarmonicMinor.chord_on(9, 4, true).unwrap().to_string(), "E7");
+        assert!(Scale::MajorPentatonic.chord_on(0, 0, false).is_none());
+    }
+
+    #[test]
+    fn chords_and_scales_are_added_to_parts_whole_or_not_at_all() {
+        let mut part = Part::new("Keys".to_string());
+        part.add_chord(&Chord::parse("Am").unwrap(), 0.0, 2.0, 4, 0.5).unwrap();
+        assert_eq!(part.notes.len(), 3);
+        // the A4 would overlap the A4 already playing
+        assert!(part.add_chord(&Chord::parse("F").unwrap(), 1.0, 1.0, 4, 0.5).is_err());
+        assert_eq!(part.notes.len(), 3);
+        part.add_scale(&Scale::Major, 0, 3, 2.0, 0.25, 0.5).unwrap();
+        assert_eq!(part.notes.len(), 11);
+    }
+}
@@ -0,0 +1,372 @@
+use super::note::{BendPoint, Note};
+use super::part::Part;
+use super::pitch::frequency_from_midi;
+use super::random::Random;
+use super::selection::Selection;
+use super::signature::Key;
+use super::song::Song;
+
+/// Quantized notes are never made shorter than this many beats
+const QUANTIZE_MIN_LENGTH: f32 = 0.001;
+/// How close to a grid line a beat has to be to be swung
+const SWING_TOLERANCE: f32 = 0.001;
+
+/// A change made to every selected note of a [Part] or [Song]
+#[derive(Clone, Copy)]
+pub enum Transform {
+    /// Moves pitches up by a number of semitones, or down when negative
+    Transpose(f32),
+    /// Moves pitches along the scale of a key by a number of scale steps, keeping notes outside the scale
+    /// as far from the scale note below them
+    TransposeDiatonic { key: Key, steps: i32 },
+    /// Mirrors pitches around a pivot frequency so intervals upwards become intervals downwards
+    Invert { pivot: f32 },
+    /// Plays the selected notes backwards, keeping the time they span
+    Retrograde,
+    /// Multiplies lengths and the distances between notes by a factor, from the first selected note
+    Stretch(f32),
+    /// Moves notes later by a number of beats, or earlier when negative
+    Shift(f32),
+    /// Pulls note starts and ends towards the nearest multiple of `grid` beats, all the way at a strength of 1
+    Quantize { grid: f32, strength: f32 },
+    /// Delays notes starting or ending on the second `grid` of every pair of grids. `ratio` is how much of the pair
+    /// the first grid gets, 0.5 is straight and 2/3 swings like triplets
+    Swing { grid: f32, ratio: f32 },
+    /// Sets the volume of notes
+    SetVolume(f32),
+    /// Moves notes by up to `timing` beats and changes their volume by up to `volume`, at random but the same
+    /// way every time for the same `seed`
+    Humanize { timing: f32, volume: f32, seed: u64 },
+}
+
+impl Transform {
+    /// Whether the transform changes pitches, which would change the drum of a drum note
+    pub fn changes_pitch(&self) -> bool {
+        matches!(self, Transform::Transpose(_) | Transform::TransposeDiatonic { .. } | Transform::Invert { .. })
+    }
+
+    /// Checks the settings of the transform can be applied
+    fn check(&self) -> Result<(), &'static str> {
+        match *self {
+            Transform::Stretch(factor) if factor <= 0.0 => Err("Notes can only be stretched by a factor above 0!"),
+            Transform::Invert { pivot } if pivot <= 0.0 => Err("Pivot pitch must be above 0 Hz!"),
+            Transform::Quantize { grid, strength } if grid <= 0.0 || !(0.0..=1.0).contains(&strength) => {
+                Err("Quantize needs a grid above 0 beats and a strength between 0 and 1!")
+            }
+            Transform::Swing { grid, ratio } if grid <= 0.0 || ratio <= 0.0 || ratio >= 1.0 => {
+                Err("Swing needs a grid above 0 beats and a ratio between 0 and 1!")
+            }
+            Transform::SetVolume(volume) if !(0.0..=1.0).contains(&volume) => Err("Note must have volume in range [0, 1]"),
+            Transform::Humanize { timing, volume, .. } if timing < 0.0 || volume < 0.0 => Err("Humanize amounts can't be negative!"),
+            _ => Ok(()),
+        }
+    }
+
+    /// Changes a single note. `span` is the first beat and the end beat of all selected notes
+    fn apply(&self, note: &mut Note, span: (f32, f32), random: &mut Random) {
+        match *self {
+            Transform::Transpose(semitones) => note.frequency *= 2_f32.powf(semitones / 12.0),
+            Transform::TransposeDiatonic { key, steps } => {
+                let semitones = diatonic_semitones(&key, note.frequency, steps);
+                note.frequency *= 2_f32.powf(semitones as f32 / 12.0);
+            }
+            Transform::Invert { pivot } => {
+                note.frequency = pivot * pivot / note.frequency;
+                for point in &mut note.bend {
+                    point.semitones = -point.semitones;
+                }
+            }
+            Transform::Retrograde => {
+                note.beat = span.0 + span.1 - note.end_beat();
+                let duration = note.duration;
+                let bend = note.bend.iter().map(|point| BendPoint { offset: duration - point.offset, semitones: point.semitones }).collect();
+                note.set_bend(bend);
+            }
+            Transform::Stretch(factor) => {
+                note.beat = span.0 + (note.beat - span.0) * factor;
+                note.duration *= factor;
+                for point in &mut note.bend {
+                    point.offset *= factor;
+                }
+            }
+            Transform::Shift(beats) => note.beat += beats,
+            Transform::Quantize { grid, strength } => {
+                let pull = |beat: f32| beat + ((beat / grid).round() * grid - beat) * strength;
+                let end = pull(note.end_beat());
+                note.beat = pull(note.beat);
+                // notes shorter than the grid would be pulled down to nothing so they keep their length
+                if end - note.beat > QUANTIZE_MIN_LENGTH {
+                    note.duration = end - note.beat;
+                }
+            }
+            Transform::Swing { grid, ratio } => {
+                let start = swing(note.beat, grid, ratio);
+                // ends between grid lines move with the start so short notes can't be swung past their own end
+                if on_grid(note.end_beat(), grid) {
+                    note.duration = swing(note.end_beat(), grid, ratio) - start;
+                }
+                note.beat = start;
+            }
+            Transform::SetVolume(volume) => note.volume = volume,
+            Transform::Humanize { timing, volume, .. } => {
+                note.beat = (note.beat + random.next_signed_unit() * timing).max(0.0);
+                note.volume = (note.volume + random.next_signed_unit() * volume).clamp(0.0, 1.0);
+            }
+        }
+    }
+
+    /// The transform to use on the part at `index` of a song, so humanized parts don't all move the same way
+    fn for_part(&self, index: usize) -> Self {
+        match *self {
+            Transform::Humanize { timing, volume, seed } => Transform::Humanize { timing, volume, seed: seed.wrapping_add(index as u64) },
+            transform => transform,
+        }
+    }
+}
+
+/// Moves a beat on the second grid of a pair of grids to where the swing ratio puts it
+fn swing(beat: f32, grid: f32, ratio: f32) -> f32 {
+    let step = (beat / grid).round();
+    if !on_grid(beat, grid) || step as i64 % 2 == 0 {
+        return beat;
+    }
+    (step - 1.0) * grid + 2.0 * grid * ratio
+}
+
+/// Whether a beat is on a multiple of `grid` beats
+fn on_grid(beat: f32, grid: f32) -> bool {
+    (beat - (beat / grid).round() * grid).abs() <= SWING_TOLERANCE
+}
+
+/// How many semitones moving a pitch `steps` steps along the scale of `key` moves it
+fn diatonic_semitones(key: &Key, frequency: f32, steps: i32) -> i32 {
+    let scale = key.scale();
+    let midi = (69.0 + 12.0 * (frequency / 440.0).log2()).round() as i32;
+    let from_tonic = midi - key.tonic as i32;
+    let octave = from_tonic.div_euclid(12);
+    let pitch_class = from_tonic.rem_euclid(12);
+    // notes outside the scale move with the scale note below them
+    let degree = scale.iter().rposition(|step| (*step as i32) <= pitch_class).unwrap_or(0) as i32;
+    let chromatic = pitch_class - scale[degree as usize] as i32;
+    let target = degree + steps;
+    let target_midi = (octave + target.div_euclid(7)) * 12 + scale[target.rem_euclid(7) as usize] as i32 + chromatic + key.tonic as i32;
+    target_midi - midi
+}
+
+/// Gets the number of semitones in an interval like `m3` for a minor third or `P5` for a perfect fifth.
+/// A leading `-` goes down
+pub fn interval_semitones(name: &str) -> Option<i32> {
+    let name = name.trim();
+    let (sign, name) = match name.strip_prefix('-') {
+        Some(name) => (-1, name),
+        None => (1, name),
+    };
+    let semitones = match name {
+        "P1" => 0,
+        "m2" => 1,
+        "M2" => 2,
+        "m3" => 3,
+        "M3" => 4,
+        "P4" => 5,
+        "A4" | "d5" => 6,
+        "P5" => 7,
+        "m6" => 8,
+        "M6" => 9,
+        "m7" => 10,
+        "M7" => 11,
+        "P8" => 12,
+        _ => return None,
+    };
+    Some(sign * semitones)
+}
+
+/// First beat and end beat of the selected notes of each part
+fn selected_span<'a>(parts: impl Iterator<Item = &'a Part>, selection: &Selection) -> Option<(f32, f32)> {
+    let selected = parts.flat_map(|part| part.notes.iter().enumerate().filter(|(index, note)| selection.selects(*index, note)));
+    selected.fold(None, |span, (_, note)| match span {
+        Some((start, end)) => Some((f32::min(start, note.beat), f32::max(end, note.end_beat()))),
+        None => Some((note.beat, note.end_beat())),
+    })
+}
+
+impl Part {
+    /// Applies a transform to the selected notes. Nothing changes if a note would end up before beat 0,
+    /// outside the MIDI pitch range or on top of another note of the same pitch
+    pub fn transform(&mut self, transform: Transform, selection: &Selection) -> Result<(), &'static str> {
+        let span = selected_span(std::iter::once(&*self), selection).ok_or("No notes are selected!")?;
+        self.transform_within(transform, selection, span)
+    }
+
+    fn transform_within(&mut self, transform: Transform, selection: &Selection, span: (f32, f32)) -> Result<(), &'static str> {
+        if self.is_percussion() && transform.changes_pitch() {
+            return Err("Drum notes pick their drum by pitch so their pitch can't be transformed!");
+        }
+        transform.check()?;
+        let mut random = Random::new(match transform {
+            Transform::Humanize { seed, .. } => seed,
+            _ => 0,
+        });
+        let old_notes = self.notes.clone();
+        let mut changed = Vec::new();
+        for (index, note) in self.notes.iter_mut().enumerate().filter(|(index, note)| selection.selects(*index, note)) {
+            transform.apply(note, span, &mut random);
+            changed.push(index);
+        }
+        if let Err(err) = self.check_notes(&changed) {
+            self.notes = old_notes;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Checks the rules notes are added by still hold for the notes at the `changed` indices
+    pub(crate) fn check_notes(&self, changed: &[usize]) -> Result<(), &'static str> {
+        // a little leeway so transposing the lowest and highest notes back and forth doesn't fail on rounding
+        let (lowest, highest) = (frequency_from_midi(0) * 0.99, frequency_from_midi(127) * 1.01);
+        for index in changed {
+            let note = &self.notes[*index];
+            if note.beat < -0.0001 {
+                return Err("Notes would start before beat 0!");
+            }
+            if note.duration <= 0.0 {
+                return Err("Notes would last no time!");
+            }
+            if note.frequency < lowest || note.frequency > highest {
+                return Err("Notes would leave the range of MIDI pitches!");
+            }
+            if self.overlaps_same_pitch(note, Some(*index)) {
+                return Err("Notes would overlap notes of the same pitch!");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Song {
+    /// Applies a transform to the selected notes of every part, with note indices counted within each part and
+    /// retrogrades and stretches measured from the notes of all parts so the parts stay together.
+    /// Drum parts are left out of pitch transforms. Nothing changes if any part can't be transformed
+    pub fn transform(&mut self, transform: Transform, selection: &Selection) -> Result<(), &'static str> {
+        let in_transform = |part: &&Part| !(part.is_percussion() && transform.changes_pitch());
+        let span = selected_span(self.parts.iter().filter(in_transform), selection).ok_or("No notes are selected!")?;
+        let old_notes: Vec<Vec<Note>> = self.parts.iter().map(|part| part.notes.clone()).collect();
+        for index in 0..self.parts.len() {
+            if !in_transform(&&self.parts[index]) || selection.indices_in(&self.parts[index].notes).is_empty() {
+                continue;
+            }
+            if let Err(err) = self.parts[index].transform_within(transform.for_part(index), selection, span) {
+                for (part, notes) in self.parts.iter_mut().zip(old_notes) {
+                    part.notes = notes;
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::{frequency_from_name, name_from_frequency};
+
+    fn part_of(notes: &[(f32, f32, &str)]) -> Part {
+        let mut part = Part::new("test".to_string());
+        for (beat, duration, name) in notes {
+            part.add_note(Note::new(*beat, *duration, frequency_from_name(name).unwrap(), 0.5).unwrap()).unwrap();
+        }
+        part
+    }
+
+    fn part_with(notes: &[(f32, f32)]) -> Part {
+        let mut part = Part::new("test".to_string());
+        for (beat, duration) in notes {
+            part.add_note(Note::new(*beat, *duration, 440.0, 0.5).unwrap()).unwrap();
+        }
+        part
+    }
+
+    fn names(part: &Part) -> Vec<(f32, String)> {
+        part.notes.iter().map(|note| (note.beat, name_from_frequency(note.frequency))).collect()
+    }
+
+    #[test]
+    fn chords_can_be_transformed() {
+        let mut part = part_of(&[(0.0, 1.0, "C4"), (0.0, 1.0, "E4"), (0.0, 1.0, "G4")]);
+        part.transform(Transform::Transpose(2.0), &Selection::all()).unwrap();
+        assert_eq!(names(&part), [(0.0, "D4".to_string()), (0.0, "F#4".to_string()), (0.0, "A4".to_string())]);
+    }
+
+    #[test]
+    fn transforms_onto_notes_of_the_same_pitch_change_nothing() {
+        let mut part = part_of(&[(0.0, 1.0, "C4"), (2.0, 1.0, "C4"), (2.0, 1.0, "E4")]);
+        assert!(part.transform(Transform::Shift(1.5), &Selection::beats(0.0, 1.0).unwrap()).is_err());
+        assert_eq!(part.notes[0].beat, 0.0);
+        // moving onto a different pitch makes a chord
+        part.transform(Transform::Transpose(2.0), &Selection::beats(0.0, 1.0).unwrap()).unwrap();
+        part.transform(Transform::Shift(2.0), &Selection::beats(0.0, 1.0).unwrap()).unwrap();
+        assert_eq!(names(&part)[0], (2.0, "D4".to_string()));
+    }
+
+    #[test]
+    fn diatonic_transposes_follow_the_scale() {
+        let mut part = part_of(&[(0.0, 1.0, "E4"), (1.0, 1.0, "B4"), (2.0, 1.0, "F#4")]);
+        part.transform(Transform::TransposeDiatonic { key: Key::default(), steps: 1 }, &Selection::all()).unwrap();
+        let names: Vec<String> = names(&part).into_iter().map(|(_, name)| name).collect();
+        assert_eq!(names, ["F4", "C5", "G#4"]);
+    }
+
+    #[test]
+    fn retrogrades_keep_the_span_of_the_notes() {
+        let mut part = part_of(&[(1.0, 1.0, "C4"), (2.0, 2.0, "D4")]);
+        part.transform(Transform::Retrograde, &Selection::all()).unwrap();
+        assert_eq!(names(&part), [(3.0, "C4".to_string()), (1.0, "D4".to_string())]);
+        assert_eq!(interval_semitones("-m3"), Some(-3));
+    }
+
+    #[test]
+    fn swing_moves_notes_on_the_off_beat_grid() {
+        let mut part = part_with(&[(0.0, 0.5), (0.5, 0.5)]);
+        part.transform(Transform::Swing { grid: 0.5, ratio: 0.75 }, &Selection::all()).unwrap();
+        assert_eq!((part.notes[0].beat, part.notes[0].duration), (0.0, 0.75));
+        assert_eq!((part.notes[1].beat, part.notes[1].duration), (0.75, 0.25));
+    }
+
+    #[test]
+    fn swing_keeps_the_length_of_notes_ending_off_the_grid() {
+        let mut part = part_with(&[(0.5, 0.1)]);
+        part.transform(Transform::Swing { grid: 0.5, ratio: 0.75 }, &Selection::all()).unwrap();
+        assert_eq!(part.notes[0].beat, 0.75);
+        assert!((part.notes[0].duration - 0.1).abs() < 0.0001);
+    }
+
+    #[test]
+    fn notes_lasting_no_time_are_refused() {
+        let mut part = part_with(&[(0.0, 1.0)]);
+        part.notes[0].duration = 0.0;
+        assert!(part.check_notes(&[0]).is_err());
+    }
+
+    #[test]
+    fn quantize_pulls_notes_towards_the_grid_by_the_strength() {
+        let mut part = part_with(&[(0.1, 0.8)]);
+        part.transform(Transform::Quantize { grid: 0.5, strength: 0.5 }, &Selection::all()).unwrap();
+        assert!((part.notes[0].beat - 0.05).abs() < 0.0001);
+        assert!((part.notes[0].end_beat() - 0.95).abs() < 0.0001);
+    }
+
+    #[test]
+    fn humanize_is_the_same_for_the_same_seed() {
+        let notes: Vec<(f32, f32)> = (0..8).map(|step| (step as f32 + 1.0, 0.5)).collect();
+        let humanize = |seed: u64| {
+            let mut part = part_with(&notes);
+            part.transform(Transform::Humanize { timing: 0.1, volume: 0.2, seed }, &Selection::all()).unwrap();
+            part.notes.iter().map(|note| (note.beat, note.volume)).collect::<Vec<(f32, f32)>>()
+        };
+        assert_eq!(humanize(7), humanize(7));
+        assert_ne!(humanize(7), humanize(8));
+        for ((beat, volume), (original, _)) in humanize(7).into_iter().zip(&notes) {
+            assert!((beat - original).abs() <= 0.1 && (0.3..=0.7).contains(&volume));
+        }
+    }
+}
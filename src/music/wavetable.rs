@@ -0,0 +1,301 @@
+use std::f32::consts::PI;
+use std::fmt::Display;
+
+use super::envelope::Envelope;
+use super::serializable::{ByteReader, Serializable};
+
+/// Length of the band limited tables the frames are rendered into
+const TABLE_SIZE: usize = 2048;
+/// Each frame is rendered once per power of two harmonic count up to the most the table can hold
+const NUM_LEVELS: usize = 11;
+
+/// One single cycle waveform of a [WavetableSynth]
+#[derive(Clone)]
+pub enum Frame {
+    /// Amplitudes of the harmonics, starting at the fundamental
+    Harmonics(Vec<f32>),
+    /// A drawn waveform of any length that is played back as one cycle
+    Samples(Vec<f32>),
+}
+
+impl Frame {
+    /// Gets the (cosine, sine) amplitudes of each harmonic starting at the fundamental
+    fn spectrum(&self) -> Vec<(f32, f32)> {
+        match self {
+            Frame::Harmonics(amplitudes) => amplitudes.iter().map(|amplitude| (0.0, *amplitude)).collect(),
+            Frame::Samples(samples) => {
+                let len = samples.len();
+                (1..=len / 2)
+                    .map(|harmonic| {
+                        let mut cos_sum = 0.0;
+                        let mut sin_sum = 0.0;
+                        for (i, sample) in samples.iter().enumerate() {
+                            let angle = 2.0 * PI * (harmonic * i) as f32 / len as f32;
+                            cos_sum += sample * angle.cos();
+                            sin_sum += sample * angle.sin();
+                        }
+                        (cos_sum * 2.0 / len as f32, sin_sum * 2.0 / len as f32)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Renders the frame once per level, where level `l` only holds the first 2^l harmonics
+    fn band_limited_tables(&self) -> Vec<Vec<f32>> {
+        let spectrum = self.spectrum();
+        let mut tables: Vec<Vec<f32>> = (0..NUM_LEVELS)
+            .map(|level| {
+                let num_harmonics = (1 << level).min(spectrum.len());
+                (0..TABLE_SIZE)
+                    .map(|i| {
+                        spectrum[..num_harmonics]
+                            .iter()
+                            .enumerate()
+                            .map(|(harmonic, (cos_amp, sin_amp))| {
+                                let angle = 2.0 * PI * ((harmonic + 1) * i) as f32 / TABLE_SIZE as f32;
+                                cos_amp * angle.cos() + sin_amp * angle.sin()
+                            })
+                            .sum()
+                    })
+                    .collect()
+            })
+            .collect();
+        // Scale every level the same so the full band table peaks at 1
+        let peak = tables[NUM_LEVELS - 1].iter().fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        if peak > 0.0 {
+            for sample in tables.iter_mut().flatten() {
+                *sample /= peak;
+            }
+        }
+        tables
+    }
+}
+
+/// An additive or wavetable synthesizer. Frames are morphed from the first to the last over `morph_time` seconds
+/// and are only played with the harmonics that fit under the nyquist frequency to avoid aliasing
+#[derive(Clone)]
+pub struct WavetableSynth {
+    frames: Vec<Frame>,
+    // seconds it takes to morph from the first to the last frame
+    pub morph_time: f32,
+    pub envelope: Envelope,
+    // [frame][level][sample]
+    tables: Vec<Vec<Vec<f32>>>,
+}
+
+impl WavetableSynth {
+    pub fn new(frames: Vec<Frame>, morph_time: f32, envelope: Envelope) -> Result<Self, &'static str> {
+        let mut synth = WavetableSynth { frames: Vec::new(), morph_time, envelope, tables: Vec::new() };
+        synth.set_frames(frames)?;
+        Ok(synth)
+    }
+
+    /// Additive synth with a single frame of harmonic amplitudes
+    pub fn additive(harmonics: Vec<f32>, envelope: Envelope) -> Result<Self, &'static str> {
+        WavetableSynth::new(vec![Frame::Harmonics(harmonics)], 0.0, envelope)
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Replaces the frames and renders their band limited tables
+    pub fn set_frames(&mut self, frames: Vec<Frame>) -> Result<(), &'static str> {
+        if frames.is_empty() {
+            return Err("A wavetable needs at least one frame");
+        }
+        for frame in &frames {
+            match frame {
+                Frame::Harmonics(harmonics) if harmonics.is_empty() => return Err("A harmonic frame needs at least one harmonic"),
+                Frame::Samples(samples) if samples.len() < 2 => return Err("A drawn frame needs at least two samples"),
+                _ => {}
+            }
+        }
+        self.tables = frames.iter().map(|frame| frame.band_limited_tables()).collect();
+        self.frames = frames;
+        Ok(())
+    }
+
+    /// Gets the value of a note playing at `frequency` `time` seconds after it started when it is held for `held` seconds.
+    /// `phase` is how many cycles have played
+    pub fn sample(&self, phase: f64, frequency: f32, time: f32, held: f32, sample_rate: u32) -> f32 {
+        let max_harmonics = (sample_rate as f32 / 2.0 / frequency).floor();
+        if max_harmonics < 1.0 {
+            return 0.0;
+        }
+        let level = (max_harmonics.log2().floor() as usize).min(NUM_LEVELS - 1);
+        let phase = phase.rem_euclid(1.0) as f32 * TABLE_SIZE as f32;
+        let position = if self.morph_time > 0.0 {
+            (time / self.morph_time).min(1.0) * (self.tables.len() - 1) as f32
+        } else {
+            0.0
+        };
+        let frame = (position.floor() as usize).min(self.tables.len() - 1);
+        let next_frame = (frame + 1).min(self.tables.len() - 1);
+        let blend = position - frame as f32;
+        let value = lookup(&self.tables[frame][level], phase) * (1.0 - blend) + lookup(&self.tables[next_frame][level], phase) * blend;
+        value * self.envelope.level(time, held)
+    }
+
+    /// The wavetable patches that ship with the editor as (name, synth) pairs
+    pub fn presets() -> Vec<(String, WavetableSynth)> {
+        let saw: Vec<f32> = (1..=64).map(|harmonic| 1.0 / harmonic as f32).collect();
+        let square: Vec<f32> = (1..=64).map(|harmonic| if harmonic % 2 == 1 { 1.0 / harmonic as f32 } else { 0.0 }).collect();
+        let sine: Vec<f32> = (0..64).map(|i| (2.0 * PI * i as f32 / 64.0).sin()).collect();
+        let pulse: Vec<f32> = (0..64).map(|i| if i < 16 { 1.0 } else { -0.3 }).collect();
+        vec![
+            ("Organ".to_string(), WavetableSynth::additive(vec![1.0, 0.5, 0.0, 0.25, 0.0, 0.12, 0.0, 0.06], Envelope::new(0.01, 0.0, 1.0, 0.05)).unwrap()),
+            ("Saw Lead".to_string(), WavetableSynth::additive(saw, Envelope::new(0.01, 0.2, 0.7, 0.1)).unwrap()),
+            ("Square Pad".to_string(), WavetableSynth::additive(square, Envelope::new(0.3, 0.5, 0.8, 0.6)).unwrap()),
+            ("Morph Pad".to_string(), WavetableSynth::new(vec![Frame::Samples(sine), Frame::Samples(pulse)], 2.0, Envelope::new(0.2, 0.5, 0.8, 0.8)).unwrap()),
+        ]
+    }
+}
+
+fn lookup(table: &[f32], phase: f32) -> f32 {
+    let index = phase.floor() as usize % table.len();
+    let next = (index + 1) % table.len();
+    let blend = phase - phase.floor();
+    table[index] * (1.0 - blend) + table[next] * blend
+}
+
+impl Display for WavetableSynth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Wavetable morphing over {}s, {}", self.morph_time, self.envelope)?;
+        for (index, frame) in self.frames.iter().enumerate() {
+            match frame {
+                Frame::Harmonics(harmonics) => writeln!(f, "\t{}. harmonics: {:?}", index + 1, harmonics)?,
+                Frame::Samples(samples) => writeln!(f, "\t{}. drawn cycle of {} samples", index + 1, samples.len())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serializable for WavetableSynth {
+    /// Serializes a `WavetableSynth` struct into a byte representation
+    /// 16: envelope
+    /// f32: morph_time
+    /// u16: num_frames
+    /// (frames) u8: kind
+    /// (frames) u16: num_values
+    /// (frames) (values) f32: value
+    fn serialize(&self) -> Result<Vec<u8>, &'static str> {
+        let mut serialized_data = self.envelope.serialize()?;
+        serialized_data.extend(self.morph_time.to_le_bytes());
+        serialized_data.extend((self.frames.len() as u16).to_le_bytes());
+        for frame in &self.frames {
+            let (kind, values) = match frame {
+                Frame::Harmonics(harmonics) => (0_u8, harmonics),
+                Frame::Samples(samples) => (1_u8, samples),
+            };
+            if values.len() > u16::MAX as usize {
+                return Err("Could not serialize wavetable. Frame too long!");
+            }
+            serialized_data.push(kind);
+            serialized_data.extend((values.len() as u16).to_le_bytes());
+            for value in values {
+                serialized_data.extend(value.to_le_bytes());
+            }
+        }
+        Ok(serialized_data)
+    }
+
+    fn deserialize(serialized_data: &[u8]) -> Result<Self, &'static str> {
+        let err = "Invalid serialized data! Insufficient data for wavetable";
+        let mut reader = ByteReader::new(serialized_data);
+        let envelope = Envelope::deserialize(reader.read_bytes(16, err)?)?;
+        let morph_time = reader.read_f32(err)?;
+        let num_frames = reader.read_u16(err)?;
+        let mut frames = Vec::new();
+        for _ in 0..num_frames {
+            let kind = reader.read_u8(err)?;
+            let num_values = reader.read_u16(err)?;
+            let mut values = Vec::new();
+            for _ in 0..num_values {
+                values.push(reader.read_f32(err)?);
+            }
+            frames.push(match kind {
+                0 => Frame::Harmonics(values),
+                1 => Frame::Samples(values),
+                _ => return Err("Invalid serialized data! Unknown wavetable frame kind"),
+            });
+        }
+        WavetableSynth::new(frames, morph_time, envelope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held(synth: &WavetableSynth, frequency: f32, sample_rate: u32, num_samples: usize) -> Vec<f32> {
+        (0..num_samples)
+            .map(|i| {
+                let time = i as f32 / sample_rate as f32;
+                synth.sample((frequency * time) as f64, frequency, time, 10.0, sample_rate)
+            })
+            .collect()
+    }
+
+    /// Largest difference between the samples and a sine of the same frequency scaled to the same peak
+    fn distance_from_sine(samples: &[f32], frequency: f32, sample_rate: u32) -> f32 {
+        let peak = samples.iter().fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| (sample - peak * (2.0 * PI * frequency * i as f32 / sample_rate as f32).sin()).abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn a_single_harmonic_is_a_sine() {
+        let synth = WavetableSynth::additive(vec![1.0], Envelope::default()).unwrap();
+        let samples = held(&synth, 220.0, 44100, 500);
+        assert!(distance_from_sine(&samples, 220.0, 44100) < 0.001);
+    }
+
+    #[test]
+    fn harmonics_over_the_nyquist_frequency_are_left_out() {
+        let saw: Vec<f32> = (1..=64).map(|harmonic| 1.0 / harmonic as f32).collect();
+        let synth = WavetableSynth::additive(saw, Envelope::default()).unwrap();
+        // at 3 kHz only the fundamental fits under 4 kHz
+        assert!(distance_from_sine(&held(&synth, 3000.0, 8000, 500), 3000.0, 8000) < 0.01);
+        // at 110 Hz the saw keeps its harmonics
+        assert!(distance_from_sine(&held(&synth, 110.0, 8000, 500), 110.0, 8000) > 0.1);
+    }
+
+    #[test]
+    fn drawn_frames_play_as_one_cycle() {
+        let cycle: Vec<f32> = (0..32).map(|i| (2.0 * PI * i as f32 / 32.0).sin()).collect();
+        let synth = WavetableSynth::new(vec![Frame::Samples(cycle)], 0.0, Envelope::default()).unwrap();
+        assert!(distance_from_sine(&held(&synth, 440.0, 44100, 500), 440.0, 44100) < 0.001);
+    }
+
+    #[test]
+    fn frames_morph_from_first_to_last() {
+        let synth = WavetableSynth::new(vec![Frame::Harmonics(vec![1.0]), Frame::Harmonics(vec![0.0, 1.0])], 1.0, Envelope::default()).unwrap();
+        let start = synth.sample(0.25, 100.0, 0.0025, 10.0, 8000);
+        let end = synth.sample(200.25, 100.0, 2.0025, 10.0, 8000);
+        // a quarter of the way through a cycle of the fundamental and half way through its second harmonic, the
+        // start has barely begun to morph
+        assert!((start - 1.0).abs() < 0.01);
+        assert!(end.abs() < 0.001);
+    }
+
+    #[test]
+    fn empty_frames_are_refused() {
+        assert!(WavetableSynth::new(Vec::new(), 0.0, Envelope::default()).is_err());
+        assert!(WavetableSynth::additive(Vec::new(), Envelope::default()).is_err());
+        assert!(WavetableSynth::new(vec![Frame::Samples(vec![1.0])], 0.0, Envelope::default()).is_err());
+    }
+
+    #[test]
+    fn patches_sound_the_same_after_being_saved() {
+        for (name, synth) in WavetableSynth::presets() {
+            let loaded = WavetableSynth::deserialize(&synth.serialize().unwrap()).unwrap();
+            assert_eq!(held(&loaded, 330.0, 8000, 200), held(&synth, 330.0, 8000, 200), "{name} changed when saved");
+        }
+    }
+}
@@ -0,0 +1,206 @@
+/// An element of an XML document with its attributes, child elements and the text directly inside it
+pub(crate) struct XmlElement {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlElement>,
+    pub text: String,
+}
+
+impl XmlElement {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Gets the first child element with a name
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Gets every child element with a name in document order
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// Gets the trimmed text of the first child element with a name
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+}
+
+/// Reads an XML document into its root element. Comments, processing instructions and the doctype are skipped,
+/// CDATA is kept as text and the predefined and numeric character entities are replaced
+pub(crate) fn parse_xml(text: &str) -> Result<XmlElement, &'static str> {
+    let mut reader = XmlReader { text, position: 0 };
+    loop {
+        reader.skip_whitespace();
+        if reader.rest().is_empty() {
+            return Err("XML document has no root element!");
+        }
+        if !reader.skip_markup()? {
+            break;
+        }
+    }
+    reader.read_element()
+}
+
+struct XmlReader<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> XmlReader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Moves past `end`, failing when it never comes
+    fn skip_past(&mut self, end: &str) -> Result<(), &'static str> {
+        match self.rest().find(end) {
+            Some(index) => {
+                self.position += index + end.len();
+                Ok(())
+            }
+            None => Err("XML document ends in the middle of a tag!"),
+        }
+    }
+
+    /// Skips a comment, processing instruction or doctype at the current position, returning whether there was one
+    fn skip_markup(&mut self) -> Result<bool, &'static str> {
+        let rest = self.rest();
+        if rest.starts_with("<!--") {
+            self.skip_past("-->")?;
+        } else if rest.starts_with("<?") {
+            self.skip_past("?>")?;
+        } else if rest.starts_with("<!DOCTYPE") {
+            // the doctype can hold an internal subset in square brackets with tags of its own
+            let mut depth = 0;
+            let end = rest.char_indices().find(|(_, c)| {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    '>' if depth == 0 => return true,
+                    _ => {}
+                }
+                false
+            });
+            match end {
+                Some((index, _)) => self.position += index + 1,
+                None => return Err("XML document ends in the middle of a tag!"),
+            }
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn read_name(&mut self) -> String {
+        let rest = self.rest();
+        let len = rest.find(|c: char| c.is_whitespace() || c == '>' || c == '/' || c == '=').unwrap_or(rest.len());
+        self.position += len;
+        rest[..len].to_string()
+    }
+
+    /// Reads the element starting at the current `<`, with everything inside it
+    fn read_element(&mut self) -> Result<XmlElement, &'static str> {
+        let err = "XML element is not closed!";
+        if !self.rest().starts_with('<') {
+            return Err("Expected an XML element!");
+        }
+        self.position += 1;
+        let mut element = XmlElement { name: self.read_name(), attributes: Vec::new(), children: Vec::new(), text: String::new() };
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.position += 2;
+                return Ok(element);
+            }
+            if rest.starts_with('>') {
+                self.position += 1;
+                break;
+            }
+            if rest.is_empty() {
+                return Err(err);
+            }
+            let name = self.read_name();
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err("XML attribute has no value!");
+            }
+            self.position += 1;
+            self.skip_whitespace();
+            let quote = self.rest().chars().next().ok_or(err)?;
+            if quote != '"' && quote != '\'' {
+                return Err("XML attribute value is not quoted!");
+            }
+            self.position += 1;
+            let len = self.rest().find(quote).ok_or(err)?;
+            let value = unescape(&self.rest()[..len]);
+            self.position += len + 1;
+            element.attributes.push((name, value));
+        }
+        loop {
+            let rest = self.rest();
+            let len = rest.find('<').ok_or(err)?;
+            element.text.push_str(&unescape(&rest[..len]));
+            self.position += len;
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.skip_past(">")?;
+                return Ok(element);
+            }
+            if rest.starts_with("<![CDATA[") {
+                let len = rest.find("]]>").ok_or(err)?;
+                element.text.push_str(&rest["<![CDATA[".len()..len]);
+                self.position += len + "]]>".len();
+                continue;
+            }
+            if !self.skip_markup()? {
+                element.children.push(self.read_element()?);
+            }
+        }
+    }
+}
+
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut unescaped = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else { break };
+        let entity = &rest[1..end];
+        let character = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()).and_then(char::from_u32),
+            },
+        };
+        match character {
+            Some(character) => {
+                unescaped.push(character);
+                rest = &rest[end + 1..];
+            }
+            // unknown entities are kept as they are written
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
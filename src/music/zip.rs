@@ -0,0 +1,265 @@
+const END_OF_DIRECTORY: u32 = 0x06054b50;
+const DIRECTORY_ENTRY: u32 = 0x02014b50;
+const LOCAL_HEADER: u32 = 0x04034b50;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// Order the lengths of the code length code are stored in
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// A file stored in a zip archive
+pub(crate) struct ZipEntry<'a> {
+    pub name: String,
+    method: u16,
+    compressed: &'a [u8],
+}
+
+impl ZipEntry<'_> {
+    /// Gets the contents of the file, inflating them if they are compressed
+    pub fn read(&self) -> Result<Vec<u8>, &'static str> {
+        match self.method {
+            STORED => Ok(self.compressed.to_vec()),
+            DEFLATED => inflate(self.compressed),
+            _ => Err("Zip file uses an unsupported compression method!"),
+        }
+    }
+}
+
+/// Lists the files of a zip archive from its central directory
+pub(crate) fn zip_entries(data: &[u8]) -> Result<Vec<ZipEntry<'_>>, &'static str> {
+    let err = "Not a valid zip file!";
+    // the end of directory record is at the very end, followed by a comment of at most 65535 bytes
+    let end = (0..data.len().saturating_sub(21)).rev().take(65536 + 22).find(|index| u32_at(data, *index) == Some(END_OF_DIRECTORY)).ok_or(err)?;
+    let num_entries = u16_at(data, end + 10).ok_or(err)? as usize;
+    let mut position = u32_at(data, end + 16).ok_or(err)? as usize;
+    let mut entries = Vec::new();
+    for _ in 0..num_entries {
+        if u32_at(data, position) != Some(DIRECTORY_ENTRY) {
+            return Err(err);
+        }
+        let method = u16_at(data, position + 10).ok_or(err)?;
+        let compressed_size = u32_at(data, position + 20).ok_or(err)? as usize;
+        let name_len = u16_at(data, position + 28).ok_or(err)? as usize;
+        let extra_len = u16_at(data, position + 30).ok_or(err)? as usize;
+        let comment_len = u16_at(data, position + 32).ok_or(err)? as usize;
+        let header = u32_at(data, position + 42).ok_or(err)? as usize;
+        let name = data.get(position + 46..position + 46 + name_len).ok_or(err)?;
+        // the local header repeats the name and has an extra field of its own before the data
+        if u32_at(data, header) != Some(LOCAL_HEADER) {
+            return Err(err);
+        }
+        let start = header + 30 + u16_at(data, header + 26).ok_or(err)? as usize + u16_at(data, header + 28).ok_or(err)? as usize;
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            method,
+            compressed: data.get(start..start + compressed_size).ok_or(err)?,
+        });
+        position += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+fn u16_at(data: &[u8], index: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(index..index + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], index: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(index..index + 4)?.try_into().ok()?))
+}
+
+/// Reads bits of a deflate stream, starting from the least significant bit of each byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    byte: usize,
+    bit: u8,
+}
+
+impl BitReader<'_> {
+    fn read_bit(&mut self) -> Result<u16, &'static str> {
+        let byte = *self.data.get(self.byte).ok_or("Compressed data ends early!")?;
+        let bit = (byte >> self.bit) & 1;
+        self.bit += 1;
+        if self.bit == 8 {
+            self.bit = 0;
+            self.byte += 1;
+        }
+        Ok(bit as u16)
+    }
+
+    fn read_bits(&mut self, count: u8) -> Result<u16, &'static str> {
+        let mut value = 0;
+        for bit in 0..count {
+            value |= self.read_bit()? << bit;
+        }
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        if self.bit > 0 {
+            self.bit = 0;
+            self.byte += 1;
+        }
+    }
+}
+
+/// A canonical huffman code given by the code length of every symbol
+struct Huffman {
+    // how many codes there are of each length
+    counts: [u16; 16],
+    // symbols ordered by code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols = Vec::new();
+        for length in 1..16 {
+            for (symbol, _) in lengths.iter().enumerate().filter(|(_, symbol_length)| **symbol_length == length) {
+                symbols.push(symbol as u16);
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> Result<u16, &'static str> {
+        // codes of one length are consecutive and longer codes follow shorter ones
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for length in 1..16 {
+            code |= bits.read_bit()? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("Compressed data has an invalid code!")
+    }
+}
+
+/// Decompresses raw deflate data
+pub(crate) fn inflate(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let err = "Compressed data is damaged!";
+    let mut bits = BitReader { data, byte: 0, bit: 0 };
+    let mut output = Vec::new();
+    loop {
+        let last = bits.read_bit()? == 1;
+        match bits.read_bits(2)? {
+            0 => {
+                bits.align_to_byte();
+                let len = u16_at(data, bits.byte).ok_or(err)? as usize;
+                let start = bits.byte + 4;
+                output.extend_from_slice(data.get(start..start + len).ok_or(err)?);
+                bits.byte = start + len;
+            }
+            1 => {
+                let mut lengths = [8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                inflate_block(&mut bits, &mut output, &Huffman::new(&lengths), &Huffman::new(&[5; 30]))?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, &mut output, &literals, &distances)?;
+            }
+            _ => return Err(err),
+        }
+        if last {
+            return Ok(output);
+        }
+    }
+}
+
+/// Reads the literal and length code and the distance code a dynamic block starts with
+fn read_dynamic_codes(bits: &mut BitReader) -> Result<(Huffman, Huffman), &'static str> {
+    let num_literals = bits.read_bits(5)? as usize + 257;
+    let num_distances = bits.read_bits(5)? as usize + 1;
+    let num_code_lengths = bits.read_bits(4)? as usize + 4;
+    let mut code_lengths = [0; 19];
+    for index in CODE_LENGTH_ORDER.iter().take(num_code_lengths) {
+        code_lengths[*index] = bits.read_bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+    let mut lengths: Vec<u8> = Vec::new();
+    while lengths.len() < num_literals + num_distances {
+        let (length, repeat) = match code_length_code.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or("Compressed data is damaged!")?, 3 + bits.read_bits(2)?),
+            17 => (0, 3 + bits.read_bits(3)?),
+            _ => (0, 11 + bits.read_bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths.len() > num_literals + num_distances {
+        return Err("Compressed data is damaged!");
+    }
+    Ok((Huffman::new(&lengths[..num_literals]), Huffman::new(&lengths[num_literals..])))
+}
+
+/// Decodes literals and back references up to the end of block symbol
+fn inflate_block(bits: &mut BitReader, output: &mut Vec<u8>, literals: &Huffman, distances: &Huffman) -> Result<(), &'static str> {
+    let err = "Compressed data is damaged!";
+    loop {
+        let symbol = literals.decode(bits)? as usize;
+        if symbol < 256 {
+            output.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Ok(());
+        }
+        let index = symbol - 257;
+        let length = *LENGTH_BASE.get(index).ok_or(err)? as usize + bits.read_bits(LENGTH_EXTRA[index])? as usize;
+        let index = distances.decode(bits)? as usize;
+        let distance = *DISTANCE_BASE.get(index).ok_or(err)? as usize + bits.read_bits(DISTANCE_EXTRA[index])? as usize;
+        if distance > output.len() {
+            return Err(err);
+        }
+        // the copy can overlap the bytes it writes
+        let start = output.len() - distance;
+        for offset in 0..length {
+            output.push(output[start + offset]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inflates_stored_and_fixed_blocks() {
+        let stored = [1, 20, 0, 235, 255, 72, 101, 108, 108, 111, 44, 32, 104, 101, 108, 108, 111, 44, 32, 104, 101, 108, 108, 111, 33];
+        let fixed = [243, 72, 205, 201, 201, 215, 81, 200, 64, 162, 20, 1];
+        assert_eq!(inflate(&stored).unwrap(), b"Hello, hello, hello!");
+        assert_eq!(inflate(&fixed).unwrap(), b"Hello, hello, hello!");
+    }
+
+    #[test]
+    fn reads_stored_and_deflated_entries() {
+        // the mimetype is stored and both xml files are deflated with dynamic codes
+        let entries = zip_entries(include_bytes!("../../tests/fixtures/packed.mxl")).unwrap();
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["mimetype", "META-INF/container.xml", "score/packed.musicxml"]);
+        assert_eq!(entries[0].read().unwrap(), b"application/vnd.recordare.musicxml");
+        let score = String::from_utf8(entries[2].read().unwrap()).unwrap();
+        assert!(score.starts_with("<?xml") && score.trim_end().ends_with("</score-partwise>"));
+    }
+
+    #[test]
+    fn truncated_data_is_refused() {
+        let fixed = [243, 72, 205, 201, 201, 215, 81, 200, 64, 162, 20, 1];
+        assert!(inflate(&fixed[..6]).is_err());
+        assert!(zip_entries(&include_bytes!("../../tests/fixtures/packed.mxl")[..400]).is_err());
+    }
+}
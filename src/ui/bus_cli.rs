@@ -0,0 +1,71 @@
+use super::{choice_ui::{self, Choice}, input_ui::{prompt_f32, prompt_index, prompt_line}};
+use crate::music::{Bus, Effect};
+
+pub fn edit_bus_ui(bus: &mut Bus) {
+    let choices = vec![
+        Choice::new("Add Effect".to_string(), Box::from(add_effect_ui)),
+        Choice::new("Delete Effect".to_string(), Box::from(delete_effect_ui)),
+        Choice::new("Change Name".to_string(), Box::from(change_name_ui)),
+    ];
+    loop {
+        println!("Bus editor: Editing {}", bus.name);
+        print!("{bus}");
+        let result = choice_ui::ui_offer_choices(&choices, bus);
+        if let Err(err) = result {
+            println!("{err}");
+            continue
+        }
+        if let Some(res) = result.unwrap() {
+            if let Err(err) = res {
+                println!("{err}");
+            }
+        }
+        else {
+            break
+        }
+    }
+    println!("You have left bus editor!");
+}
+
+fn add_effect_ui(bus: &mut Bus) -> Result<(), &'static str> {
+    println!("Which effect would you like to add?");
+    println!("\t1. Gain\n\t2. Reverb\n\t3. Compressor\n\t4. Equalizer");
+    let effect = match prompt_line("")?.as_str() {
+        "1" => Effect::Gain { gain: prompt_f32("gain in dB: ")? },
+        "2" => Effect::Reverb {
+            room_size: prompt_f32("room size [0, 1]: ")?,
+            damping: prompt_f32("damping [0, 1]: ")?,
+            mix: prompt_f32("wet mix [0, 1]: ")?,
+        },
+        "3" => Effect::Compressor {
+            threshold: prompt_f32("threshold in dB: ")?,
+            ratio: prompt_f32("ratio: ")?,
+            attack: prompt_f32("attack in ms: ")?,
+            release: prompt_f32("release in ms: ")?,
+            makeup_gain: prompt_f32("makeup gain in dB: ")?,
+        },
+        "4" => Effect::Equalizer {
+            low_gain: prompt_f32("low band gain in dB: ")?,
+            mid_gain: prompt_f32("mid band gain in dB: ")?,
+            high_gain: prompt_f32("high band gain in dB: ")?,
+        },
+        _ => return Err("Input not recognised as an effect!"),
+    };
+    println!("Added {effect}!");
+    bus.effects.push(effect);
+    Ok(())
+}
+
+fn delete_effect_ui(bus: &mut Bus) -> Result<(), &'static str> {
+    let index = prompt_index("Select an effect by number: ", bus.effects.len())?;
+    bus.effects.remove(index);
+    println!("Successfully deleted effect!");
+    Ok(())
+}
+
+fn change_name_ui(bus: &mut Bus) -> Result<(), &'static str> {
+    let old_name = bus.name.clone();
+    bus.name = prompt_line("New bus name: ")?;
+    println!("Changed name from {old_name} to {}!", bus.name);
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use super::{choice_ui::{self, Choice}, input_ui::{prompt_envelope, prompt_f32, prompt_index}};
+use crate::music::FmSynth;
+
+pub fn edit_fm_ui(synth: &mut FmSynth) {
+    let choices = vec![
+        Choice::new("Change Algorithm".to_string(), Box::from(change_algorithm_ui)),
+        Choice::new("Edit Operator".to_string(), Box::from(edit_operator_ui)),
+    ];
+    loop {
+        println!("FM editor");
+        print!("{synth}");
+        let result = choice_ui::ui_offer_choices(&choices, synth);
+        if let Err(err) = result {
+            println!("{err}");
+            continue
+        }
+        if let Some(res) = result.unwrap() {
+            if let Err(err) = res {
+                println!("{err}");
+            }
+        }
+        else {
+            break
+        }
+    }
+    println!("You have left FM editor!");
+}
+
+fn change_algorithm_ui(synth: &mut FmSynth) -> Result<(), &'static str> {
+    println!("Algorithms (-> modulates, + mixes):");
+    for algorithm in 0..8 {
+        println!("\t{}. {}", algorithm + 1, FmSynth::describe_algorithm(algorithm));
+    }
+    synth.algorithm = prompt_index("Select an algorithm by number: ", 8)?;
+    println!("Changed algorithm to {}!", FmSynth::describe_algorithm(synth.algorithm));
+    Ok(())
+}
+
+fn edit_operator_ui(synth: &mut FmSynth) -> Result<(), &'static str> {
+    let index = prompt_index("Select an operator by number: ", synth.operators.len())?;
+    let ratio = prompt_f32("frequency ratio: ")?;
+    let modulation_index = prompt_f32("index (modulation depth or output level): ")?;
+    let envelope = prompt_envelope()?;
+    let operator = &mut synth.operators[index];
+    operator.ratio = ratio;
+    operator.index = modulation_index;
+    operator.envelope = envelope;
+    println!("Changed operator {}!", index + 1);
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::io::{self, Write};
+
+use crate::music::{Envelope, Selection};
+
+/// Prints a prompt on the current line and reads the trimmed reply of the user
+pub fn prompt_line(prompt: &str) -> Result<String, &'static str> {
+    print!("{prompt}");
+    if io::stdout().flush().is_err() {
+        return Err("Failed to flush stdout! Exiting!");
+    }
+    let mut buf = String::new();
+    if io::stdin().read_line(&mut buf).is_err() {
+        return Err("Failed to read user input!");
+    }
+    Ok(buf.trim().to_string())
+}
+
+pub fn prompt_f32(prompt: &str) -> Result<f32, &'static str> {
+    match prompt_line(prompt)?.parse::<f32>() {
+        Ok(value) => Ok(value),
+        Err(_) => Err("failed to parse user input as float!")
+    }
+}
+
+/// Asks for a number between 1 and `len` and returns it as a zero based index
+pub fn prompt_index(prompt: &str, len: usize) -> Result<usize, &'static str> {
+    match prompt_line(prompt)?.parse::<usize>() {
+        Ok(number) if number >= 1 && number <= len => Ok(number - 1),
+        _ => Err("Failed to parse user input as an available number!")
+    }
+}
+
+/// Asks for a list of numbers separated by spaces or commas
+pub fn prompt_f32_list(prompt: &str) -> Result<Vec<f32>, &'static str> {
+    let line = prompt_line(prompt)?;
+    let mut values = Vec::new();
+    for value in line.split(|c: char| c == ',' || c.is_whitespace()).filter(|value| !value.is_empty()) {
+        match value.parse::<f32>() {
+            Ok(value) => values.push(value),
+            Err(_) => return Err("failed to parse user input as a list of floats!")
+        }
+    }
+    Ok(values)
+}
+
+pub fn prompt_envelope() -> Result<Envelope, &'static str> {
+    Ok(Envelope::new(
+        prompt_f32("attack in seconds: ")?,
+        prompt_f32("decay in seconds: ")?,
+        prompt_f32("sustain level [0, 1]: ")?,
+        prompt_f32("release in seconds: ")?,
+    ))
+}
+
+/// Asks which notes to work on, like `3-10`, `beats 4-8`, `pitch C4-G4` or a mix of them
+pub fn prompt_selection() -> Result<Selection, &'static str> {
+    println!("Select notes by number like 3-10,12, by start beat like beats 4-8, by pitch like pitch C4-G4,");
+    println!("\tby volume like volume 0-0.5 or by length like length 0.25, combining them with spaces");
+    Selection::parse(&prompt_line("notes (empty for all notes): ")?)
+}
@@ -1,14 +1,15 @@
 use std::io::{self, Write};
 
-use crate::music::Note;
-use super::{choice_ui::{self, Choice}, pitch_ui::select_note_ui};
+use crate::music::{BendPoint, Note};
+use super::{choice_ui::{self, Choice}, input_ui::prompt_line, pitch_ui::select_note_ui};
 
 pub fn edit_note_ui(note: &mut Note) {
     let choices = vec![
         Choice::new("Change Starting Beat".to_string(), Box::from(change_note_start_ui)),
         Choice::new("Change Note Duration".to_string(), Box::from(change_note_duration_ui)),
         Choice::new("Change Note Pitch".to_string(), Box::from(change_note_pitch_ui)),
-        Choice::new("Change Note Volume".to_string(), Box::from(change_note_volume_ui))
+        Choice::new("Change Note Volume".to_string(), Box::from(change_note_volume_ui)),
+        Choice::new("Change Pitch Bend".to_string(), Box::from(change_note_bend_ui))
     ];
     loop {
         println!("Note editor\nNote: {note}");
@@ -107,4 +108,22 @@ pub fn change_note_volume_ui(note: &mut Note) -> Result<(), &'static str>{
             return Err("Failed to parse user input as volume!");
         }
     }
-}
\ No newline at end of file
+}
+
+pub fn change_note_bend_ui(note: &mut Note) -> Result<(), &'static str> {
+    println!("Write the bend as <beats into the note>:<semitones> points e.g. 0:0 0.5:2 1:0, or nothing to remove the bend");
+    let mut bend = Vec::new();
+    for point in prompt_line("bend: ")?.split_whitespace() {
+        let (offset, semitones) = match point.split_once(':') {
+            Some(point) => point,
+            None => return Err("Bend points must be written as <beats>:<semitones>!")
+        };
+        match (offset.parse::<f32>(), semitones.parse::<f32>()) {
+            (Ok(offset), Ok(semitones)) => bend.push(BendPoint { offset, semitones }),
+            _ => return Err("Failed to parse bend point as numbers!")
+        }
+    }
+    note.set_bend(bend);
+    println!("Changed pitch bend!");
+    Ok(())
+}
@@ -1,15 +1,36 @@
 use std::io::{self, Write};
 
 use super::choice_ui::{self, Choice};
-use crate::{music::{Note, Part}, ui::{note_cli, pitch_ui}};
+use crate::{music::{melody_to_text, parse_melody, ArpeggioMode, Arpeggiator, Chord, DrumVoice, Envelope, FmSynth, Instrument, Key, Lfo, LfoShape, LfoTarget, Note, Part, PartFilter, Preset, Scale, StepPattern, Transform, Waveform, WavetableSynth}, ui::{fm_cli, input_ui::{prompt_f32, prompt_index, prompt_line, prompt_selection}, note_cli, pitch_ui, transform_ui, wavetable_cli}};
 
-pub fn edit_part_ui(part: &mut Part) {
+/// Edits a part of a song whose bars hold `bar_beats` beats
+pub fn edit_part_ui(part: &mut Part, bar_beats: f32) {
     let choices = vec![
         Choice::new("Add Note".to_string(), Box::from(add_note_ui)),
+        Choice::new("Enter Step Pattern".to_string(), Box::new(move |part: &mut Part| enter_step_pattern_ui(part, bar_beats))),
+        Choice::new("Show Step Grid".to_string(), Box::new(move |part: &mut Part| show_step_grid_ui(part, bar_beats))),
+        Choice::new("Enter Notes as Text".to_string(), Box::from(enter_notes_as_text_ui)),
+        Choice::new("Show Notes as Text".to_string(), Box::from(show_notes_as_text_ui)),
+        Choice::new("Add Chord".to_string(), Box::from(add_chord_ui)),
+        Choice::new("Add Scale Run".to_string(), Box::from(add_scale_ui)),
         Choice::new("Delete Note".to_string(), Box::from(delete_note_ui)),
+        Choice::new("Delete Notes".to_string(), Box::from(delete_notes_ui)),
+        Choice::new("Move Notes".to_string(), Box::from(move_notes_ui)),
+        Choice::new("Copy Notes".to_string(), Box::from(copy_notes_ui)),
+        Choice::new("Change Volume of Notes".to_string(), Box::from(change_notes_volume_ui)),
+        Choice::new("Transform Notes".to_string(), Box::from(transform_notes_ui)),
+        Choice::new("Arpeggiate Notes".to_string(), Box::from(arpeggiate_notes_ui)),
+        Choice::new("Set Arpeggiator".to_string(), Box::from(set_arpeggiator_ui)),
         Choice::new("Change Name".to_string(), Box::from(change_name_ui)),
         // Todo Change Volume
-        Choice::new("Edit Note".to_string(), Box::from(edit_note_ui))
+        Choice::new("Edit Note".to_string(), Box::from(edit_note_ui)),
+        Choice::new("Change Instrument".to_string(), Box::from(change_instrument_ui)),
+        Choice::new("Edit Instrument".to_string(), Box::from(edit_instrument_ui)),
+        Choice::new("Toggle Legato".to_string(), Box::from(toggle_legato_ui)),
+        Choice::new("Change Glide Time".to_string(), Box::from(change_glide_ui)),
+        Choice::new("Add LFO".to_string(), Box::from(add_lfo_ui)),
+        Choice::new("Delete LFO".to_string(), Box::from(delete_lfo_ui)),
+        Choice::new("Set Filter".to_string(), Box::from(set_filter_ui))
     ];
     loop {
         println!("Part editor: Editing {}", part.name);
@@ -33,13 +54,20 @@ pub fn edit_part_ui(part: &mut Part) {
 
 fn show_notes_ui(part: &Part) {
     println!("Part Name: {}", part.name);
+    print!("Part Instrument: {}", part.instrument);
     println!("Part Notes:");
     for (index, note) in part.notes.iter().enumerate() {
-        println!("{}. {}", index + 1, note);
+        match DrumVoice::from_frequency(note.frequency) {
+            Some(voice) if part.is_percussion() => println!("{}. beat: {}, drum: {voice}, volume: {}", index + 1, note.beat, note.volume),
+            _ => println!("{}. {}", index + 1, note)
+        }
     }
 }
 
 fn add_note_ui(part: &mut Part) -> Result<(), &'static str>{
+    if part.is_percussion() {
+        return add_drum_hit_ui(part);
+    }
     let mut buf = String::new();
     // Get beat to play on from user
     print!("beat to play on: ");
@@ -81,6 +109,114 @@ fn add_note_ui(part: &mut Part) -> Result<(), &'static str>{
     Ok(())
 }
 
+/// Length of the notes of drum hits. Drums always ring out fully so it only decides when the same drum can be hit again
+const DRUM_HIT_BEATS: f32 = 0.25;
+
+fn add_drum_hit_ui(part: &mut Part) -> Result<(), &'static str> {
+    let beat = prompt_f32("beat to play on: ")?;
+    let voice = select_drum_ui()?;
+    let volume = prompt_f32("volume: ")?;
+    let note = Note::new(beat, DRUM_HIT_BEATS, voice.frequency(), volume)?;
+    part.add_note(note)?;
+    println!("Added {voice} hit!");
+    Ok(())
+}
+
+fn select_drum_ui() -> Result<DrumVoice, &'static str> {
+    println!("Drums:");
+    for voice in DrumVoice::ALL {
+        println!("\t{}: {voice}", voice.gm_number());
+    }
+    let drum = prompt_line("drum by General MIDI number or name: ")?;
+    let voice = match drum.parse::<u8>() {
+        Ok(number) => DrumVoice::from_gm(number),
+        Err(_) => DrumVoice::from_name(&drum)
+    };
+    voice.ok_or("Unknown drum!")
+}
+
+fn prompt_step_beats(bar_beats: f32) -> Result<f32, &'static str> {
+    match prompt_line(&format!("steps per bar of {bar_beats} beats (16 for sixteenth notes in 4/4): "))?.parse::<usize>() {
+        Ok(steps) if steps > 0 => Ok(bar_beats / steps as f32),
+        _ => Err("failed to parse user input as a number of steps!")
+    }
+}
+
+fn enter_step_pattern_ui(part: &mut Part, bar_beats: f32) -> Result<(), &'static str> {
+    let step_beats = prompt_step_beats(bar_beats)?;
+    let start_beat = prompt_f32("beat the pattern starts on: ")?;
+    let hit_frequency = if part.is_percussion() {
+        Some(select_drum_ui()?.frequency())
+    } else {
+        None
+    };
+    if hit_frequency.is_some() {
+        println!("Write the pattern as x for a hit, X for an accent, . for a rest and - to hold e.g. x..x..x.x...x...");
+    } else {
+        println!("Write the pattern as note names, . for a rest and - to hold separated by spaces e.g. C4 - E4 . G4 - - .");
+    }
+    let pattern = StepPattern::parse(&prompt_line("pattern: ")?, step_beats)?;
+    let volume = prompt_f32("volume: ")?;
+    pattern.write_into(part, start_beat, hit_frequency, volume)?;
+    println!("Entered {} steps!", pattern.steps.len());
+    Ok(())
+}
+
+fn show_step_grid_ui(part: &mut Part, bar_beats: f32) -> Result<(), &'static str> {
+    let step_beats = prompt_step_beats(bar_beats)?;
+    let num_steps = (part.duration() / step_beats).ceil() as usize;
+    if part.is_percussion() {
+        for voice in DrumVoice::ALL {
+            if part.notes.iter().any(|note| DrumVoice::from_frequency(note.frequency) == Some(voice)) {
+                let pattern = StepPattern::from_notes(&part.notes, 0.0, step_beats, num_steps, Some(voice.frequency()));
+                println!("{:>14}: {pattern}", voice.to_string());
+            }
+        }
+    } else {
+        println!("{}", StepPattern::from_notes(&part.notes, 0.0, step_beats, num_steps, None));
+    }
+    Ok(())
+}
+
+fn enter_notes_as_text_ui(part: &mut Part) -> Result<(), &'static str> {
+    let start_beat = prompt_f32("beat the notes start on: ")?;
+    println!("Write notes as <pitch>/<value> separated by spaces e.g. C4/4 D4/8 E4/8 r/4 G4/2");
+    println!("\tr is a rest, /4. is dotted, /8t is a triplet, ~ ties into the next note, > and < shift the octave of pitches written without one");
+    let notes = parse_melody(&prompt_line("notes: ")?, start_beat, prompt_f32("volume: ")?)?;
+    let num_notes = notes.len();
+    part.add_notes(notes)?;
+    println!("Added {num_notes} notes!");
+    Ok(())
+}
+
+fn show_notes_as_text_ui(part: &mut Part) -> Result<(), &'static str> {
+    println!("{}", melody_to_text(&part.notes, 0.0)?);
+    Ok(())
+}
+
+fn add_chord_ui(part: &mut Part) -> Result<(), &'static str> {
+    let mut chord = Chord::parse(&prompt_line("chord (e.g. C, F#m7, Bbmaj9, Gsus4 or C/E): ")?).ok_or("Chord not recognised!")?;
+    let inversion = prompt_line("inversion (0 for root position, 1 for first inversion..., empty to keep the bass after a slash): ")?;
+    if !inversion.is_empty() {
+        chord = Chord::new(chord.root, chord.quality, inversion.parse::<u8>().map_err(|_| "failed to parse user input as an inversion!")?)?;
+    }
+    let octave = prompt_line("octave of the root (4 holds middle C): ")?.parse::<i32>().map_err(|_| "failed to parse user input as an octave!")?;
+    part.add_chord(&chord, prompt_f32("beat to play on: ")?, prompt_f32("duration in beats: ")?, octave, prompt_f32("volume: ")?)?;
+    println!("Added {chord}!");
+    Ok(())
+}
+
+fn add_scale_ui(part: &mut Part) -> Result<(), &'static str> {
+    let tonic = Key::parse(&prompt_line("tonic (e.g. D or Bb): ")?).ok_or("Tonic not recognised!")?.tonic;
+    println!("Scales: major, natural minor, harmonic minor, melodic minor, dorian, phrygian, lydian, mixolydian, locrian,");
+    println!("\tmajor pentatonic, minor pentatonic, blues, or semitones above the tonic like 0 2 3 7 9");
+    let scale = Scale::parse(&prompt_line("scale: ")?)?;
+    let octave = prompt_line("octave of the tonic (4 holds middle C): ")?.parse::<i32>().map_err(|_| "failed to parse user input as an octave!")?;
+    part.add_scale(&scale, tonic, octave, prompt_f32("beat the run starts on: ")?, prompt_f32("beats per note: ")?, prompt_f32("volume: ")?)?;
+    println!("Added {scale} scale run!");
+    Ok(())
+}
+
 fn delete_note_ui(part: &mut Part) -> Result<(), &'static str> {
     println!("Which note would you like to delete?");
     match select_note_ui(part) {
@@ -93,6 +229,80 @@ fn delete_note_ui(part: &mut Part) -> Result<(), &'static str> {
     }
 }
 
+fn delete_notes_ui(part: &mut Part) -> Result<(), &'static str> {
+    let selection = prompt_selection()?;
+    println!("Deleted {} notes!", part.delete_notes(&selection));
+    Ok(())
+}
+
+fn move_notes_ui(part: &mut Part) -> Result<(), &'static str> {
+    let selection = prompt_selection()?;
+    part.transform(Transform::Shift(prompt_f32("beats to move by (negative moves earlier): ")?), &selection)?;
+    println!("Moved notes!");
+    Ok(())
+}
+
+fn copy_notes_ui(part: &mut Part) -> Result<(), &'static str> {
+    let selection = prompt_selection()?;
+    let copied = part.copy_notes(&selection, prompt_f32("beats after the notes to put the copies: ")?)?;
+    println!("Copied {copied} notes!");
+    Ok(())
+}
+
+fn change_notes_volume_ui(part: &mut Part) -> Result<(), &'static str> {
+    let selection = prompt_selection()?;
+    part.transform(Transform::SetVolume(prompt_f32("volume [0, 1]: ")?), &selection)?;
+    println!("Changed volume of notes!");
+    Ok(())
+}
+
+fn transform_notes_ui(part: &mut Part) -> Result<(), &'static str> {
+    let selection = prompt_selection()?;
+    let transform = transform_ui::prompt_transform(None)?;
+    part.transform(transform, &selection)?;
+    println!("Transformed notes!");
+    Ok(())
+}
+
+fn arpeggiate_notes_ui(part: &mut Part) -> Result<(), &'static str> {
+    let selection = prompt_selection()?;
+    let arpeggiator = prompt_arpeggiator()?;
+    let num_notes = part.arpeggiate(&arpeggiator, &selection)?;
+    println!("Arpeggiated the notes into {num_notes} notes!");
+    Ok(())
+}
+
+fn set_arpeggiator_ui(part: &mut Part) -> Result<(), &'static str> {
+    println!("The arpeggiator plays the notes when the song is rendered and leaves the notes as they are");
+    if part.arpeggiator.is_some() && prompt_line("Remove the arpeggiator? (y/n): ")?.to_lowercase() == "y" {
+        part.set_arpeggiator(None)?;
+        println!("Removed arpeggiator!");
+        return Ok(());
+    }
+    part.set_arpeggiator(Some(prompt_arpeggiator()?))?;
+    println!("Set arpeggiator!");
+    Ok(())
+}
+
+fn prompt_arpeggiator() -> Result<Arpeggiator, &'static str> {
+    let modes = [ArpeggioMode::Up, ArpeggioMode::Down, ArpeggioMode::UpDown, ArpeggioMode::Random, ArpeggioMode::AsPlayed];
+    println!("Arpeggio modes:");
+    for (index, mode) in modes.iter().enumerate() {
+        println!("\t{}. {}", index + 1, mode.name());
+    }
+    let mode = modes[prompt_index("Select a mode by number: ", modes.len())?];
+    let rate = prompt_f32("beats per step (0.25 for sixteenth notes): ")?;
+    let gate = prompt_f32("gate in percent of a step: ")? / 100.0;
+    let octaves = prompt_line("octave range (1 to 4): ")?.parse::<u8>().map_err(|_| "failed to parse user input as a whole number!")?;
+    let seed = match mode {
+        ArpeggioMode::Random => prompt_line("seed (the same seed gives the same result): ")?.parse::<u64>().map_err(|_| "failed to parse user input as a whole number!")?,
+        _ => 0,
+    };
+    let arpeggiator = Arpeggiator { mode, rate, gate, octaves, seed };
+    arpeggiator.check()?;
+    Ok(arpeggiator)
+}
+
 fn change_name_ui(part: &mut Part) -> Result<(), &'static str> {
     // Get new Part name from user
     print!("New part name: ");
@@ -123,6 +333,119 @@ fn edit_note_ui(part: &mut Part) -> Result<(), &'static str> {
     }
 }
 
+fn change_instrument_ui(part: &mut Part) -> Result<(), &'static str> {
+    let presets = Preset::built_in();
+    println!("Which instrument should the part play?");
+    for (index, waveform) in Waveform::ALL.iter().enumerate() {
+        println!("\t{}. {waveform} oscillator", index + 1);
+    }
+    let num_waveforms = Waveform::ALL.len();
+    println!("\t{}. FM (blank)", num_waveforms + 1);
+    println!("\t{}. Additive (enter harmonics)", num_waveforms + 2);
+    println!("\t{}. Wavetable (draw a waveform)", num_waveforms + 3);
+    println!("\t{}. Drum kit", num_waveforms + 4);
+    for (index, preset) in presets.iter().enumerate() {
+        println!("\t{}. {} preset: {}", index + num_waveforms + 5, preset.instrument.kind_name(), preset.name);
+    }
+    let choice = prompt_index("Select an instrument by number: ", presets.len() + num_waveforms + 4)?;
+    part.instrument = if choice < num_waveforms {
+        Instrument::Oscillator(Waveform::ALL[choice])
+    } else {
+        match choice - num_waveforms {
+            0 => Instrument::Fm(FmSynth::default()),
+            1 => Instrument::Wavetable(WavetableSynth::new(vec![wavetable_cli::prompt_harmonics()?], 0.0, Envelope::default())?),
+            2 => Instrument::Wavetable(WavetableSynth::new(vec![wavetable_cli::prompt_drawn_frame()?], 0.0, Envelope::default())?),
+            3 => Instrument::Drums,
+            index => presets[index - 4].instrument.clone()
+        }
+    };
+    println!("Changed instrument to {}!", part.instrument.kind_name());
+    Ok(())
+}
+
+fn edit_instrument_ui(part: &mut Part) -> Result<(), &'static str> {
+    match &mut part.instrument {
+        Instrument::Oscillator(_) => Err("Oscillators have nothing to edit! Change the instrument to pick another waveform"),
+        Instrument::Fm(synth) => {
+            fm_cli::edit_fm_ui(synth);
+            Ok(())
+        }
+        Instrument::Wavetable(synth) => {
+            wavetable_cli::edit_wavetable_ui(synth);
+            Ok(())
+        }
+        Instrument::Drums => Err("Drum kits have nothing to edit! Add notes to pick drums")
+    }
+}
+
+fn toggle_legato_ui(part: &mut Part) -> Result<(), &'static str> {
+    part.legato = !part.legato;
+    if part.legato {
+        println!("Legato on! Notes that start as the previous note ends continue its waveform");
+    } else {
+        println!("Legato off! Every note restarts its waveform");
+    }
+    Ok(())
+}
+
+fn change_glide_ui(part: &mut Part) -> Result<(), &'static str> {
+    println!("Notes only glide into notes they run straight into while legato is on");
+    let glide = prompt_f32("Seconds to slide between notes (0 turns glide off): ")?;
+    if glide < 0.0 {
+        return Err("Glide time can't be negative!");
+    }
+    part.glide = glide;
+    println!("Changed glide time to {}s!", part.glide);
+    if part.glide > 0.0 && !part.legato {
+        println!("Legato is off so the part won't glide until it is turned on!");
+    }
+    Ok(())
+}
+
+fn add_lfo_ui(part: &mut Part) -> Result<(), &'static str> {
+    println!("LFO shape:\n\t1. Sine\n\t2. Triangle\n\t3. Square\n\t4. Random");
+    let shape = [LfoShape::Sine, LfoShape::Triangle, LfoShape::Square, LfoShape::Random][prompt_index("Select a shape by number: ", 4)?];
+    println!("LFO target:\n\t1. Pitch (vibrato, depth in semitones)\n\t2. Amplitude (tremolo, depth in range [0, 1])\n\t3. Filter cutoff (depth in octaves)");
+    let target = [LfoTarget::Pitch, LfoTarget::Amplitude, LfoTarget::FilterCutoff][prompt_index("Select a target by number: ", 3)?];
+    let lfo = Lfo::new(
+        shape,
+        target,
+        prompt_f32("rate in Hz: ")?,
+        prompt_f32("depth: ")?,
+        prompt_f32("delay in seconds: ")?,
+        prompt_f32("fade in seconds: ")?
+    );
+    if target == LfoTarget::FilterCutoff && part.filter.is_none() {
+        println!("The part has no filter yet so the LFO won't be heard until one is set!");
+    }
+    println!("Added {lfo}!");
+    part.lfos.push(lfo);
+    Ok(())
+}
+
+fn delete_lfo_ui(part: &mut Part) -> Result<(), &'static str> {
+    for (index, lfo) in part.lfos.iter().enumerate() {
+        println!("\t{}. {lfo}", index + 1);
+    }
+    let index = prompt_index("Select an LFO by number: ", part.lfos.len())?;
+    part.lfos.remove(index);
+    println!("Deleted LFO!");
+    Ok(())
+}
+
+fn set_filter_ui(part: &mut Part) -> Result<(), &'static str> {
+    let cutoff = prompt_f32("Low pass cutoff in Hz (0 removes the filter): ")?;
+    if cutoff <= 0.0 {
+        part.filter = None;
+        println!("Removed filter!");
+        return Ok(());
+    }
+    let resonance = prompt_f32("Resonance (0.707 for none): ")?;
+    part.filter = Some(PartFilter { cutoff, resonance });
+    println!("Set filter!");
+    Ok(())
+}
+
 fn select_note_ui<'a>(part: &'a mut Part) -> Result<(usize, &'a mut Note), &'static str> {
     print!("Select a note by number: ");
     io::stdout().flush().expect("Stdout failed to flush! Exiting!");
@@ -1,18 +1,46 @@
 use std::io::{self, Write};
 
-use crate::{music::{Part, Song}, ui::choice_ui::{self, Choice}, wav::WavOptions};
+use crate::{music::{parse_melody, parse_progression, Accompaniment, AccompanimentStyle, Bus, Instrument, Key, Part, Pattern, Preset, Song, TimeSignature}, ui::choice_ui::{self, Choice}, wav::WavOptions};
 
-use super::part_cli;
+use super::{bus_cli, input_ui::{prompt_f32, prompt_f32_list, prompt_index, prompt_line, prompt_selection}, part_cli, transform_ui};
 
 pub fn edit_song_ui(song: &mut Song) {
     let choices = vec![
         Choice::new("Export Song to .wav".to_string(), Box::from(compile_song_ui)),
+        Choice::new("Export Song to .wav with Oversampling".to_string(), Box::from(compile_song_oversampled_ui)),
+        Choice::new("Export Section to .wav".to_string(), Box::from(compile_section_ui)),
+        Choice::new("Export Song to .abc".to_string(), Box::from(export_abc_ui)),
+        Choice::new("Export Song to MusicXML".to_string(), Box::from(export_musicxml_ui)),
+        Choice::new("Export Song to LilyPond".to_string(), Box::from(export_lilypond_ui)),
         Choice::new("Save Song".to_string(), Box::from(save_song_ui)),
         Choice::new("Add Part".to_string(), Box::from(add_part_ui)),
+        Choice::new("Add Accompaniment Part from Chords".to_string(), Box::from(add_accompaniment_ui)),
         Choice::new("Delete Part".to_string(), Box::from(delete_part_ui)),
+        Choice::new("Duplicate Part".to_string(), Box::from(duplicate_part_ui)),
         Choice::new("Edit Part".to_string(), Box::from(edit_part_ui)),
         Choice::new("Change Name".to_string(), Box::from(change_name_ui)),
         Choice::new("Change BPM(Beats Per Minute)".to_string(), Box::from(change_bpm_ui)),
+        Choice::new("Change Time Signature".to_string(), Box::from(change_time_signature_ui)),
+        Choice::new("Change Key".to_string(), Box::from(change_key_ui)),
+        Choice::new("Analyze Key and Chords".to_string(), Box::from(analyze_ui)),
+        Choice::new("Check Part Writing".to_string(), Box::from(check_part_writing_ui)),
+        Choice::new("Transform Notes of All Parts".to_string(), Box::from(transform_notes_ui)),
+        Choice::new("Create Pattern from Part Notes".to_string(), Box::from(pattern_from_part_ui)),
+        Choice::new("Create Pattern from Melody".to_string(), Box::from(pattern_from_melody_ui)),
+        Choice::new("Place Pattern in Part".to_string(), Box::from(place_pattern_ui)),
+        Choice::new("Remove Pattern from Part".to_string(), Box::from(remove_pattern_instance_ui)),
+        Choice::new("Delete Pattern".to_string(), Box::from(delete_pattern_ui)),
+        Choice::new("Add Section Marker".to_string(), Box::from(add_marker_ui)),
+        Choice::new("Delete Section Marker".to_string(), Box::from(delete_marker_ui)),
+        Choice::new("Set Arrangement".to_string(), Box::from(set_arrangement_ui)),
+        Choice::new("Add Aux Bus".to_string(), Box::from(add_aux_bus_ui)),
+        Choice::new("Delete Aux Bus".to_string(), Box::from(delete_aux_bus_ui)),
+        Choice::new("Edit Aux Bus".to_string(), Box::from(edit_aux_bus_ui)),
+        Choice::new("Edit Master Bus".to_string(), Box::from(edit_master_bus_ui)),
+        Choice::new("Set Part Send Level".to_string(), Box::from(set_send_ui)),
+        Choice::new("Save Part Instrument as Preset".to_string(), Box::from(save_preset_ui)),
+        Choice::new("Apply Preset to Part".to_string(), Box::from(apply_preset_ui)),
+        Choice::new("Delete Preset".to_string(), Box::from(delete_preset_ui)),
     ];
     loop {
         println!("Song editor: Editing {}", song.name);
@@ -36,10 +64,52 @@ pub fn edit_song_ui(song: &mut Song) {
 
 fn show_parts_ui(song: &Song) {
     println!("Song Name: {}", song.name);
+    println!("Time Signature: {}, Key: {}", song.time_signature, song.key);
     println!("Song Parts:");
     for (index, part) in song.parts.iter().enumerate() {
         println!("\t{}. {}", index + 1, part.name);
+        if !song.markers.is_empty() {
+            let notes = part.with_patterns(&song.patterns).notes;
+            let sections: Vec<&str> = (0..song.markers.len())
+                .filter(|section| {
+                    let (start, end) = song.section_span(*section).unwrap_or_default();
+                    notes.iter().any(|note| note.beat >= start && note.beat < end)
+                })
+                .map(|section| song.markers[section].name.as_str())
+                .collect();
+            println!("\t\tplays in sections: {}", if sections.is_empty() { "none".to_string() } else { sections.join(", ") });
+        }
+        for send in &part.sends {
+            if let Some(bus) = song.aux_buses.get(send.bus) {
+                println!("\t\tsends {} to {}", send.level, bus.name);
+            }
+        }
+        for instance in &part.pattern_instances {
+            if let Some(pattern) = song.patterns.get(instance.pattern) {
+                print!("\t\tplays {} at beat {}", pattern.name, instance.beat);
+                if instance.transpose != 0.0 {
+                    print!(" moved {} semitones", instance.transpose);
+                }
+                println!();
+            }
+        }
+    }
+    if !song.patterns.is_empty() {
+        println!("Patterns:");
+        show_patterns_ui(song);
+    }
+    if !song.markers.is_empty() {
+        println!("Sections:");
+        show_sections_ui(song);
+        if !song.arrangement.is_empty() {
+            println!("Arrangement: {}", song.arrangement_text());
+        }
+    }
+    println!("Aux Buses:");
+    for (index, bus) in song.aux_buses.iter().enumerate() {
+        println!("\t{}. {} ({} effects)", index + 1, bus.name, bus.effects.len());
     }
+    println!("Master Bus: {} effects", song.master_bus.effects.len());
 }
 
 fn compile_song_ui(song: &mut Song) -> Result<(), &'static str> {
@@ -50,6 +120,45 @@ fn compile_song_ui(song: &mut Song) -> Result<(), &'static str> {
     result
 }
 
+fn export_abc_ui(song: &mut Song) -> Result<(), &'static str> {
+    song.write_to_abc_file(song.name.clone())?;
+    println!("Exported ABC tune!");
+    Ok(())
+}
+
+fn export_musicxml_ui(song: &mut Song) -> Result<(), &'static str> {
+    song.write_to_musicxml_file(song.name.clone())?;
+    println!("Exported MusicXML score!");
+    Ok(())
+}
+
+fn export_lilypond_ui(song: &mut Song) -> Result<(), &'static str> {
+    song.write_to_lilypond_file(song.name.clone())?;
+    println!("Exported LilyPond score!");
+    Ok(())
+}
+
+fn compile_section_ui(song: &mut Song) -> Result<(), &'static str> {
+    show_sections_ui(song);
+    let section = song.section(prompt_index("Select a section by number: ", song.markers.len())?)?;
+    println!("Compiling section...");
+    let result = section.write_to_wav_file(section.name.clone(), &WavOptions::default());
+    println!("Compilation complete!");
+    result
+}
+
+fn compile_song_oversampled_ui(song: &mut Song) -> Result<(), &'static str> {
+    let oversampling = match prompt_line("Oversampling factor (e.g. 2 or 4): ")?.parse::<u32>() {
+        Ok(factor) if (1..=16).contains(&factor) => factor,
+        _ => return Err("Oversampling factor must be an integer in range [1, 16]!")
+    };
+    println!("Compiling song at {oversampling}x...");
+    let options = WavOptions { oversampling, ..WavOptions::default() };
+    let result = song.write_to_wav_file(song.name.clone(), &options);
+    println!("Compilation complete!");
+    result
+}
+
 fn save_song_ui(song: &mut Song) -> Result<(), &'static str>{
     println!("Saving song...");
     match song.write_to_song_file(song.name.clone()) {
@@ -71,12 +180,32 @@ fn add_part_ui(song: &mut Song) -> Result<(), &'static str> {
     if let Err(_) = io::stdin().read_line(&mut part_name) {
         return Err("Failed to read user input!");
     }
-    let part = Part::new(part_name.trim().to_string());
+    let mut part = Part::new(part_name.trim().to_string());
+    println!("Part kind:\n\t1. Melodic\n\t2. Drums");
+    if prompt_index("Select a kind by number: ", 2)? == 1 {
+        part.instrument = Instrument::Drums;
+    }
     song.parts.push(part);
     println!("Added part!");
     Ok(())
 }
 
+fn add_accompaniment_ui(song: &mut Song) -> Result<(), &'static str> {
+    println!("Write chords as Roman numerals in {} like I vi IV V7, or as chord symbols like Cmaj7 Am7 Dm7 G7", song.key);
+    println!("\t- holds the previous chord for another chord length and | can be written between bars");
+    let text = prompt_line("chords: ")?;
+    let progression = parse_progression(&text, song.key, prompt_f32("beats per chord: ")?)?;
+    println!("Styles:\n\t1. Block chords\n\t2. Arpeggio\n\t3. Alberti bass");
+    let style = [AccompanimentStyle::Block, AccompanimentStyle::Arpeggio, AccompanimentStyle::Alberti][prompt_index("Select a style by number: ", 3)?];
+    let rhythm = prompt_f32("beats between hits or notes: ")?;
+    let octave = prompt_line("octave of the first chord (4 holds middle C): ")?.parse::<i32>().map_err(|_| "failed to parse user input as an octave!")?;
+    let accompaniment = Accompaniment { style, rhythm, octave, volume: prompt_f32("volume: ")? };
+    let part = accompaniment.part(prompt_line("Part Name: ")?, &progression, prompt_f32("beat the chords start on: ")?)?;
+    song.parts.push(part);
+    println!("Added accompaniment part with {} chords!", progression.len());
+    Ok(())
+}
+
 fn delete_part_ui(song: &mut Song) -> Result<(), &'static str>{
     println!("Which part would you like to delete?");
     match select_part_ui(song) {
@@ -88,12 +217,21 @@ fn delete_part_ui(song: &mut Song) -> Result<(), &'static str>{
     }
 }
 
+fn duplicate_part_ui(song: &mut Song) -> Result<(), &'static str> {
+    println!("Which part would you like to duplicate?");
+    let (index, _) = select_part_ui(song)?;
+    song.duplicate_part(index)?;
+    println!("Duplicated part!");
+    Ok(())
+}
+
 fn edit_part_ui(song: &mut Song) -> Result<(), &'static str>{
     //  user is presented with options to edit part
     println!("Which part would you like to edit?");
+    let bar_beats = song.time_signature.bar_beats();
     match select_part_ui(song) {
         Ok((_index, part)) => {
-            part_cli::edit_part_ui(part);
+            part_cli::edit_part_ui(part, bar_beats);
             println!("Done editing Song!");
             Ok(())
         },
@@ -140,7 +278,188 @@ fn change_bpm_ui(song: &mut Song) -> Result<(), &'static str> {
     }
 }
 
-fn select_part_ui<'a>(song: &'a mut Song) -> Result<(usize, &'a mut Part), &'static str> {
+fn transform_notes_ui(song: &mut Song) -> Result<(), &'static str> {
+    let selection = prompt_selection()?;
+    let transform = transform_ui::prompt_transform(Some(song.key))?;
+    song.transform(transform, &selection)?;
+    println!("Transformed notes of all parts!");
+    Ok(())
+}
+
+fn show_patterns_ui(song: &Song) {
+    for (index, pattern) in song.patterns.iter().enumerate() {
+        println!("\t{}. {} ({} notes, {} beats)", index + 1, pattern.name, pattern.notes.len(), pattern.duration());
+    }
+}
+
+fn pattern_from_part_ui(song: &mut Song) -> Result<(), &'static str> {
+    println!("Which part has the notes of the pattern?");
+    let (index, part) = select_part_ui(song)?;
+    let selection = prompt_selection()?;
+    let notes: Vec<_> = selection.indices_in(&part.notes).into_iter().map(|index| part.notes[index].clone()).collect();
+    let first_beat = notes.iter().map(|note| note.beat).fold(f32::INFINITY, f32::min);
+    let pattern = Pattern::new(prompt_line("Pattern Name: ")?, notes)?;
+    song.patterns.push(pattern);
+    println!("Created pattern!");
+    // the notes can be swapped for the pattern so later changes to the pattern change them too
+    if prompt_line("Play the pattern in place of the notes? (y/n): ")?.to_lowercase() == "y" {
+        song.parts[index].delete_notes(&selection);
+        song.place_pattern(index, song.patterns.len() - 1, first_beat, 0.0)?;
+        println!("Replaced the notes with the pattern!");
+    }
+    Ok(())
+}
+
+fn pattern_from_melody_ui(song: &mut Song) -> Result<(), &'static str> {
+    let name = prompt_line("Pattern Name: ")?;
+    println!("Write notes as <pitch>/<value> separated by spaces e.g. C4/4 D4/8 E4/8 r/4 G4/2");
+    println!("\tr is a rest, /4. is dotted, /8t is a triplet, ~ ties into the next note, > and < shift the octave of pitches written without one");
+    let notes = parse_melody(&prompt_line("notes: ")?, 0.0, prompt_f32("volume: ")?)?;
+    song.patterns.push(Pattern::new(name, notes)?);
+    println!("Created pattern!");
+    Ok(())
+}
+
+fn place_pattern_ui(song: &mut Song) -> Result<(), &'static str> {
+    show_patterns_ui(song);
+    let pattern = prompt_index("Select a pattern by number: ", song.patterns.len())?;
+    println!("Which part should play the pattern?");
+    let (index, _) = select_part_ui(song)?;
+    let beats = prompt_f32_list("beats to start the pattern on, separated by spaces: ")?;
+    let transpose = prompt_f32("semitones to move the pattern up or down (0 for none): ")?;
+    for beat in &beats {
+        song.place_pattern(index, pattern, *beat, transpose)?;
+    }
+    println!("Placed pattern {} times!", beats.len());
+    Ok(())
+}
+
+fn remove_pattern_instance_ui(song: &mut Song) -> Result<(), &'static str> {
+    println!("Which part should stop playing a pattern?");
+    let (_, part) = select_part_ui(song)?;
+    for (index, instance) in part.pattern_instances.iter().enumerate() {
+        println!("\t{}. pattern {} at beat {}", index + 1, instance.pattern + 1, instance.beat);
+    }
+    let index = prompt_index("Select a pattern instance by number: ", part.pattern_instances.len())?;
+    part.pattern_instances.remove(index);
+    println!("Removed pattern from part!");
+    Ok(())
+}
+
+fn delete_pattern_ui(song: &mut Song) -> Result<(), &'static str> {
+    show_patterns_ui(song);
+    let index = prompt_index("Select a pattern by number: ", song.patterns.len())?;
+    song.delete_pattern(index);
+    println!("Deleted pattern and every part's instances of it!");
+    Ok(())
+}
+
+fn show_sections_ui(song: &Song) {
+    for index in 0..song.markers.len() {
+        let (start, end) = song.section_span(index).unwrap_or_default();
+        println!("\t{}. {} (beats {start} to {end})", index + 1, song.markers[index].name);
+    }
+}
+
+fn add_marker_ui(song: &mut Song) -> Result<(), &'static str> {
+    let name = prompt_line("Section name without spaces (e.g. Verse or A): ")?;
+    let beat = prompt_f32("beat the section starts on: ")?;
+    song.add_marker(name, beat)?;
+    println!("Added section marker!");
+    Ok(())
+}
+
+fn delete_marker_ui(song: &mut Song) -> Result<(), &'static str> {
+    show_sections_ui(song);
+    let index = prompt_index("Select a section by number: ", song.markers.len())?;
+    song.delete_marker(index);
+    println!("Deleted section marker and removed its section from the arrangement!");
+    Ok(())
+}
+
+fn set_arrangement_ui(song: &mut Song) -> Result<(), &'static str> {
+    show_sections_ui(song);
+    println!("Write section names in the order they play, like Intro Verse Chorus Verse Chorus*2");
+    song.set_arrangement(&prompt_line("arrangement (empty plays the song as written): ")?)?;
+    println!("Set arrangement!");
+    Ok(())
+}
+
+fn add_aux_bus_ui(song: &mut Song) -> Result<(), &'static str> {
+    let name = prompt_line("Bus Name: ")?;
+    song.aux_buses.push(Bus::new(name));
+    println!("Added aux bus!");
+    Ok(())
+}
+
+fn delete_aux_bus_ui(song: &mut Song) -> Result<(), &'static str> {
+    println!("Which aux bus would you like to delete?");
+    let index = prompt_index("Select a bus by number: ", song.aux_buses.len())?;
+    song.remove_aux_bus(index);
+    println!("Deleted aux bus and every send to it!");
+    Ok(())
+}
+
+fn edit_aux_bus_ui(song: &mut Song) -> Result<(), &'static str> {
+    println!("Which aux bus would you like to edit?");
+    let index = prompt_index("Select a bus by number: ", song.aux_buses.len())?;
+    bus_cli::edit_bus_ui(&mut song.aux_buses[index]);
+    Ok(())
+}
+
+fn edit_master_bus_ui(song: &mut Song) -> Result<(), &'static str> {
+    bus_cli::edit_bus_ui(&mut song.master_bus);
+    Ok(())
+}
+
+fn set_send_ui(song: &mut Song) -> Result<(), &'static str> {
+    println!("Which aux bus should the part send to?");
+    let bus = prompt_index("Select a bus by number: ", song.aux_buses.len())?;
+    println!("Which part should send to the bus?");
+    let (_index, part) = select_part_ui(song)?;
+    let level = prompt_f32("Send level (0 removes the send): ")?;
+    part.set_send(bus, level);
+    println!("Set send level!");
+    Ok(())
+}
+
+fn save_preset_ui(song: &mut Song) -> Result<(), &'static str> {
+    println!("Which part's instrument would you like to save?");
+    let (_index, part) = select_part_ui(song)?;
+    let instrument = part.instrument.clone();
+    let name = prompt_line("Preset Name: ")?;
+    song.presets.retain(|preset| preset.name != name);
+    song.presets.push(Preset::new(name, instrument));
+    println!("Saved preset!");
+    Ok(())
+}
+
+fn apply_preset_ui(song: &mut Song) -> Result<(), &'static str> {
+    let mut presets = song.presets.clone();
+    presets.extend(Preset::built_in());
+    println!("Presets:");
+    for (index, preset) in presets.iter().enumerate() {
+        println!("\t{}. {} ({})", index + 1, preset.name, preset.instrument.kind_name());
+    }
+    let preset = prompt_index("Select a preset by number: ", presets.len())?;
+    println!("Which part should use the preset?");
+    let (_index, part) = select_part_ui(song)?;
+    part.instrument = presets[preset].instrument.clone();
+    println!("Applied preset {}!", presets[preset].name);
+    Ok(())
+}
+
+fn delete_preset_ui(song: &mut Song) -> Result<(), &'static str> {
+    for (index, preset) in song.presets.iter().enumerate() {
+        println!("\t{}. {}", index + 1, preset.name);
+    }
+    let index = prompt_index("Select a preset by number: ", song.presets.len())?;
+    song.presets.remove(index);
+    println!("Deleted preset!");
+    Ok(())
+}
+
+pub fn select_part_ui(song: &mut Song) -> Result<(usize, &mut Part), &'static str> {
     print!("Select a part by name or number: ");
     io::stdout().flush().expect("Stdout failed to flush! Exiting!");
     let mut buf = String::new();
@@ -170,4 +489,45 @@ fn select_part_ui<'a>(song: &'a mut Song) -> Result<(usize, &'a mut Part), &'sta
             Err("Failed to parse index as part or a part name!")
         }
     }
-}
\ No newline at end of file
+}
+fn change_time_signature_ui(song: &mut Song) -> Result<(), &'static str> {
+    let line = prompt_line("New time signature (e.g. 3/4 or 6/8): ")?;
+    let (beats, beat_unit) = line.split_once('/').ok_or("Time signature must be written like 3/4!")?;
+    match (beats.trim().parse::<u8>(), beat_unit.trim().parse::<u8>()) {
+        (Ok(beats), Ok(beat_unit)) => song.time_signature = TimeSignature::new(beats, beat_unit)?,
+        _ => return Err("Time signature must be written like 3/4!")
+    }
+    println!("Changed time signature to {}!", song.time_signature);
+    Ok(())
+}
+
+fn change_key_ui(song: &mut Song) -> Result<(), &'static str> {
+    song.key = Key::parse(&prompt_line("New key (e.g. G, F#m or Bb minor): ")?).ok_or("Key not recognised!")?;
+    println!("Changed key to {}!", song.key);
+    Ok(())
+}
+
+fn analyze_ui(song: &mut Song) -> Result<(), &'static str> {
+    let window = prompt_line("beats per chord (empty for one bar): ")?;
+    let window_beats = if window.is_empty() { song.time_signature.bar_beats() } else { window.parse::<f32>().map_err(|_| "failed to parse user input as float!")? };
+    let analysis = song.analyze(window_beats)?;
+    println!("{analysis}");
+    if analysis.key() != song.key && prompt_line(&format!("Change the song's key from {} to {}? (y/n): ", song.key, analysis.key()))?.to_lowercase() == "y" {
+        song.key = analysis.key();
+        println!("Changed key to {}!", song.key);
+    }
+    Ok(())
+}
+
+fn check_part_writing_ui(song: &mut Song) -> Result<(), &'static str> {
+    let issues = song.check_part_writing();
+    if issues.is_empty() {
+        println!("No part writing problems found!");
+        return Ok(());
+    }
+    println!("Found {} part writing problems:", issues.len());
+    for issue in &issues {
+        println!("\t{issue}");
+    }
+    Ok(())
+}
@@ -1,15 +1,20 @@
 use std::{fs::File, io::{self, BufReader, Read, Write}};
 use rfd::FileDialog;
 
-use crate::music::{Serializable, Song, SongEditor};
-use super::{choice_ui::{self, Choice}, song_cli};
+use crate::music::{song_from_abc, song_from_musicxml, song_from_mxl, Clipboard, Serializable, Song, SongEditor};
+use super::{choice_ui::{self, Choice}, input_ui::{prompt_f32, prompt_selection}, song_cli};
 
 pub fn ui(editor: &mut SongEditor) {
     let choices = vec![
         Choice::new("Load Song".to_string(), Box::from(load_song_ui)),
+        Choice::new("Import ABC Tune".to_string(), Box::from(import_abc_ui)),
+        Choice::new("Import MusicXML Score".to_string(), Box::from(import_musicxml_ui)),
         Choice::new("Add Song".to_string(), Box::from(add_song_ui)),
         Choice::new("Delete Song".to_string(), Box::from(delete_song_ui)),
         Choice::new("Edit Song".to_string(), Box::from(edit_song_ui)),
+        Choice::new("Copy Notes".to_string(), Box::from(copy_notes_ui)),
+        Choice::new("Copy Part".to_string(), Box::from(copy_part_ui)),
+        Choice::new("Paste".to_string(), Box::from(paste_ui)),
     ];
     println!("Hello! Welcome to Song Maker!");
     loop {
@@ -126,6 +131,47 @@ fn load_song_ui(editor: &mut SongEditor) -> Result<(), &'static str> {
     }
 }
 
+fn import_abc_ui(editor: &mut SongEditor) -> Result<(), &'static str> {
+    println!("Select a .abc file to import");
+    let file_path = FileDialog::new()
+        .add_filter("abc tunes", &["abc"])
+        .set_directory("/")
+        .pick_file()
+        .ok_or("No files selected!")?;
+    let mut file = File::open(&file_path).map_err(|_| "failed to open file")?;
+    let mut data = Vec::new();
+    if file.read_to_end(&mut data).is_err() {
+        return Err("could not read from file!");
+    }
+    let song = song_from_abc(&String::from_utf8_lossy(&data))?;
+    println!("Imported {} with {} parts!", song.name, song.parts.len());
+    editor.loaded_songs.push(song);
+    Ok(())
+}
+
+fn import_musicxml_ui(editor: &mut SongEditor) -> Result<(), &'static str> {
+    println!("Select a .musicxml, .xml or .mxl file to import");
+    let file_path = FileDialog::new()
+        .add_filter("MusicXML scores", &["musicxml", "xml", "mxl"])
+        .set_directory("/")
+        .pick_file()
+        .ok_or("No files selected!")?;
+    let mut file = File::open(&file_path).map_err(|_| "failed to open file")?;
+    let mut data = Vec::new();
+    if file.read_to_end(&mut data).is_err() {
+        return Err("could not read from file!");
+    }
+    // compressed scores are zip archives
+    let song = if data.starts_with(b"PK") {
+        song_from_mxl(&data)?
+    } else {
+        song_from_musicxml(&String::from_utf8_lossy(&data))?
+    };
+    println!("Imported {} with {} parts!", song.name, song.parts.len());
+    editor.loaded_songs.push(song);
+    Ok(())
+}
+
 fn edit_song_ui(editor: &mut SongEditor) -> Result<(), &'static str> {
     // User selects song to edit
     println!("Which song would you like to edit?");
@@ -139,11 +185,58 @@ fn edit_song_ui(editor: &mut SongEditor) -> Result<(), &'static str> {
     }
 }
 
+fn copy_notes_ui(editor: &mut SongEditor) -> Result<(), &'static str> {
+    println!("Which song would you like to copy notes from?");
+    let (_, song) = select_song_ui(editor)?;
+    println!("Which part would you like to copy notes from?");
+    let (_, part) = song_cli::select_part_ui(song)?;
+    let clipboard = Clipboard::copy_notes(part, &prompt_selection()?)?;
+    editor.clipboard = clipboard;
+    println!("Copied notes!");
+    Ok(())
+}
+
+fn copy_part_ui(editor: &mut SongEditor) -> Result<(), &'static str> {
+    println!("Which song would you like to copy a part from?");
+    let (_, song) = select_song_ui(editor)?;
+    println!("Which part would you like to copy?");
+    let (index, _) = song_cli::select_part_ui(song)?;
+    // patterns belong to the song so they are copied as notes
+    let clipboard = Clipboard::copy_parts(&[song.parts[index].with_patterns(&song.patterns)]);
+    editor.clipboard = clipboard;
+    println!("Copied part!");
+    Ok(())
+}
+
+fn paste_ui(editor: &mut SongEditor) -> Result<(), &'static str> {
+    let clipboard = editor.clipboard.clone();
+    println!("Which song would you like to paste into?");
+    let (_, song) = select_song_ui(editor)?;
+    match clipboard {
+        Clipboard::Notes(_) => {
+            println!("Which part would you like to paste the notes into?");
+            let (_, part) = song_cli::select_part_ui(song)?;
+            let (pasted, left_out) = clipboard.paste_notes(part, prompt_f32("beat to paste the first note on: ")?)?;
+            println!("Pasted {pasted} notes!");
+            if left_out > 0 {
+                println!("Left out {left_out} notes that would overlap notes already in the part");
+            }
+        }
+        _ => println!("Pasted {} parts!", clipboard.paste_parts(song)?),
+    }
+    Ok(())
+}
+
 fn show_songs_ui(editor: &mut SongEditor) {
     println!("Your Songs:");
     for (index, song) in editor.loaded_songs.iter().enumerate() {
         println!("\t{}. {}", index + 1, song.name);
     }
+    match &editor.clipboard {
+        Clipboard::Empty => {}
+        Clipboard::Notes(notes) => println!("Clipboard: {} notes", notes.len()),
+        Clipboard::Parts(parts) => println!("Clipboard: {} parts", parts.len()),
+    }
 }
 
 fn select_song_ui<'a>(editor: &'a mut SongEditor) -> Result<(usize, &'a mut Song), &'static str> {
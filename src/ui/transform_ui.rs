@@ -0,0 +1,56 @@
+use crate::music::{frequency_from_name, interval_semitones, Key, Transform};
+
+use super::input_ui::{prompt_f32, prompt_index, prompt_line};
+
+/// Asks which transform to make and its settings. Diatonic transposition uses `key`, or asks for one when there is none
+pub fn prompt_transform(key: Option<Key>) -> Result<Transform, &'static str> {
+    println!("Which transformation?");
+    println!("\t1. Transpose by semitones");
+    println!("\t2. Transpose by interval");
+    println!("\t3. Transpose by scale steps");
+    println!("\t4. Invert around a pitch");
+    println!("\t5. Retrograde");
+    println!("\t6. Stretch by a factor");
+    println!("\t7. Shift by beats");
+    println!("\t8. Quantize");
+    println!("\t9. Swing");
+    println!("\t10. Humanize");
+    match prompt_index("transformation: ", 10)? {
+        0 => Ok(Transform::Transpose(prompt_f32("semitones (negative goes down): ")?)),
+        1 => {
+            let interval = prompt_line("interval like M3, P5 or -m2: ")?;
+            let semitones = interval_semitones(&interval).ok_or("Not an interval!")?;
+            Ok(Transform::Transpose(semitones as f32))
+        }
+        2 => {
+            let key = match key {
+                Some(key) => key,
+                None => Key::parse(&prompt_line("key like G, F#m or Bb minor: ")?).ok_or("Not a key!")?,
+            };
+            match prompt_line("scale steps (negative goes down): ")?.parse::<i32>() {
+                Ok(steps) => Ok(Transform::TransposeDiatonic { key, steps }),
+                Err(_) => Err("failed to parse user input as a whole number!"),
+            }
+        }
+        3 => {
+            let pivot = frequency_from_name(&prompt_line("pivot pitch like C4: ")?).ok_or("Not a pitch!")?;
+            Ok(Transform::Invert { pivot })
+        }
+        4 => Ok(Transform::Retrograde),
+        5 => Ok(Transform::Stretch(prompt_f32("factor (2 is twice as long, 0.5 half as long): ")?)),
+        6 => Ok(Transform::Shift(prompt_f32("beats (negative moves earlier): ")?)),
+        7 => Ok(Transform::Quantize {
+            grid: prompt_f32("grid in beats (0.25 for sixteenth notes): ")?,
+            strength: prompt_f32("strength in percent: ")? / 100.0,
+        }),
+        8 => Ok(Transform::Swing {
+            grid: prompt_f32("grid in beats (0.5 for eighth notes): ")?,
+            ratio: prompt_f32("swing in percent (50 is straight, 66 is triplet swing): ")? / 100.0,
+        }),
+        _ => Ok(Transform::Humanize {
+            timing: prompt_f32("most beats a note may move: ")?,
+            volume: prompt_f32("most a note's volume may change: ")?,
+            seed: prompt_line("seed (the same seed gives the same result): ")?.parse::<u64>().map_err(|_| "failed to parse user input as a whole number!")?,
+        }),
+    }
+}
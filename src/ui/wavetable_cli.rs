@@ -0,0 +1,81 @@
+use super::{choice_ui::{self, Choice}, input_ui::{prompt_envelope, prompt_f32, prompt_f32_list, prompt_index}};
+use crate::music::{Frame, WavetableSynth};
+
+pub fn edit_wavetable_ui(synth: &mut WavetableSynth) {
+    let choices = vec![
+        Choice::new("Add Harmonic Frame".to_string(), Box::from(add_harmonic_frame_ui)),
+        Choice::new("Add Drawn Frame".to_string(), Box::from(add_drawn_frame_ui)),
+        Choice::new("Delete Frame".to_string(), Box::from(delete_frame_ui)),
+        Choice::new("Change Morph Time".to_string(), Box::from(change_morph_time_ui)),
+        Choice::new("Change Envelope".to_string(), Box::from(change_envelope_ui)),
+    ];
+    loop {
+        println!("Wavetable editor");
+        print!("{synth}");
+        let result = choice_ui::ui_offer_choices(&choices, synth);
+        if let Err(err) = result {
+            println!("{err}");
+            continue
+        }
+        if let Some(res) = result.unwrap() {
+            if let Err(err) = res {
+                println!("{err}");
+            }
+        }
+        else {
+            break
+        }
+    }
+    println!("You have left wavetable editor!");
+}
+
+/// Asks for the harmonic amplitudes of an additive frame
+pub fn prompt_harmonics() -> Result<Frame, &'static str> {
+    println!("Write the amplitude of each harmonic starting at the fundamental e.g. 1 0.5 0.33 0.25");
+    Ok(Frame::Harmonics(prompt_f32_list("harmonics: ")?))
+}
+
+/// Asks for the samples of a drawn single cycle frame
+pub fn prompt_drawn_frame() -> Result<Frame, &'static str> {
+    println!("Write one cycle of the waveform as values in range [-1, 1] e.g. 0 1 0.5 0 -0.5 -1");
+    Ok(Frame::Samples(prompt_f32_list("samples: ")?))
+}
+
+fn add_frame(synth: &mut WavetableSynth, frame: Frame) -> Result<(), &'static str> {
+    let mut frames = synth.frames().to_vec();
+    frames.push(frame);
+    synth.set_frames(frames)?;
+    println!("Added frame!");
+    Ok(())
+}
+
+fn add_harmonic_frame_ui(synth: &mut WavetableSynth) -> Result<(), &'static str> {
+    let frame = prompt_harmonics()?;
+    add_frame(synth, frame)
+}
+
+fn add_drawn_frame_ui(synth: &mut WavetableSynth) -> Result<(), &'static str> {
+    let frame = prompt_drawn_frame()?;
+    add_frame(synth, frame)
+}
+
+fn delete_frame_ui(synth: &mut WavetableSynth) -> Result<(), &'static str> {
+    let index = prompt_index("Select a frame by number: ", synth.frames().len())?;
+    let mut frames = synth.frames().to_vec();
+    frames.remove(index);
+    synth.set_frames(frames)?;
+    println!("Deleted frame!");
+    Ok(())
+}
+
+fn change_morph_time_ui(synth: &mut WavetableSynth) -> Result<(), &'static str> {
+    synth.morph_time = prompt_f32("seconds to morph from the first to the last frame: ")?;
+    println!("Changed morph time to {}s!", synth.morph_time);
+    Ok(())
+}
+
+fn change_envelope_ui(synth: &mut WavetableSynth) -> Result<(), &'static str> {
+    synth.envelope = prompt_envelope()?;
+    println!("Changed envelope to {}!", synth.envelope);
+    Ok(())
+}
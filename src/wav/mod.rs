@@ -1,5 +1,10 @@
 pub const MAX_AMPLITUDE: i16 = i16::MAX;
 
+// turns a sample in the range [-1, 1] into a 16 bit sample, clipping anything outside the range
+pub fn quantize(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * MAX_AMPLITUDE as f32) as i16
+}
+
 const BITS_PER_SAMPLE: u16 = 16;
 
 // WavHeader Struct contains header information for wav file
@@ -56,12 +61,14 @@ impl WavHeader {
 pub struct WavOptions {
     pub sample_rate: u32,
     pub num_channels: u16,
-    pub bits_per_sample: u16
+    pub bits_per_sample: u16,
+    // songs are rendered at sample_rate * oversampling and filtered back down. 1 turns it off
+    pub oversampling: u32
 }
 
 impl WavOptions {
     pub fn new(sample_rate:u32, num_channels: u16, bits_per_sample: u16) -> Self {
-        WavOptions { sample_rate, num_channels, bits_per_sample }
+        WavOptions { sample_rate, num_channels, bits_per_sample, oversampling: 1 }
     }
 }
 
@@ -70,7 +77,8 @@ impl Default for WavOptions {
         WavOptions {
             sample_rate: 44100,
             num_channels: 1,
-            bits_per_sample: 16
+            bits_per_sample: 16,
+            oversampling: 1
         }
     }
 }
\ No newline at end of file